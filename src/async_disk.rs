@@ -0,0 +1,673 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use serde_json::Value as Json;
+use tokio::sync::mpsc;
+
+use crate::embedding::{Embedder, IdfWeights};
+use crate::errors::DiskError;
+use crate::models::{
+    Chunk, Document, HybridWeights, IndexConfig, ModelSignature, OwnedNewChunk, OwnedQueryVector,
+    ReembedOptions, ReembedReport, SearchOptions, SearchResult, SignatureInfo,
+};
+use crate::shared::{PoolConfig, SharedDisk};
+use crate::IdentityDisk;
+
+/// Sets its flag when dropped, telling a queued blocking call not to start.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+/// Runs `f` on tokio's blocking pool. If the returned future is dropped
+/// before `f` starts, `f` never runs.
+async fn run_blocking<R, F>(f: F) -> Result<R, DiskError>
+where
+    F: FnOnce() -> Result<R, DiskError> + Send + 'static,
+    R: Send + 'static,
+{
+    run_cancellable(|_| f()).await
+}
+
+/// `run_blocking`, passing `f` the flag that dropping the future sets so a
+/// long call can also stop once it has started.
+async fn run_cancellable<R, F>(f: F) -> Result<R, DiskError>
+where
+    F: FnOnce(&AtomicBool) -> Result<R, DiskError> + Send + 'static,
+    R: Send + 'static,
+{
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let _guard = CancelOnDrop(Arc::clone(&cancelled));
+    let task = tokio::task::spawn_blocking(move || (!cancelled.load(Ordering::Acquire)).then(|| f(&cancelled)));
+    // The flag is only set once this future is dropped, so `None` should never
+    // be seen here.
+    task.await?.unwrap_or(Err(DiskError::Cancelled))
+}
+
+/// An async handle to an Identity Disk for use from tokio.
+///
+/// Every call runs on tokio's blocking pool, so SQLite and HNSW work never
+/// stalls the runtime. The handle wraps a [`SharedDisk`]: it is cheap to clone,
+/// reads run concurrently and writes are serialized.
+///
+/// Dropping a call's future before the blocking pool picks the call up
+/// cancels it. A call that has already started runs to completion in the
+/// background, except for re-embedding runs, which stop after their current
+/// batch; each write is transactional, so it is applied entirely or not at
+/// all.
+#[derive(Clone)]
+pub struct AsyncIdentityDisk {
+    disk: SharedDisk,
+}
+
+impl From<SharedDisk> for AsyncIdentityDisk {
+    fn from(disk: SharedDisk) -> Self {
+        Self { disk }
+    }
+}
+
+impl AsyncIdentityDisk {
+    /// Creates a new, empty disk. See [`IdentityDisk::create`].
+    pub async fn create<P: AsRef<Path>>(path: P, model_signature: &str) -> Result<Self, DiskError> {
+        let path = path.as_ref().to_path_buf();
+        let model_signature = model_signature.to_string();
+        let disk = run_blocking(move || {
+            SharedDisk::new(
+                IdentityDisk::create(path, &model_signature)?,
+                PoolConfig::default(),
+            )
+        })
+        .await?;
+        Ok(Self { disk })
+    }
+
+    /// Opens an existing disk. See [`IdentityDisk::open`].
+    pub async fn open<P: AsRef<Path>>(path: P, model_signature: &str) -> Result<Self, DiskError> {
+        let path = path.as_ref().to_path_buf();
+        let model_signature = model_signature.to_string();
+        let disk = run_blocking(move || SharedDisk::open(path, &model_signature)).await?;
+        Ok(Self { disk })
+    }
+
+    /// Opens an existing disk with the model signature recorded when it was
+    /// created. See [`IdentityDisk::open_default`].
+    pub async fn open_default<P: AsRef<Path>>(path: P) -> Result<Self, DiskError> {
+        let path = path.as_ref().to_path_buf();
+        let disk = run_blocking(move || SharedDisk::open_default(path)).await?;
+        Ok(Self { disk })
+    }
+
+    /// The blocking handle this one wraps.
+    pub fn shared(&self) -> &SharedDisk {
+        &self.disk
+    }
+
+    /// Runs `f` with the shared handle on the blocking pool.
+    async fn run<R, F>(&self, f: F) -> Result<R, DiskError>
+    where
+        F: FnOnce(&SharedDisk) -> Result<R, DiskError> + Send + 'static,
+        R: Send + 'static,
+    {
+        let disk = self.disk.clone();
+        run_blocking(move || f(&disk)).await
+    }
+
+    /// Runs `f` against a read-only view of the disk on the blocking pool. See
+    /// [`SharedDisk::read`].
+    pub async fn read<R, F>(&self, f: F) -> Result<R, DiskError>
+    where
+        F: FnOnce(&IdentityDisk) -> Result<R, DiskError> + Send + 'static,
+        R: Send + 'static,
+    {
+        self.run(move |disk| disk.read(f)).await
+    }
+
+    /// See [`IdentityDisk::model_signature`].
+    pub async fn model_signature(&self) -> Result<ModelSignature, DiskError> {
+        self.run(|disk| disk.model_signature()).await
+    }
+
+    /// See [`IdentityDisk::index_config`].
+    pub async fn index_config(&self) -> Result<IndexConfig, DiskError> {
+        self.run(|disk| disk.index_config()).await
+    }
+
+    // --- Reads ---
+
+    /// See [`IdentityDisk::search`].
+    pub async fn search(
+        &self,
+        query_vector: OwnedQueryVector,
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>, DiskError> {
+        self.run(move |disk| disk.search(query_vector.as_query(), top_k))
+            .await
+    }
+
+    /// See [`IdentityDisk::search_with_options`].
+    pub async fn search_with_options(
+        &self,
+        query_vector: OwnedQueryVector,
+        top_k: usize,
+        options: SearchOptions,
+    ) -> Result<Vec<SearchResult>, DiskError> {
+        self.run(move |disk| disk.search_with_options(query_vector.as_query(), top_k, &options))
+            .await
+    }
+
+    /// See [`IdentityDisk::keyword_search`].
+    pub async fn keyword_search(
+        &self,
+        query: &str,
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>, DiskError> {
+        let query = query.to_string();
+        self.run(move |disk| disk.keyword_search(&query, top_k))
+            .await
+    }
+
+    /// See [`IdentityDisk::hybrid_search`].
+    pub async fn hybrid_search(
+        &self,
+        text: &str,
+        vector: OwnedQueryVector,
+        top_k: usize,
+        weights: HybridWeights,
+    ) -> Result<Vec<SearchResult>, DiskError> {
+        let text = text.to_string();
+        self.run(move |disk| disk.hybrid_search(&text, vector.as_query(), top_k, &weights))
+            .await
+    }
+
+    /// See [`IdentityDisk::get_chunks`]. [`Self::stream_chunks`] avoids
+    /// holding every chunk in memory at once.
+    pub async fn get_chunks(&self) -> Result<Vec<Chunk>, DiskError> {
+        self.run(|disk| disk.get_chunks()).await
+    }
+
+    /// Streams every chunk in `chunk_id` order, reading `page_size` chunks
+    /// (at least one) at a time on the blocking pool.
+    ///
+    /// Reading stays at most one page ahead of the consumer and stops once the
+    /// stream is dropped. Each page is a separate read, so writes made while
+    /// streaming may or may not be seen. A failed read ends the stream after
+    /// yielding its error.
+    ///
+    /// # Panics
+    /// Panics if called outside a tokio runtime.
+    pub fn stream_chunks(&self, page_size: usize) -> ChunkStream {
+        let page_size = page_size.max(1);
+        let (tx, rx) = mpsc::channel(page_size);
+        let disk = self.disk.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut after: Option<String> = None;
+            loop {
+                let page = match disk.read(|disk| disk.get_chunks_page(after.as_deref(), page_size))
+                {
+                    Ok(page) => page,
+                    Err(e) => {
+                        let _ = tx.blocking_send(Err(e));
+                        return;
+                    }
+                };
+                let is_last = page.len() < page_size;
+                after = page.last().map(|chunk| chunk.chunk_id.clone());
+                for chunk in page {
+                    if tx.blocking_send(Ok(chunk)).is_err() {
+                        // The stream was dropped.
+                        return;
+                    }
+                }
+                if is_last {
+                    return;
+                }
+            }
+        });
+        ChunkStream { rx }
+    }
+
+    /// See [`IdentityDisk::list_documents`].
+    pub async fn list_documents(&self) -> Result<Vec<Document>, DiskError> {
+        self.run(|disk| disk.list_documents()).await
+    }
+
+    /// See [`IdentityDisk::get_document`].
+    pub async fn get_document(&self, document_id: &str) -> Result<Document, DiskError> {
+        let document_id = document_id.to_string();
+        self.run(move |disk| disk.get_document(&document_id)).await
+    }
+
+    /// See [`IdentityDisk::get_document_chunks`].
+    pub async fn get_document_chunks(&self, document_id: &str) -> Result<Vec<Chunk>, DiskError> {
+        let document_id = document_id.to_string();
+        self.run(move |disk| disk.get_document_chunks(&document_id))
+            .await
+    }
+
+    /// See [`IdentityDisk::list_model_signatures`].
+    pub async fn list_model_signatures(&self) -> Result<Vec<SignatureInfo>, DiskError> {
+        self.run(|disk| disk.list_model_signatures()).await
+    }
+
+    /// See [`IdentityDisk::get_manifest`].
+    pub async fn get_manifest(&self) -> Result<BTreeMap<String, Json>, DiskError> {
+        self.run(|disk| disk.get_manifest()).await
+    }
+
+    // --- Writes ---
+
+    /// See [`IdentityDisk::add_chunk`].
+    pub async fn add_chunk(
+        &self,
+        content: &str,
+        embedding: OwnedQueryVector,
+        metadata: Option<Json>,
+    ) -> Result<String, DiskError> {
+        let content = content.to_string();
+        self.run(move |disk| disk.add_chunk(&content, embedding.as_query(), metadata))
+            .await
+    }
+
+    /// See [`IdentityDisk::add_chunks`].
+    pub async fn add_chunks(&self, chunks: Vec<OwnedNewChunk>) -> Result<Vec<String>, DiskError> {
+        self.run(move |disk| {
+            let chunks: Vec<_> = chunks.iter().map(OwnedNewChunk::as_new_chunk).collect();
+            disk.add_chunks(&chunks)
+        })
+        .await
+    }
+
+    /// See [`IdentityDisk::add_document`].
+    pub async fn add_document(
+        &self,
+        uri: &str,
+        title: Option<&str>,
+        content: &str,
+        chunks: Vec<OwnedNewChunk>,
+    ) -> Result<String, DiskError> {
+        let uri = uri.to_string();
+        let title = title.map(str::to_string);
+        let content = content.to_string();
+        self.run(move |disk| {
+            let chunks: Vec<_> = chunks.iter().map(OwnedNewChunk::as_new_chunk).collect();
+            disk.add_document(&uri, title.as_deref(), &content, &chunks)
+        })
+        .await
+    }
+
+    /// See [`IdentityDisk::update_chunk`].
+    pub async fn update_chunk(
+        &self,
+        chunk_id: &str,
+        content: &str,
+        embedding: OwnedQueryVector,
+        metadata: Option<Json>,
+    ) -> Result<(), DiskError> {
+        let chunk_id = chunk_id.to_string();
+        let content = content.to_string();
+        self.run(move |disk| disk.update_chunk(&chunk_id, &content, embedding.as_query(), metadata))
+            .await
+    }
+
+    /// See [`IdentityDisk::update_chunk_metadata`].
+    pub async fn update_chunk_metadata(
+        &self,
+        chunk_id: &str,
+        new_metadata: Json,
+    ) -> Result<(), DiskError> {
+        let chunk_id = chunk_id.to_string();
+        self.run(move |disk| disk.update_chunk_metadata(&chunk_id, new_metadata))
+            .await
+    }
+
+    /// See [`IdentityDisk::delete_chunk`].
+    pub async fn delete_chunk(&self, chunk_id: &str) -> Result<(), DiskError> {
+        let chunk_id = chunk_id.to_string();
+        self.run(move |disk| disk.delete_chunk(&chunk_id)).await
+    }
+
+    /// See [`IdentityDisk::delete_chunks`].
+    pub async fn delete_chunks<F>(&self, filter: F) -> Result<usize, DiskError>
+    where
+        F: Fn(&Chunk) -> bool + Send + 'static,
+    {
+        self.run(move |disk| disk.delete_chunks(filter)).await
+    }
+
+    /// See [`IdentityDisk::delete_document`].
+    pub async fn delete_document(&self, document_id: &str) -> Result<usize, DiskError> {
+        let document_id = document_id.to_string();
+        self.run(move |disk| disk.delete_document(&document_id))
+            .await
+    }
+
+    /// See [`IdentityDisk::add_embedding`].
+    pub async fn add_embedding(
+        &self,
+        chunk_id: &str,
+        model_signature: &str,
+        embedding: OwnedQueryVector,
+    ) -> Result<(), DiskError> {
+        let chunk_id = chunk_id.to_string();
+        let model_signature = model_signature.to_string();
+        self.run(move |disk| disk.add_embedding(&chunk_id, &model_signature, embedding.as_query()))
+            .await
+    }
+
+    /// See [`IdentityDisk::reembed`]. Dropping the future stops the run after
+    /// the batch in progress is stored, and the next run resumes from there.
+    pub async fn reembed(
+        &self,
+        target_signature: &str,
+        embedder: Arc<dyn Embedder>,
+        batch_size: usize,
+    ) -> Result<ReembedReport, DiskError> {
+        self.reembed_with_options(target_signature, embedder, ReembedOptions::new(batch_size))
+            .await
+    }
+
+    /// See [`IdentityDisk::reembed_with_options`] and [`reembed`](Self::reembed).
+    pub async fn reembed_with_options(
+        &self,
+        target_signature: &str,
+        embedder: Arc<dyn Embedder>,
+        options: ReembedOptions,
+    ) -> Result<ReembedReport, DiskError> {
+        let target_signature = target_signature.to_string();
+        let disk = self.disk.clone();
+        run_cancellable(move |cancelled| {
+            disk.reembed_until_cancelled(&target_signature, embedder.as_ref(), &options, cancelled)
+        })
+        .await
+    }
+
+    /// See [`IdentityDisk::persist_index`].
+    pub async fn persist_index(&self) -> Result<(), DiskError> {
+        self.run(|disk| disk.persist_index()).await
+    }
+
+    /// See [`IdentityDisk::calibrate_int8`].
+    pub async fn calibrate_int8(&self, samples: Vec<Vec<f32>>) -> Result<(), DiskError> {
+        self.run(move |disk| {
+            let samples: Vec<&[f32]> = samples.iter().map(Vec::as_slice).collect();
+            disk.calibrate_int8(&samples)
+        })
+        .await
+    }
+
+    /// See [`IdentityDisk::set_hashing_idf`].
+    pub async fn set_hashing_idf(&self, idf: IdfWeights) -> Result<(), DiskError> {
+        self.run(move |disk| disk.set_hashing_idf(&idf)).await
+    }
+
+    /// See [`IdentityDisk::set_manifest_value`].
+    pub async fn set_manifest_value(&self, key: &str, value: Json) -> Result<(), DiskError> {
+        let key = key.to_string();
+        self.run(move |disk| disk.set_manifest_value(&key, value))
+            .await
+    }
+
+    /// See [`IdentityDisk::remove_manifest_value`].
+    pub async fn remove_manifest_value(&self, key: &str) -> Result<(), DiskError> {
+        let key = key.to_string();
+        self.run(move |disk| disk.remove_manifest_value(&key)).await
+    }
+
+    /// See [`IdentityDisk::set_default_model_signature`].
+    pub async fn set_default_model_signature(
+        &self,
+        model_signature: &str,
+    ) -> Result<(), DiskError> {
+        let model_signature = model_signature.to_string();
+        self.run(move |disk| disk.set_default_model_signature(&model_signature))
+            .await
+    }
+
+    /// See [`IdentityDisk::drop_model_signature`].
+    pub async fn drop_model_signature(&self, model_signature: &str) -> Result<usize, DiskError> {
+        let model_signature = model_signature.to_string();
+        self.run(move |disk| disk.drop_model_signature(&model_signature))
+            .await
+    }
+
+    /// See [`SharedDisk::switch_model_signature`].
+    pub async fn switch_model_signature(&self, model_signature: &str) -> Result<(), DiskError> {
+        let model_signature = model_signature.to_string();
+        self.run(move |disk| disk.switch_model_signature(&model_signature))
+            .await
+    }
+
+    /// See [`IdentityDisk::set_exact_search_threshold`].
+    pub async fn set_exact_search_threshold(&self, max_vectors: usize) -> Result<(), DiskError> {
+        self.run(move |disk| disk.set_exact_search_threshold(max_vectors))
+            .await
+    }
+}
+
+/// The chunks of a disk, read page by page on the blocking pool. Returned by
+/// [`AsyncIdentityDisk::stream_chunks`].
+pub struct ChunkStream {
+    rx: mpsc::Receiver<Result<Chunk, DiskError>>,
+}
+
+impl Stream for ChunkStream {
+    type Item = Result<Chunk, DiskError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{mpsc as std_mpsc, Mutex};
+    use std::time::Duration;
+
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::embedding::HashingEmbedder;
+
+    const SIGNATURE: &str = "local/hashing-8_fp32";
+
+    fn new_chunks(texts: &[&str]) -> Vec<OwnedNewChunk> {
+        let embedder = HashingEmbedder::new(8).unwrap();
+        texts
+            .iter()
+            .zip(embedder.embed(texts).unwrap())
+            .map(|(content, embedding)| OwnedNewChunk {
+                content: content.to_string(),
+                embedding: OwnedQueryVector::F32(embedding),
+                metadata: None,
+            })
+            .collect()
+    }
+
+    /// A runtime with a single blocking thread, so calls queue behind each other.
+    fn single_blocking_thread() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .max_blocking_threads(1)
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn async_calls_read_their_own_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        let disk = AsyncIdentityDisk::create(&path, SIGNATURE).await.unwrap();
+        let chunks = new_chunks(&["apples and pears", "a red bicycle", "pears in syrup"]);
+        let query = chunks[2].embedding.clone();
+        let chunk_ids = disk.add_chunks(chunks).await.unwrap();
+
+        let results = disk.search(query.clone(), 1).await.unwrap();
+        assert_eq!(results[0].chunk.chunk_id, chunk_ids[2]);
+        let results = disk.keyword_search("bicycle", 3).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.chunk_id, chunk_ids[1]);
+
+        disk.delete_chunk(&chunk_ids[2]).await.unwrap();
+        let results = disk.search(query.clone(), 3).await.unwrap();
+        assert!(results.iter().all(|r| r.chunk.chunk_id != chunk_ids[2]));
+
+        // Clones share the disk, and reopening sees what was written.
+        assert_eq!(disk.clone().get_chunks().await.unwrap().len(), 2);
+        drop(disk);
+        let disk = AsyncIdentityDisk::open_default(&path).await.unwrap();
+        assert_eq!(disk.model_signature().await.unwrap().to_string(), SIGNATURE);
+        assert_eq!(disk.get_chunks().await.unwrap().len(), 2);
+        let error = AsyncIdentityDisk::open(&path, "not a signature").await.err().unwrap();
+        assert!(matches!(error, DiskError::InvalidSignature(_)), "{error}");
+    }
+
+    #[tokio::test]
+    async fn chunk_streams_yield_every_chunk_once_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let disk = AsyncIdentityDisk::create(dir.path().join("disk.idz"), SIGNATURE).await.unwrap();
+        let texts: Vec<String> = (0..10).map(|i| format!("chunk {}", i)).collect();
+        let mut chunk_ids = disk
+            .add_chunks(new_chunks(&texts.iter().map(String::as_str).collect::<Vec<_>>()))
+            .await
+            .unwrap();
+        chunk_ids.sort();
+
+        // Page sizes that divide the chunks evenly, unevenly, exceed them, and
+        // the zero that is raised to one.
+        for page_size in [5, 3, 20, 0] {
+            let streamed: Vec<String> = disk
+                .stream_chunks(page_size)
+                .map(|chunk| chunk.unwrap().chunk_id)
+                .collect()
+                .await;
+            assert_eq!(streamed, chunk_ids, "page size {page_size}");
+        }
+    }
+
+    #[test]
+    fn dropped_chunk_streams_stop_reading() {
+        let runtime = single_blocking_thread();
+        runtime.block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let disk = AsyncIdentityDisk::create(dir.path().join("disk.idz"), SIGNATURE).await.unwrap();
+            disk.add_chunks(new_chunks(&["a", "b", "c", "d", "e"])).await.unwrap();
+
+            let mut stream = disk.stream_chunks(1);
+            stream.next().await.unwrap().unwrap();
+            drop(stream);
+
+            // The reader holds the only blocking thread until it sees the drop.
+            let chunks = tokio::time::timeout(Duration::from_secs(5), disk.get_chunks()).await;
+            assert_eq!(chunks.expect("the stream's reader kept running").unwrap().len(), 5);
+        });
+    }
+
+    #[test]
+    fn dropped_calls_that_have_not_started_never_run() {
+        let runtime = single_blocking_thread();
+        runtime.block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let disk = AsyncIdentityDisk::create(dir.path().join("disk.idz"), SIGNATURE).await.unwrap();
+
+            // Occupy the only blocking thread until `release` is sent.
+            let (release, released) = std_mpsc::channel::<()>();
+            let blocker = tokio::spawn({
+                let disk = disk.clone();
+                async move {
+                    disk.read(move |_| {
+                        released.recv().unwrap();
+                        Ok(())
+                    })
+                    .await
+                }
+            });
+            tokio::task::yield_now().await;
+
+            // The write is queued behind the blocker and dropped on timeout.
+            let write = disk.add_chunks(new_chunks(&["never written"]));
+            assert!(tokio::time::timeout(Duration::from_millis(50), write).await.is_err());
+
+            release.send(()).unwrap();
+            blocker.await.unwrap().unwrap();
+            assert!(disk.get_chunks().await.unwrap().is_empty());
+        });
+    }
+
+    /// Hashing embeddings that report each call and hold the second one until
+    /// the test releases it.
+    struct GatedEmbedder {
+        inner: HashingEmbedder,
+        calls: AtomicUsize,
+        called: Mutex<std_mpsc::Sender<usize>>,
+        release: Mutex<std_mpsc::Receiver<()>>,
+    }
+
+    impl Embedder for GatedEmbedder {
+        fn model(&self) -> &str {
+            self.inner.model()
+        }
+
+        fn dim(&self) -> usize {
+            self.inner.dim()
+        }
+
+        fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, DiskError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            self.called.lock().unwrap().send(call).unwrap();
+            if call == 2 {
+                self.release.lock().unwrap().recv_timeout(Duration::from_secs(5)).unwrap();
+            }
+            self.inner.embed(texts)
+        }
+    }
+
+    #[tokio::test]
+    async fn dropped_reembed_runs_stop_after_their_batch_and_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let disk = AsyncIdentityDisk::create(dir.path().join("disk.idz"), SIGNATURE).await.unwrap();
+        let texts: Vec<String> = (0..6).map(|i| format!("chunk {}", i)).collect();
+        disk.add_chunks(new_chunks(&texts.iter().map(String::as_str).collect::<Vec<_>>())).await.unwrap();
+        let target = "local/hashing-8_fp16";
+        let (called, calls) = std_mpsc::channel();
+        let (release, released) = std_mpsc::channel();
+        let embedder = Arc::new(GatedEmbedder {
+            inner: HashingEmbedder::new(8).unwrap(),
+            calls: AtomicUsize::new(0),
+            called: Mutex::new(called),
+            release: Mutex::new(released),
+        });
+
+        // Drop the run while its second batch is being embedded.
+        let second_call = tokio::task::spawn_blocking(move || {
+            assert_eq!(calls.recv().unwrap(), 1);
+            assert_eq!(calls.recv().unwrap(), 2);
+            calls
+        });
+        let calls = tokio::select! {
+            report = disk.reembed(target, embedder.clone(), 2) => panic!("the run was not dropped: {report:?}"),
+            calls = second_call => calls.unwrap(),
+        };
+        release.send(()).unwrap();
+
+        // The second batch is stored, but no third is embedded.
+        assert!(calls.recv_timeout(Duration::from_secs(1)).is_err());
+        let vectors = |signatures: Vec<SignatureInfo>| {
+            signatures.into_iter().find(|info| info.model_signature == target).map(|info| info.vectors)
+        };
+        assert_eq!(vectors(disk.list_model_signatures().await.unwrap()), Some(4));
+
+        let report = disk.reembed(target, Arc::new(HashingEmbedder::new(8).unwrap()), 2).await.unwrap();
+        assert!(report.resumed);
+        assert_eq!(report.embedded, 2);
+        assert!(report.is_complete());
+    }
+}
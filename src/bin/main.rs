@@ -1,11 +1,16 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use idz::{IdentityDisk, models::{QueryVector, Chunk, SearchResult}}; // Updated idz imports, removed DiskError
+use idz::{IdentityDisk, models::{QueryVector, Chunk, NewChunk, ReembedOptions, SearchResult, SignatureInfo}}; // Updated idz imports, removed DiskError
+use idz::chunking::{
+    Chunker, FixedWindowChunker, LineChunker, ParagraphChunker, RecursiveChunker, SentenceChunker,
+};
+use idz::embedding::{Embedder, OpenAiEmbedder};
+use idz::errors::DiskError;
 use ratatui::{
     backend::{CrosstermBackend},
     layout::{Constraint, Direction, Layout, Rect},
@@ -16,6 +21,7 @@ use ratatui::{
 use std::fs;
 use std::io;
 use std::path::PathBuf;
+use std::time::Duration;
 // use std::ops::Deref; // No longer needed
 
 #[derive(Parser)]
@@ -35,104 +41,317 @@ enum Commands {
         output: PathBuf,
         /// Text files to process
         files: Vec<PathBuf>,
-        /// Embedding model signature (e.g., "openai/text-embedding-ada-002_fp32")
-        #[arg(short, long, default_value = "openai/text-embedding-ada-002_fp32")]
+        /// Embedding model signature (e.g., "openai/text-embedding-ada-002-1536_fp32")
+        #[arg(short, long, default_value = "local/hashing-512_fp32")]
         model_signature: String,
+        /// How to split each file into chunks
+        #[arg(long, value_enum, default_value_t = ChunkStrategy::Line)]
+        chunker: ChunkStrategy,
+        /// Maximum chunk length in characters, for the fixed and recursive chunkers
+        #[arg(long, default_value_t = 1000)]
+        chunk_size: usize,
+        /// Characters shared by consecutive chunks, for the fixed chunker [default: a fifth of the chunk size]
+        #[arg(long)]
+        chunk_overlap: Option<usize>,
+        /// Weight the hashing embedder by IDF fitted on the new chunks
+        #[arg(long)]
+        tf_idf: bool,
+        #[command(flatten)]
+        embedder: EmbedderArgs,
     },
     /// Explore an existing .idz file with TUI
     Explore {
         /// .idz file to explore
         file: PathBuf,
-        /// Model signature to load for searching (e.g., "openai/text-embedding-ada-002_fp32")
+        /// Model signature to load for searching [default: the one the disk was created with]
         #[arg(short, long)]
-        model_signature: String,
+        model_signature: Option<String>,
+        #[command(flatten)]
+        embedder: EmbedderArgs,
     },
+    /// Embed every chunk of an existing .idz file under another model signature
+    Reembed {
+        /// .idz file to re-embed
+        file: PathBuf,
+        /// Model signature to embed into (e.g., "local/hashing-1024_fp32")
+        #[arg(short, long)]
+        target_signature: String,
+        /// Chunks embedded and committed together; an interrupted run resumes after the last commit
+        #[arg(long, default_value_t = 64)]
+        batch_size: usize,
+        /// Once every chunk is embedded, make the target the default signature and drop the old default
+        #[arg(long)]
+        drop_old: bool,
+        #[command(flatten)]
+        embedder: EmbedderArgs,
+    },
+}
+
+#[derive(Args, Clone)]
+struct EmbedderArgs {
+    /// Where chunk and query embeddings come from
+    #[arg(long, value_enum, default_value_t = EmbedderKind::Hashing)]
+    embedder: EmbedderKind,
+    /// Base URL of an OpenAI-compatible embeddings API; the key is read from OPENAI_API_KEY
+    #[arg(long, default_value = OpenAiEmbedder::DEFAULT_BASE_URL)]
+    embedding_url: String,
+    /// Model to request [default: the model in the model signature]
+    #[arg(long)]
+    embedding_model: Option<String>,
+    /// Texts sent per embeddings request
+    #[arg(long, default_value_t = OpenAiEmbedder::DEFAULT_BATCH_SIZE)]
+    embedding_batch_size: usize,
+    /// Seconds before an embeddings request times out
+    #[arg(long, default_value_t = OpenAiEmbedder::DEFAULT_TIMEOUT.as_secs())]
+    embedding_timeout: u64,
+    /// How often a failed embeddings request is retried
+    #[arg(long, default_value_t = OpenAiEmbedder::DEFAULT_MAX_RETRIES)]
+    embedding_retries: u32,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum EmbedderKind {
+    /// Offline feature hashing of words and character n-grams; needs a "*/hashing-{dim}_*" signature
+    Hashing,
+    /// An OpenAI-compatible /v1/embeddings endpoint
+    Openai,
+}
+
+impl EmbedderArgs {
+    /// Builds the selected embedder and checks it against the disk's signature.
+    fn build(&self, disk: &IdentityDisk) -> Result<Box<dyn Embedder>> {
+        let signature = disk.model_signature();
+        let model = self.embedding_model.as_deref().unwrap_or(&signature.model);
+        let embedder: Box<dyn Embedder> = match self.embedder {
+            EmbedderKind::Hashing => Box::new(disk.hashing_embedder()?),
+            EmbedderKind::Openai => {
+                let mut embedder = OpenAiEmbedder::new(&self.embedding_url, model, signature.dim)
+                    .with_batch_size(self.embedding_batch_size)
+                    .with_timeout(Duration::from_secs(self.embedding_timeout))
+                    .with_retries(self.embedding_retries, OpenAiEmbedder::DEFAULT_RETRY_BACKOFF);
+                if let Ok(api_key) = std::env::var("OPENAI_API_KEY") {
+                    embedder = embedder.with_api_key(api_key);
+                }
+                Box::new(embedder)
+            }
+        };
+        if self.embedding_model.is_some() && matches!(self.embedder, EmbedderKind::Openai) {
+            // The signature's model is served under another name, so only the
+            // dimension can be checked here; responses are checked as they arrive.
+            if embedder.dim() != signature.dim {
+                return Err(DiskError::DimensionMismatch { expected: signature.dim, got: embedder.dim() }.into());
+            }
+        } else {
+            embedder.check_signature(signature)?;
+        }
+        Ok(embedder)
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ChunkStrategy {
+    /// One chunk per non-blank line
+    Line,
+    /// One chunk per blank-line separated paragraph
+    Paragraph,
+    /// One chunk per sentence
+    Sentence,
+    /// Fixed character windows with overlap
+    Fixed,
+    /// Split on paragraphs, lines, sentences and words until chunks fit
+    Recursive,
+}
+
+impl ChunkStrategy {
+    fn chunker(self, size: usize, overlap: usize) -> Result<Box<dyn Chunker>> {
+        Ok(match self {
+            ChunkStrategy::Line => Box::new(LineChunker),
+            ChunkStrategy::Paragraph => Box::new(ParagraphChunker),
+            ChunkStrategy::Sentence => Box::new(SentenceChunker),
+            ChunkStrategy::Fixed => Box::new(FixedWindowChunker::new(size, overlap)?),
+            ChunkStrategy::Recursive => Box::new(RecursiveChunker::new(size)?),
+        })
+    }
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Create { output, files, model_signature } => {
-            create_idz_file(output, files, &model_signature)?;
+        Commands::Create { output, files, model_signature, chunker, chunk_size, chunk_overlap, tf_idf, embedder } => {
+            let chunker = chunker.chunker(chunk_size, chunk_overlap.unwrap_or(chunk_size / 5))?;
+            create_idz_file(output, files, &model_signature, chunker.as_ref(), &embedder, tf_idf)?;
+        }
+        Commands::Explore { file, model_signature, embedder } => {
+            run_tui(file, model_signature.as_deref(), embedder)?;
         }
-        Commands::Explore { file, model_signature } => {
-            run_tui(file, &model_signature)?;
+        Commands::Reembed { file, target_signature, batch_size, drop_old, embedder } => {
+            reembed_idz_file(file, &target_signature, batch_size, drop_old, &embedder)?;
         }
     }
 
     Ok(())
 }
 
-fn create_idz_file(output: PathBuf, files: Vec<PathBuf>, model_signature: &str) -> Result<()> {
+fn create_idz_file(
+    output: PathBuf,
+    files: Vec<PathBuf>,
+    model_signature: &str,
+    chunker: &dyn Chunker,
+    embedder_args: &EmbedderArgs,
+    tf_idf: bool,
+) -> Result<()> {
     println!("Creating .idz file: {:?}", output);
     println!("Model Signature: {}", model_signature);
 
-    // Determine embedding dimension from model_signature (very basic parsing)
-    // E.g., "model-name-1536_fp32" -> 1536. This is a simplification.
-    // A more robust solution would involve a lookup or more structured signature.
-    let dim: usize = model_signature.split('_').next().unwrap_or("").split('-').last()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(1536); // Default if parsing fails
-
-    // Check if the model signature implies f32, otherwise this dummy generation is wrong
-    if !model_signature.contains("fp32") && model_signature.contains('_') {
-        // if there's a type specified and it's not fp32
-        eprintln!("Warning: Model signature '{}' does not explicitly state 'fp32'. Dummy f32 embeddings will be generated. This might be incorrect.", model_signature);
+    if tf_idf && !matches!(embedder_args.embedder, EmbedderKind::Hashing) {
+        anyhow::bail!("--tf-idf only applies to the hashing embedder");
     }
-
+    let contents = files
+        .iter()
+        .map(fs::read_to_string)
+        .collect::<io::Result<Vec<String>>>()?;
 
     let mut disk = IdentityDisk::create(&output, model_signature)?;
+    disk.set_manifest_value(
+        "creation_tool",
+        serde_json::json!(concat!("idz-cli ", env!("CARGO_PKG_VERSION"))),
+    )?;
+    if tf_idf {
+        let texts: Vec<&str> = contents
+            .iter()
+            .flat_map(|content| chunker.chunk(content))
+            .map(|chunk| chunk.text)
+            .collect();
+        let idf = disk.hashing_embedder()?.fit_idf(&texts);
+        disk.set_hashing_idf(&idf)?;
+        println!("Fitted IDF weights on {} chunks", idf.documents);
+    }
+    let embedder = embedder_args.build(&disk)?;
 
-    for file_path in files {
+    // Files that could not be embedded or added; the rest are still stored.
+    let mut failed = Vec::new();
+    for (file_path, content) in files.iter().zip(&contents) {
         println!("Processing file: {:?}", file_path);
-        let content = fs::read_to_string(&file_path)?;
-        
-        // Split content into chunks (simple line-based chunking for demo)
-        let chunks: Vec<&str> = content.lines().filter(|line| !line.trim().is_empty()).collect();
-        
-        for (i, chunk_content) in chunks.iter().enumerate() {
-            let meta = serde_json::json!({
-                "source_file": file_path.to_string_lossy(),
-                "chunk_index": i,
-                "char_count": chunk_content.len()
-            });
-            
-            // Generate dummy f32 embedding
-            let embedding_values: Vec<f32> = (0..dim).map(|_| rand::random::<f32>()).collect();
-            let query_vector = QueryVector::F32(&embedding_values);
-            
-            match disk.add_chunk(chunk_content, query_vector, Some(meta)) {
-                Ok(chunk_id) => println!("Added chunk {} from {:?}", chunk_id, file_path),
-                Err(e) => eprintln!("Failed to add chunk from {:?}: {}", file_path, e),
+
+        let chunks = chunker.chunk(content);
+
+        let texts: Vec<&str> = chunks.iter().map(|chunk| chunk.text).collect();
+        let embeddings = match embedder.embed(&texts) {
+            Ok(embeddings) => embeddings,
+            Err(e) => {
+                eprintln!("Failed to embed chunks from {:?}: {}", file_path, e);
+                failed.push(file_path);
+                continue;
+            }
+        };
+
+        let new_chunks: Vec<NewChunk> = chunks.iter().zip(&embeddings).enumerate()
+            .map(|(i, (chunk, embedding_values))| NewChunk {
+                content: chunk.text,
+                embedding: QueryVector::F32(embedding_values),
+                metadata: Some(serde_json::json!({
+                    "source_file": file_path.to_string_lossy(),
+                    "chunk_index": i,
+                    "char_count": chunk.text.len(),
+                    "byte_start": chunk.start,
+                    "byte_end": chunk.end
+                })),
+            })
+            .collect();
+
+        let uri = file_path.to_string_lossy();
+        let title = file_path.file_name().map(|name| name.to_string_lossy());
+        match disk.add_document(&uri, title.as_deref(), content, &new_chunks) {
+            Ok(_) => println!("Added {} chunks from {:?}", new_chunks.len(), file_path),
+            Err(e) => {
+                eprintln!("Failed to add chunks from {:?}: {}", file_path, e);
+                failed.push(file_path);
             }
         }
     }
 
+    // Store the built graph so opening the disk does not have to rebuild it
+    disk.persist_index()?;
+
+    if !failed.is_empty() {
+        anyhow::bail!(
+            "{} of {} files were skipped, so {:?} is incomplete: {:?}",
+            failed.len(),
+            files.len(),
+            output,
+            failed
+        );
+    }
     println!("Successfully created .idz file at {:?}!", output);
     Ok(())
 }
 
-// Simple random number generator for demo embeddings
-mod rand {
-    static mut SEED: u32 = 1;
-    
-    pub fn random<T>() -> T 
-    where 
-        T: From<f32>,
-    {
-        unsafe {
-            SEED = SEED.wrapping_mul(1103515245).wrapping_add(12345);
-            let val = (SEED >> 16) as f32 / 65536.0; // Always positive 0-1
-            T::from(val)
+fn reembed_idz_file(
+    file: PathBuf,
+    target_signature: &str,
+    batch_size: usize,
+    drop_old: bool,
+    embedder_args: &EmbedderArgs,
+) -> Result<()> {
+    let mut disk = IdentityDisk::open_default(&file)?;
+    let old_signature = disk.model_signature().to_string();
+    // Search the target so the embedder is built and checked for it
+    disk.switch_model_signature(target_signature)?;
+    let target_signature = disk.model_signature().to_string();
+    if target_signature == old_signature {
+        anyhow::bail!("{} is already the default model signature", target_signature);
+    }
+    let embedder = embedder_args.build(&disk)?;
+
+    println!("Re-embedding {:?} from {} into {}", file, old_signature, target_signature);
+    let mut options = ReembedOptions::new(batch_size);
+    if drop_old {
+        options = options.with_drop_old();
+    }
+    let report = disk.reembed_with_options(&target_signature, embedder.as_ref(), &options)?;
+    disk.persist_index()?;
+    if report.resumed {
+        println!("Resumed an interrupted run.");
+    }
+    println!(
+        "Embedded {} chunks; {} of {} chunks have a {} embedding.",
+        report.embedded,
+        report.total_chunks - report.missing.len(),
+        report.total_chunks,
+        report.model_signature
+    );
+
+    if !report.is_complete() {
+        println!("Chunks missing a {} embedding:", report.model_signature);
+        for chunk_id in &report.missing {
+            println!("  {}", chunk_id);
+        }
+        if drop_old {
+            println!("Keeping {} until every chunk is embedded.", old_signature);
         }
     }
+    if let Some(dropped_signature) = &report.dropped_signature {
+        println!(
+            "{} is now the default; dropped {} {} embeddings.",
+            target_signature, report.dropped, dropped_signature
+        );
+    }
+    Ok(())
 }
 
-fn run_tui(file_path: PathBuf, model_signature: &str) -> Result<()> {
+fn run_tui(file_path: PathBuf, model_signature: Option<&str>, embedder_args: EmbedderArgs) -> Result<()> {
     // Load the .idz file
-    println!("Opening .idz file: {:?} with model_signature: {}", file_path, model_signature);
-    let disk = IdentityDisk::open(&file_path, model_signature)?;
+    let disk = match model_signature {
+        Some(model_signature) => {
+            println!("Opening .idz file: {:?} with model_signature: {}", file_path, model_signature);
+            IdentityDisk::open(&file_path, model_signature)?
+        }
+        None => {
+            println!("Opening .idz file: {:?}", file_path);
+            IdentityDisk::open_default(&file_path)?
+        }
+    };
     
     // Setup terminal
     enable_raw_mode()?;
@@ -141,7 +360,7 @@ fn run_tui(file_path: PathBuf, model_signature: &str) -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let app = App::new(disk, file_path, model_signature.to_string());
+    let app = App::new(disk, embedder_args, file_path);
     let res = run_app(&mut terminal, app);
 
     // Restore terminal
@@ -162,8 +381,12 @@ fn run_tui(file_path: PathBuf, model_signature: &str) -> Result<()> {
 
 struct App {
     disk: IdentityDisk, // This is now the new IdentityDisk
+    embedder_args: EmbedderArgs, // Rebuilds the embedder when the signature changes
+    embedder: Option<Box<dyn Embedder>>, // Embeds search queries; None if unusable for the signature
     file_path: PathBuf,
-    model_signature: String, // Store the model signature used to open the disk
+    model_signature: String, // The model signature being searched
+    signatures: Vec<SignatureInfo>, // Signatures stored on the disk
+    signature_state: ListState, // For picking a signature in the overview
     all_chunks: Vec<Chunk>, // Cache all chunks
     current_view: AppView,
     list_state: ListState, // For navigating all_chunks
@@ -171,6 +394,7 @@ struct App {
     selected_chunk_id: Option<String>, // Store ID of the selected chunk for detail view
     // OR: selected_chunk_idx: Option<usize> to index into all_chunks / search_results.chunks
     search_mode: bool,
+    keyword_search: bool, // Full-text instead of semantic search
     search_query: String,
     search_results: Vec<SearchResult>, // Stores SearchResult structs
     status_message: String, // For displaying errors or info
@@ -185,18 +409,24 @@ enum AppView {
 }
 
 impl App {
-    fn new(disk: IdentityDisk, file_path: PathBuf, model_signature: String) -> Self {
+    fn new(disk: IdentityDisk, embedder_args: EmbedderArgs, file_path: PathBuf) -> Self {
         let list_state = ListState::default(); // Removed mut
+        let model_signature = disk.model_signature().to_string();
         let mut app = Self {
             disk,
+            embedder_args,
+            embedder: None, // Built by rebuild_embedder
             file_path,
             model_signature,
+            signatures: Vec::new(), // Loaded by refresh_signatures
+            signature_state: ListState::default(),
             all_chunks: Vec::new(), // Will be loaded by refresh_chunks
             current_view: AppView::Overview,
             list_state,
             search_list_state: ListState::default(),
             selected_chunk_id: None,
             search_mode: false,
+            keyword_search: false,
             search_query: String::new(),
             search_results: Vec::new(),
             status_message: String::new(),
@@ -205,9 +435,67 @@ impl App {
         if !app.all_chunks.is_empty() {
             app.list_state.select(Some(0));
         }
+        app.refresh_signatures();
+        app.rebuild_embedder();
         app
     }
 
+    fn refresh_signatures(&mut self) {
+        match self.disk.list_model_signatures() {
+            Ok(signatures) => {
+                let active = signatures.iter().position(|s| s.is_active);
+                self.signatures = signatures;
+                self.signature_state.select(active);
+            }
+            Err(e) => self.status_message = format!("Error listing model signatures: {}", e),
+        }
+    }
+
+    fn rebuild_embedder(&mut self) {
+        self.embedder = match self.embedder_args.build(&self.disk) {
+            Ok(embedder) => Some(embedder),
+            Err(e) => {
+                self.status_message = format!("No query embedder for {}: {}", self.model_signature, e);
+                None
+            }
+        };
+    }
+
+    fn next_signature(&mut self) {
+        let count = self.signatures.len();
+        if count > 0 {
+            let i = self.signature_state.selected().map_or(0, |i| (i + 1) % count);
+            self.signature_state.select(Some(i));
+        }
+    }
+
+    fn previous_signature(&mut self) {
+        let count = self.signatures.len();
+        if count > 0 {
+            let i = self.signature_state.selected().map_or(0, |i| (i + count - 1) % count);
+            self.signature_state.select(Some(i));
+        }
+    }
+
+    /// Searches the signature selected in the overview from now on.
+    fn switch_signature(&mut self) {
+        let Some(info) = self.signature_state.selected().and_then(|i| self.signatures.get(i)) else {
+            return;
+        };
+        let signature = info.model_signature.clone();
+        match self.disk.switch_model_signature(&signature) {
+            Ok(()) => {
+                self.model_signature = self.disk.model_signature().to_string();
+                self.status_message = format!("Switched to {}", self.model_signature);
+                self.search_results.clear();
+                self.search_list_state.select(None);
+                self.refresh_signatures();
+                self.rebuild_embedder();
+            }
+            Err(e) => self.status_message = format!("Failed to switch to {}: {}", signature, e),
+        }
+    }
+
     fn refresh_chunks(&mut self) {
         match self.disk.get_chunks() {
             Ok(chunks) => {
@@ -282,19 +570,19 @@ impl App {
             return;
         }
         
-        // Use model_signature to get dim, similar to create_idz_file
-        // This is a simplification. A robust app might store dim or parse more reliably.
-        let dim: usize = self.model_signature.split('_').next().unwrap_or("").split('-').last()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(1536); // Default if parsing fails
-
-        // Generate dummy f32 embedding for the search query
-        // Ensure this matches the expected QueryVector type for the loaded index.
-        // For now, assumes F32 based on common use and previous dummy data.
-        let query_embedding_values: Vec<f32> = (0..dim).map(|_| rand::random::<f32>() * 0.1).collect(); // small values
-        let query_vec = QueryVector::F32(&query_embedding_values);
-        
-        match self.disk.search(query_vec, 10) {
+        let results = if self.keyword_search {
+            self.disk.keyword_search(&self.search_query, 10)
+        } else {
+            let Some(embedder) = &self.embedder else {
+                self.status_message = format!("No query embedder for {}; try keyword search (m)", self.model_signature);
+                return;
+            };
+            embedder
+                .embed(&[&self.search_query])
+                .and_then(|embeddings| self.disk.search(QueryVector::F32(&embeddings[0]), 10))
+        };
+
+        match results {
             Ok(results) => {
                 self.search_results = results;
                 if !self.search_results.is_empty() {
@@ -350,6 +638,12 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, mut app: App)
                     }
                 } else {
                     match app.current_view {
+                        AppView::Overview => match key.code {
+                            KeyCode::Down | KeyCode::Char('j') => app.next_signature(),
+                            KeyCode::Up | KeyCode::Char('k') => app.previous_signature(),
+                            KeyCode::Enter => app.switch_signature(),
+                            _ => {}
+                        },
                         AppView::ChunkList => match key.code {
                             KeyCode::Down | KeyCode::Char('j') => app.next_chunk(),
                             KeyCode::Up | KeyCode::Char('k') => app.previous_chunk(),
@@ -363,15 +657,18 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, mut app: App)
                             }
                             _ => {}
                         },
-                        AppView::ChunkDetail => match key.code {
-                            KeyCode::Esc => app.current_view = AppView::ChunkList,
-                            _ => {}
-                        },
+                        AppView::ChunkDetail if key.code == KeyCode::Esc => {
+                            app.current_view = AppView::ChunkList;
+                        }
                         AppView::Search => match key.code {
                             KeyCode::Char('/') => {
                                 app.search_mode = true;
                                 app.search_results.clear(); // Clear old results
                             }
+                            KeyCode::Char('m') => {
+                                app.keyword_search = !app.keyword_search;
+                                app.perform_search();
+                            }
                             KeyCode::Down | KeyCode::Char('j') => app.next_search_result(),
                             KeyCode::Up | KeyCode::Char('k') => app.previous_search_result(),
                             KeyCode::Enter => {
@@ -413,10 +710,10 @@ fn ui(f: &mut Frame, app: &mut App) {
         "Enter: Search | Esc: Cancel"
     } else {
         match app.current_view {
-            AppView::Overview => "1: Overview | 2: Chunks | 3: Search | q: Quit",
+            AppView::Overview => "↑↓/jk: Pick Signature | Enter: Switch | 2: Chunks | 3: Search | q: Quit",
             AppView::ChunkList => "↑↓/jk: Navigate | Enter: View | 1: Overview | 3: Search | q: Quit",
             AppView::ChunkDetail => "Esc: Back | 1: Overview | 2: Chunks | q: Quit",
-            AppView::Search => "/: Search | ↑↓/jk: Navigate Results | Enter: View Chunk | q: Quit",
+            AppView::Search => "/: Search | m: Semantic/Keyword | ↑↓/jk: Navigate Results | Enter: View Chunk | q: Quit",
         }
     };
     let footer = Paragraph::new(footer_text)
@@ -432,10 +729,14 @@ fn ui(f: &mut Frame, app: &mut App) {
     }
 }
 
-fn render_overview(f: &mut Frame, area: Rect, app: &App) {
+fn render_overview(f: &mut Frame, area: Rect, app: &mut App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .constraints([
+            Constraint::Percentage(35),
+            Constraint::Percentage(35),
+            Constraint::Percentage(30),
+        ])
         .split(area);
 
     // File info
@@ -448,10 +749,16 @@ fn render_overview(f: &mut Frame, area: Rect, app: &App) {
         0
     };
 
-    let file_info = vec![
+    let documents = app
+        .disk
+        .list_documents()
+        .map_or_else(|e| format!("Error: {}", e), |docs| docs.len().to_string());
+
+    let file_info = [
         format!("File: {}", app.file_path.display()),
         format!("Spec Version: {}", spec_version),
         format!("Model Signature: {}", app.model_signature),
+        format!("Documents: {}", documents),
         format!("Total Chunks: {}", app.all_chunks.len()),
         format!("Average chars per chunk: {}", avg_chars),
     ];
@@ -461,27 +768,91 @@ fn render_overview(f: &mut Frame, area: Rect, app: &App) {
         .wrap(Wrap { trim: true });
     f.render_widget(file_widget, chunks[0]); // Use the full area for simplified overview
 
-    // Embedding info (simplified or extracted from model_signature)
-    let dim: usize = app.model_signature.split('_').next().unwrap_or("").split('-').last()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(0); // Show 0 if not parsable
-    let dtype = app.model_signature.split('_').nth(1).unwrap_or("unknown");
+    // Embedding info from the active model signature
+    let signature = app.disk.model_signature();
 
+    let index_config = app.disk.index_config();
     let index_type_desc = app.disk.get_index_type_description().unwrap_or_else(|e| format!("Error: {}", e));
-    let embed_info = vec![
-        format!("Parsed Dimension: {}", if dim == 0 { "N/A".to_string() } else { dim.to_string() }),
-        format!("Parsed Data Type: {}", dtype),
+    let embed_info = [
+        format!("Provider: {}", signature.provider),
+        format!("Model: {}", signature.model),
+        format!("Dimension: {}", signature.dim),
+        format!("Data Type: {}", signature.dtype.as_str()),
+        format!("Metric: {}", signature.metric.as_str()),
         format!("Active Index Type: {}", index_type_desc),
+        format!(
+            "HNSW: M={} ef_construction={} layers={} ef_search={}",
+            index_config.max_nb_connection,
+            index_config.ef_construction,
+            index_config.max_layers,
+            index_config.ef_search
+        ),
     ];
+    let index_area = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chunks[1]);
     let embed_widget = Paragraph::new(embed_info.join("\n"))
         .block(Block::default().borders(Borders::ALL).title("Active Index Information"))
         .wrap(Wrap { trim: true });
-    f.render_widget(embed_widget, chunks[1]);
+    f.render_widget(embed_widget, index_area[0]);
+
+    // Signatures stored on the disk; Enter switches to the selected one
+    let items: Vec<ListItem> = app
+        .signatures
+        .iter()
+        .map(|info| {
+            let mut tags = Vec::new();
+            if info.is_active {
+                tags.push("active");
+            }
+            if info.is_default {
+                tags.push("default");
+            }
+            let tags = if tags.is_empty() { String::new() } else { format!(" [{}]", tags.join(", ")) };
+            ListItem::new(format!(
+                "{} - {} vectors ({:.0}%){}",
+                info.model_signature,
+                info.vectors,
+                info.coverage * 100.0,
+                tags
+            ))
+        })
+        .collect();
+    let signature_list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Model Signatures"))
+        .highlight_style(Style::default().bg(Color::Blue).fg(Color::White))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(signature_list, index_area[1], &mut app.signature_state);
+
+    // User-defined disk properties; library-managed keys are shown above
+    let properties = match app.disk.get_manifest() {
+        Ok(manifest) => {
+            let lines: Vec<String> = manifest
+                .iter()
+                .filter(|(key, _)| !IdentityDisk::is_reserved_manifest_key(key))
+                .map(|(key, value)| match value {
+                    serde_json::Value::String(s) => format!("{}: {}", key, s),
+                    other => format!("{}: {}", key, other),
+                })
+                .collect();
+            if lines.is_empty() {
+                "No properties set.".to_string()
+            } else {
+                lines.join("\n")
+            }
+        }
+        Err(e) => format!("Error: {}", e),
+    };
+    let properties_widget = Paragraph::new(properties)
+        .block(Block::default().borders(Borders::ALL).title("Disk Properties"))
+        .wrap(Wrap { trim: true });
+    f.render_widget(properties_widget, chunks[2]);
 }
 
 fn render_chunk_list(f: &mut Frame, area: Rect, app: &mut App) {
-    let items: Vec<ListItem> = app.all_chunks.iter().enumerate()
-        .map(|(_i, chunk)| { // _i instead of i
+    let items: Vec<ListItem> = app.all_chunks.iter()
+        .map(|chunk| {
             let preview = if chunk.content.len() > 80 {
                 format!("{}...", chunk.content.chars().take(77).collect::<String>())
             } else {
@@ -551,8 +922,9 @@ fn render_search(f: &mut Frame, area: Rect, app: &mut App) {
         format!("Search: {}", app.search_query)
     };
 
+    let search_title = if app.keyword_search { "Keyword Search" } else { "Semantic Search" };
     let search_widget = Paragraph::new(query_display)
-        .block(Block::default().borders(Borders::ALL).title("Semantic Search").border_style(search_style));
+        .block(Block::default().borders(Borders::ALL).title(search_title).border_style(search_style));
     f.render_widget(search_widget, chunks[0]);
 
     // Search results
@@ -565,7 +937,13 @@ fn render_search(f: &mut Frame, area: Rect, app: &mut App) {
             } else {
                 chunk.content.clone()
             };
-            ListItem::new(format!("ID: {}... | Score: {:.4} | {}", &chunk.chunk_id[..8], score, preview))
+            ListItem::new(format!(
+                "ID: {}... | Score ({}): {:.4} | {}",
+                &chunk.chunk_id[..8],
+                result.metric.as_str(),
+                score,
+                preview
+            ))
         }).collect();
 
         let list_title = format!("Search Results (Found: {})", app.search_results.len());
@@ -0,0 +1,376 @@
+use crate::errors::DiskError;
+
+/// A piece of a source text produced by a [`Chunker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextChunk<'a> {
+    pub text: &'a str,
+    /// Byte offset of the first byte of `text` in the source.
+    pub start: usize,
+    /// Byte offset just past the last byte of `text` in the source, so
+    /// `&source[start..end] == text`.
+    pub end: usize,
+}
+
+/// Splits a text into chunks for embedding.
+///
+/// Chunks are returned in source order. Apart from the fixed window, which keeps
+/// its exact windows, strategies trim surrounding whitespace from each chunk and
+/// drop chunks that are empty after trimming.
+pub trait Chunker {
+    fn chunk<'a>(&self, text: &'a str) -> Vec<TextChunk<'a>>;
+}
+
+/// One chunk per non-blank line.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LineChunker;
+
+impl Chunker for LineChunker {
+    fn chunk<'a>(&self, text: &'a str) -> Vec<TextChunk<'a>> {
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            push_trimmed(&mut chunks, text, offset, offset + line.len());
+            offset += line.len();
+        }
+        chunks
+    }
+}
+
+/// One chunk per paragraph, where paragraphs are separated by blank lines.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ParagraphChunker;
+
+impl Chunker for ParagraphChunker {
+    fn chunk<'a>(&self, text: &'a str) -> Vec<TextChunk<'a>> {
+        let mut chunks = Vec::new();
+        let mut paragraph_start = None;
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            if line.trim().is_empty() {
+                if let Some(start) = paragraph_start.take() {
+                    push_trimmed(&mut chunks, text, start, offset);
+                }
+            } else if paragraph_start.is_none() {
+                paragraph_start = Some(offset);
+            }
+            offset += line.len();
+        }
+        if let Some(start) = paragraph_start {
+            push_trimmed(&mut chunks, text, start, offset);
+        }
+        chunks
+    }
+}
+
+/// One chunk per sentence. A sentence ends at `.`, `!` or `?` followed by
+/// whitespace, or at a blank line.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SentenceChunker;
+
+impl Chunker for SentenceChunker {
+    fn chunk<'a>(&self, text: &'a str) -> Vec<TextChunk<'a>> {
+        let mut chunks = Vec::new();
+        for paragraph in ParagraphChunker.chunk(text) {
+            let mut start = paragraph.start;
+            let mut chars = paragraph.text.char_indices().peekable();
+            while let Some((i, c)) = chars.next() {
+                let ends_sentence = matches!(c, '.' | '!' | '?')
+                    && chars.peek().is_none_or(|&(_, next)| next.is_whitespace());
+                if ends_sentence {
+                    let end = paragraph.start + i + c.len_utf8();
+                    push_trimmed(&mut chunks, text, start, end);
+                    start = end;
+                }
+            }
+            push_trimmed(&mut chunks, text, start, paragraph.end);
+        }
+        chunks
+    }
+}
+
+/// Windows of `size` characters, each starting `size - overlap` characters
+/// after the previous one. The last window may be shorter.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedWindowChunker {
+    size: usize,
+    overlap: usize,
+}
+
+impl FixedWindowChunker {
+    /// Fails with `DiskError::InvalidData` unless `0 <= overlap < size`.
+    pub fn new(size: usize, overlap: usize) -> Result<Self, DiskError> {
+        if size == 0 || overlap >= size {
+            return Err(DiskError::InvalidData(format!(
+                "Chunk overlap ({}) must be smaller than a non-zero chunk size ({})",
+                overlap, size
+            )));
+        }
+        Ok(Self { size, overlap })
+    }
+}
+
+impl Chunker for FixedWindowChunker {
+    fn chunk<'a>(&self, text: &'a str) -> Vec<TextChunk<'a>> {
+        // Byte offset of every character, plus the end of the text.
+        let bounds: Vec<usize> = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(text.len()))
+            .collect();
+        let char_count = bounds.len() - 1;
+
+        let mut chunks = Vec::new();
+        let mut first = 0;
+        while first < char_count {
+            let last = (first + self.size).min(char_count);
+            let (start, end) = (bounds[first], bounds[last]);
+            if !text[start..end].trim().is_empty() {
+                chunks.push(TextChunk {
+                    text: &text[start..end],
+                    start,
+                    end,
+                });
+            }
+            if last == char_count {
+                break;
+            }
+            first += self.size - self.overlap;
+        }
+        chunks
+    }
+}
+
+/// Splits on the first separator that occurs in the text, merges neighbouring
+/// pieces back together while they fit in `max_size` characters, and splits
+/// pieces that are still too long with the next separator. Once the separators
+/// run out, pieces are cut into `max_size` windows.
+#[derive(Debug, Clone)]
+pub struct RecursiveChunker {
+    max_size: usize,
+    separators: Vec<String>,
+}
+
+impl RecursiveChunker {
+    /// Paragraphs, then lines, then sentences, then words.
+    pub const DEFAULT_SEPARATORS: &'static [&'static str] = &["\n\n", "\n", ". ", " "];
+
+    /// Uses [`Self::DEFAULT_SEPARATORS`]. Fails with `DiskError::InvalidData` if
+    /// `max_size` is zero.
+    pub fn new(max_size: usize) -> Result<Self, DiskError> {
+        Self::with_separators(
+            max_size,
+            Self::DEFAULT_SEPARATORS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        )
+    }
+
+    /// Uses `separators`, from the most to the least preferred split point.
+    pub fn with_separators(max_size: usize, separators: Vec<String>) -> Result<Self, DiskError> {
+        if max_size == 0 {
+            return Err(DiskError::InvalidData("Chunk size must be non-zero".into()));
+        }
+        if separators.iter().any(|s| s.is_empty()) {
+            return Err(DiskError::InvalidData(
+                "Chunk separators must be non-empty".into(),
+            ));
+        }
+        Ok(Self {
+            max_size,
+            separators,
+        })
+    }
+
+    fn split<'a>(
+        &self,
+        text: &'a str,
+        start: usize,
+        end: usize,
+        separators: &[String],
+        chunks: &mut Vec<TextChunk<'a>>,
+    ) {
+        if text[start..end].chars().count() <= self.max_size {
+            push_trimmed(chunks, text, start, end);
+            return;
+        }
+
+        let Some((i, separator)) = separators
+            .iter()
+            .enumerate()
+            .find(|(_, s)| text[start..end].contains(s.as_str()))
+        else {
+            // No separator left: fall back to hard windows.
+            let windows = FixedWindowChunker {
+                size: self.max_size,
+                overlap: 0,
+            };
+            for window in windows.chunk(&text[start..end]) {
+                push_trimmed(chunks, text, start + window.start, start + window.end);
+            }
+            return;
+        };
+
+        // Pieces keep their trailing separator, so they tile `start..end`.
+        let mut pieces = Vec::new();
+        let mut piece_start = start;
+        for (offset, _) in text[start..end].match_indices(separator.as_str()) {
+            let piece_end = start + offset + separator.len();
+            pieces.push((piece_start, piece_end));
+            piece_start = piece_end;
+        }
+        if piece_start < end {
+            pieces.push((piece_start, end));
+        }
+
+        let remaining = &separators[i + 1..];
+        let mut merged: Option<(usize, usize)> = None;
+        for (piece_start, piece_end) in pieces {
+            merged = match merged {
+                Some((merged_start, _))
+                    if text[merged_start..piece_end].chars().count() <= self.max_size =>
+                {
+                    Some((merged_start, piece_end))
+                }
+                Some((merged_start, merged_end)) => {
+                    self.split(text, merged_start, merged_end, remaining, chunks);
+                    Some((piece_start, piece_end))
+                }
+                None => Some((piece_start, piece_end)),
+            };
+        }
+        if let Some((merged_start, merged_end)) = merged {
+            self.split(text, merged_start, merged_end, remaining, chunks);
+        }
+    }
+}
+
+impl Chunker for RecursiveChunker {
+    fn chunk<'a>(&self, text: &'a str) -> Vec<TextChunk<'a>> {
+        let mut chunks = Vec::new();
+        self.split(text, 0, text.len(), &self.separators, &mut chunks);
+        chunks
+    }
+}
+
+/// Pushes `text[start..end]` without its surrounding whitespace, unless nothing
+/// is left.
+fn push_trimmed<'a>(chunks: &mut Vec<TextChunk<'a>>, text: &'a str, start: usize, end: usize) {
+    let slice = &text[start..end];
+    let trimmed = slice.trim_start();
+    let start = start + (slice.len() - trimmed.len());
+    let trimmed = trimmed.trim_end();
+    if !trimmed.is_empty() {
+        chunks.push(TextChunk {
+            text: trimmed,
+            start,
+            end: start + trimmed.len(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The chunk texts, after checking that every chunk's offsets point at its text.
+    fn texts<'a>(source: &str, chunks: &[TextChunk<'a>]) -> Vec<&'a str> {
+        for chunk in chunks {
+            assert_eq!(&source[chunk.start..chunk.end], chunk.text);
+        }
+        chunks.iter().map(|c| c.text).collect()
+    }
+
+    #[test]
+    fn line_chunker_trims_lines_and_skips_blank_ones() {
+        let source = "  first line\n\n\t\nsecond line  \r\nlast";
+        let chunks = LineChunker.chunk(source);
+        assert_eq!(texts(source, &chunks), vec!["first line", "second line", "last"]);
+        assert_eq!((chunks[0].start, chunks[0].end), (2, 12));
+    }
+
+    #[test]
+    fn paragraph_chunker_splits_on_blank_lines() {
+        let source = "\nOne\nstill one\n  \n\nTwo\n\n   Three  ";
+        let chunks = ParagraphChunker.chunk(source);
+        assert_eq!(texts(source, &chunks), vec!["One\nstill one", "Two", "Three"]);
+    }
+
+    #[test]
+    fn sentence_chunker_splits_at_terminal_punctuation_and_paragraphs() {
+        let source = "Pi is 3.14. Is it? Yes!Really.\nSame sentence\n\nNew paragraph";
+        let chunks = SentenceChunker.chunk(source);
+        assert_eq!(
+            texts(source, &chunks),
+            vec!["Pi is 3.14.", "Is it?", "Yes!Really.", "Same sentence", "New paragraph"]
+        );
+    }
+
+    #[test]
+    fn sentence_chunker_keeps_multibyte_offsets() {
+        let source = "Ça va. Très bien… merci! Fin";
+        let chunks = SentenceChunker.chunk(source);
+        assert_eq!(texts(source, &chunks), vec!["Ça va.", "Très bien… merci!", "Fin"]);
+    }
+
+    #[test]
+    fn fixed_window_chunker_overlaps_windows() {
+        let source = "abcdefg";
+        let chunks = FixedWindowChunker::new(4, 2).unwrap().chunk(source);
+        assert_eq!(texts(source, &chunks), vec!["abcd", "cdef", "efg"]);
+
+        let chunks = FixedWindowChunker::new(3, 0).unwrap().chunk(source);
+        assert_eq!(texts(source, &chunks), vec!["abc", "def", "g"]);
+    }
+
+    #[test]
+    fn fixed_window_chunker_counts_characters_and_keeps_exact_windows() {
+        let source = "héllo wörld";
+        let chunks = FixedWindowChunker::new(3, 1).unwrap().chunk(source);
+        assert_eq!(texts(source, &chunks), vec!["hél", "llo", "o w", "wör", "rld"]);
+
+        // Whitespace-only windows are dropped, others keep their whitespace.
+        let source = "ab      cd";
+        let chunks = FixedWindowChunker::new(3, 0).unwrap().chunk(source);
+        assert_eq!(texts(source, &chunks), vec!["ab ", "  c", "d"]);
+    }
+
+    #[test]
+    fn fixed_window_chunker_rejects_invalid_sizes() {
+        assert!(FixedWindowChunker::new(0, 0).is_err());
+        assert!(FixedWindowChunker::new(4, 4).is_err());
+        assert!(FixedWindowChunker::new(4, 5).is_err());
+    }
+
+    #[test]
+    fn recursive_chunker_prefers_the_coarsest_separator_that_fits() {
+        let source = "First paragraph here.\n\nSecond one. It has two sentences.\n\nEnd";
+        let chunks = RecursiveChunker::new(22).unwrap().chunk(source);
+        assert_eq!(
+            texts(source, &chunks),
+            vec!["First paragraph here.", "Second one.", "It has two sentences.", "End"]
+        );
+        assert!(chunks.iter().all(|c| c.text.chars().count() <= 22));
+    }
+
+    #[test]
+    fn recursive_chunker_merges_small_pieces() {
+        let source = "a\nb\nc\nd\ne";
+        // Pieces are measured with their trailing separator, so "a\nb\nc\n" is too long.
+        let chunks = RecursiveChunker::new(5).unwrap().chunk(source);
+        assert_eq!(texts(source, &chunks), vec!["a\nb", "c\nd\ne"]);
+    }
+
+    #[test]
+    fn recursive_chunker_cuts_unsplittable_text_into_windows() {
+        let source = "tiny supercalifragilistic";
+        let chunks = RecursiveChunker::new(8).unwrap().chunk(source);
+        assert_eq!(texts(source, &chunks), vec!["tiny", "supercal", "ifragili", "stic"]);
+    }
+
+    #[test]
+    fn recursive_chunker_rejects_invalid_settings() {
+        assert!(RecursiveChunker::new(0).is_err());
+        assert!(RecursiveChunker::with_separators(10, vec![String::new()]).is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use half::f16;
+
+/// Serializes an f32 vector as little-endian bytes for the `indices` table.
+pub(crate) fn encode_f32(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+/// Deserializes a little-endian f32 blob, or `None` if it does not hold `dim` values.
+pub(crate) fn decode_f32(blob: &[u8], dim: usize) -> Option<Vec<f32>> {
+    if blob.len() != dim * 4 {
+        return None;
+    }
+    Some(
+        blob.chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect(),
+    )
+}
+
+/// Serializes an f16 vector as little-endian bytes for the `indices` table.
+pub(crate) fn encode_f16(vector: &[f16]) -> Vec<u8> {
+    vector.iter().flat_map(|h| h.to_le_bytes()).collect()
+}
+
+/// Deserializes a little-endian f16 blob widened to f32, or `None` if it does not
+/// hold `dim` values.
+pub(crate) fn decode_f16(blob: &[u8], dim: usize) -> Option<Vec<f32>> {
+    if blob.len() != dim * 2 {
+        return None;
+    }
+    Some(
+        blob.chunks_exact(2)
+            .map(|b| f16::from_le_bytes(b.try_into().unwrap()).to_f32())
+            .collect(),
+    )
+}
+
+/// Serializes int8 codes as raw bytes for the `indices` table.
+pub(crate) fn encode_i8(codes: &[i8]) -> Vec<u8> {
+    codes.iter().map(|&q| q as u8).collect()
+}
+
+/// Deserializes an int8 blob, or `None` if it does not hold `dim` codes.
+pub(crate) fn decode_i8(blob: &[u8], dim: usize) -> Option<Vec<i8>> {
+    if blob.len() != dim {
+        return None;
+    }
+    Some(blob.iter().map(|&b| b as i8).collect())
+}
+
+/// Validates a bit-packed blob holding `dim` sign bits.
+pub(crate) fn decode_bin(blob: &[u8], dim: usize) -> Option<Vec<u8>> {
+    if blob.len() != dim.div_ceil(8) {
+        return None;
+    }
+    Some(blob.to_vec())
+}
@@ -0,0 +1,52 @@
+use hnsw_rs::prelude::{DistCosine, DistL2, Distance};
+
+use crate::models::Metric;
+
+/// Inner-product distance that stays valid for vectors that are not unit length.
+///
+/// HNSW needs non-negative distances, so `1 - dot` is mapped onto a positive,
+/// strictly decreasing function of the inner product: `1 - dot` while the inner
+/// product is at most 0, and `1 / (1 + dot)` above it. Rankings are identical to
+/// `1 - dot`; use [`inner_product_distance`] to recover that value for results.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DistInnerProduct;
+
+impl Distance<f32> for DistInnerProduct {
+    fn eval(&self, va: &[f32], vb: &[f32]) -> f32 {
+        assert_eq!(va.len(), vb.len());
+        let dot: f32 = va.iter().zip(vb).map(|(a, b)| a * b).sum();
+        graph_distance(dot)
+    }
+}
+
+/// Maps an inner product onto the positive distance stored in the graph.
+pub(crate) fn graph_distance(dot: f32) -> f32 {
+    if dot <= 0.0 {
+        1.0 - dot
+    } else {
+        1.0 / (1.0 + dot)
+    }
+}
+
+/// Converts a [`DistInnerProduct`] graph distance back to `1 - dot`.
+///
+/// Unlike the graph distance this can be negative when the inner product
+/// exceeds 1.
+pub fn inner_product_distance(graph_distance: f32) -> f32 {
+    if graph_distance >= 1.0 {
+        graph_distance
+    } else {
+        2.0 - 1.0 / graph_distance
+    }
+}
+
+/// The distance reported for two f32 vectors under `metric`.
+///
+/// Cosine is `1 - cos`, L2 is the Euclidean distance and dot is `1 - dot`.
+pub fn reported_distance(metric: Metric, va: &[f32], vb: &[f32]) -> f32 {
+    match metric {
+        Metric::Cosine => DistCosine.eval(va, vb),
+        Metric::L2 => DistL2.eval(va, vb),
+        Metric::Dot => 1.0 - va.iter().zip(vb).map(|(a, b)| a * b).sum::<f32>(),
+    }
+}
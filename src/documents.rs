@@ -0,0 +1,44 @@
+use rusqlite::Connection;
+
+use crate::errors::DiskError;
+
+/// Documents group the chunks cut from one source. `uri` identifies the source,
+/// so ingesting the same source again replaces its chunks.
+const CREATE_DOCUMENTS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS documents (
+    document_id TEXT PRIMARY KEY,
+    uri TEXT NOT NULL UNIQUE,
+    title TEXT,
+    content_hash TEXT NOT NULL, -- blake3 hex digest of the ingested content
+    ingested_at TEXT NOT NULL -- UTC timestamp in ISO 8601 format
+);
+"#;
+
+/// Links chunks to their document, with `position` giving their order in it.
+/// Chunks added without a document keep both columns `NULL`.
+const ADD_CHUNK_DOCUMENT_COLUMNS_SQL: &str = r#"
+ALTER TABLE chunks ADD COLUMN document_id TEXT REFERENCES documents (document_id) ON DELETE CASCADE;
+ALTER TABLE chunks ADD COLUMN position INTEGER;
+"#;
+
+const CREATE_CHUNK_DOCUMENT_INDEX_SQL: &str =
+    "CREATE INDEX IF NOT EXISTS idx_chunks_document ON chunks (document_id, position);";
+
+/// Creates the `documents` table and the `chunks` columns that refer to it, if
+/// the disk does not have them yet.
+pub(crate) fn ensure_documents(conn: &Connection) -> Result<(), DiskError> {
+    conn.execute_batch(CREATE_DOCUMENTS_TABLE_SQL)?;
+    let has_columns = conn
+        .prepare("SELECT 1 FROM pragma_table_info('chunks') WHERE name = 'document_id'")?
+        .exists([])?;
+    if !has_columns {
+        conn.execute_batch(ADD_CHUNK_DOCUMENT_COLUMNS_SQL)?;
+    }
+    conn.execute_batch(CREATE_CHUNK_DOCUMENT_INDEX_SQL)?;
+    Ok(())
+}
+
+/// The digest stored in `documents.content_hash`.
+pub(crate) fn content_hash(content: &str) -> String {
+    blake3::hash(content.as_bytes()).to_hex().to_string()
+}
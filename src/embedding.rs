@@ -0,0 +1,592 @@
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::errors::DiskError;
+use crate::models::ModelSignature;
+
+/// Turns texts into embedding vectors.
+pub trait Embedder: Send + Sync {
+    /// The model name, as it appears in a `ModelSignature`.
+    fn model(&self) -> &str;
+
+    /// The length of every vector returned by [`Embedder::embed`].
+    fn dim(&self) -> usize;
+
+    /// Embeds a batch of texts, returning one vector per text in the same order.
+    fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, DiskError>;
+
+    /// Checks that this embedder produces vectors for the model and dimension
+    /// of `signature`. The provider is not compared, since the same model can be
+    /// served by several providers.
+    fn check_signature(&self, signature: &ModelSignature) -> Result<(), DiskError> {
+        if self.model() != signature.model {
+            return Err(DiskError::Embedding(format!(
+                "embedder model '{}' does not match model signature '{}'",
+                self.model(),
+                signature
+            )));
+        }
+        if self.dim() != signature.dim {
+            return Err(DiskError::DimensionMismatch {
+                expected: signature.dim,
+                got: self.dim(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// An [`Embedder`] for OpenAI-compatible `POST {base_url}/embeddings` endpoints.
+///
+/// Texts are sent in batches of at most `batch_size`. Requests that time out,
+/// fail to connect or get a 429 or 5xx response are retried with exponential
+/// backoff, honouring `Retry-After` when the server sends one.
+#[derive(Debug, Clone)]
+pub struct OpenAiEmbedder {
+    agent: ureq::Agent,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    dim: usize,
+    batch_size: usize,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl OpenAiEmbedder {
+    pub const DEFAULT_BASE_URL: &'static str = "https://api.openai.com/v1";
+    pub const DEFAULT_BATCH_SIZE: usize = 64;
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+    pub const DEFAULT_MAX_RETRIES: u32 = 3;
+    pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+    /// An embedder for `model` producing `dim`-dimensional vectors, served
+    /// under `base_url`, e.g. `https://api.openai.com/v1` or
+    /// `http://localhost:11434/v1`.
+    pub fn new(base_url: &str, model: &str, dim: usize) -> Self {
+        Self {
+            agent: Self::agent(Self::DEFAULT_TIMEOUT),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: None,
+            model: model.to_string(),
+            dim,
+            batch_size: Self::DEFAULT_BATCH_SIZE,
+            max_retries: Self::DEFAULT_MAX_RETRIES,
+            retry_backoff: Self::DEFAULT_RETRY_BACKOFF,
+        }
+    }
+
+    /// An embedder for the model and dimension of `signature`.
+    pub fn for_signature(base_url: &str, signature: &ModelSignature) -> Self {
+        Self::new(base_url, &signature.model, signature.dim)
+    }
+
+    /// Sends `api_key` as a bearer token.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Sends at most `batch_size` texts per request.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Fails a request attempt that takes longer than `timeout` overall.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.agent = Self::agent(timeout);
+        self
+    }
+
+    /// Retries a failed request up to `max_retries` times, waiting `backoff`
+    /// before the first retry and doubling the wait after each one.
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    fn agent(timeout: Duration) -> ureq::Agent {
+        ureq::AgentBuilder::new().timeout(timeout).build()
+    }
+
+    /// Sends one batch, retrying transient failures.
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, DiskError> {
+        let url = format!("{}/embeddings", self.base_url);
+        let body = json!({ "model": self.model, "input": texts });
+
+        let mut attempt = 0;
+        let response = loop {
+            let mut request = self.agent.post(&url);
+            if let Some(api_key) = &self.api_key {
+                request = request.set("Authorization", &format!("Bearer {}", api_key));
+            }
+            let (error, retry_after) = match request.send_json(&body) {
+                Ok(response) => break response,
+                Err(ureq::Error::Status(status, response)) => {
+                    let retry_after = response
+                        .header("Retry-After")
+                        .and_then(|s| s.trim().parse().ok())
+                        .map(Duration::from_secs);
+                    let retryable = status == 429 || status >= 500;
+                    let error = DiskError::Embedding(format!(
+                        "{} returned HTTP {}: {}",
+                        url,
+                        status,
+                        response.into_string().unwrap_or_default()
+                    ));
+                    if !retryable {
+                        return Err(error);
+                    }
+                    (error, retry_after)
+                }
+                Err(ureq::Error::Transport(transport)) => {
+                    (DiskError::Embedding(format!("embeddings request failed: {}", transport)), None)
+                }
+            };
+            if attempt >= self.max_retries {
+                return Err(error);
+            }
+            let backoff = self.retry_backoff * 2u32.saturating_pow(attempt);
+            thread::sleep(retry_after.map_or(backoff, |after| after.max(backoff)));
+            attempt += 1;
+        };
+
+        let mut data = response
+            .into_json::<EmbeddingResponse>()
+            .map_err(|e| DiskError::Embedding(format!("invalid response from {}: {}", url, e)))?
+            .data;
+        if data.len() != texts.len() {
+            return Err(DiskError::Embedding(format!(
+                "{} returned {} embeddings for {} inputs",
+                url,
+                data.len(),
+                texts.len()
+            )));
+        }
+        data.sort_by_key(|d| d.index);
+        data.into_iter()
+            .map(|d| {
+                if d.embedding.len() == self.dim {
+                    Ok(d.embedding)
+                } else {
+                    Err(DiskError::DimensionMismatch {
+                        expected: self.dim,
+                        got: d.embedding.len(),
+                    })
+                }
+            })
+            .collect()
+    }
+}
+
+impl Embedder for OpenAiEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, DiskError> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            vectors.extend(self.embed_batch(batch)?);
+        }
+        Ok(vectors)
+    }
+}
+
+/// An offline [`Embedder`] that hashes word, word-bigram and character n-gram
+/// features into `dim` buckets.
+///
+/// Vectors are deterministic and unit length, so texts sharing words or word
+/// fragments score as similar under any metric. Optional [`IdfWeights`] scale
+/// every bucket by how rare its features are in a corpus, usually the disk's
+/// own chunks.
+#[derive(Debug, Clone)]
+pub struct HashingEmbedder {
+    dim: usize,
+    idf: Option<IdfWeights>,
+}
+
+/// Inverse document frequencies of the buckets of a [`HashingEmbedder`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdfWeights {
+    /// The number of texts the weights were fitted on.
+    pub documents: usize,
+    /// One weight per bucket.
+    pub weights: Vec<f32>,
+}
+
+impl HashingEmbedder {
+    /// The model name to use in model signatures, e.g. `local/hashing-512_fp32`.
+    pub const MODEL: &'static str = "hashing";
+
+    /// Lengths of the character n-grams taken from every word.
+    const CHAR_NGRAMS: std::ops::RangeInclusive<usize> = 3..=4;
+
+    /// Fails with `DiskError::InvalidData` if `dim` is zero.
+    pub fn new(dim: usize) -> Result<Self, DiskError> {
+        if dim == 0 {
+            return Err(DiskError::InvalidData(
+                "Hashing embeddings need a non-zero dimension".into(),
+            ));
+        }
+        Ok(Self { dim, idf: None })
+    }
+
+    /// Weights buckets by `idf`, which must have been fitted for this dimension.
+    pub fn with_idf(mut self, idf: IdfWeights) -> Result<Self, DiskError> {
+        if idf.weights.len() != self.dim {
+            return Err(DiskError::DimensionMismatch {
+                expected: self.dim,
+                got: idf.weights.len(),
+            });
+        }
+        self.idf = Some(idf);
+        Ok(self)
+    }
+
+    /// The IDF weights in use, if any.
+    pub fn idf(&self) -> Option<&IdfWeights> {
+        self.idf.as_ref()
+    }
+
+    /// Fits IDF weights on `texts`, using the smoothed
+    /// `ln((1 + n) / (1 + df)) + 1` of every bucket.
+    pub fn fit_idf(&self, texts: &[&str]) -> IdfWeights {
+        let mut document_frequency = vec![0usize; self.dim];
+        let mut seen = vec![false; self.dim];
+        for text in texts {
+            seen.iter_mut().for_each(|s| *s = false);
+            self.features(text, |hash, _| seen[self.bucket(hash)] = true);
+            for (df, &seen) in document_frequency.iter_mut().zip(&seen) {
+                *df += seen as usize;
+            }
+        }
+        let n = texts.len() as f32;
+        IdfWeights {
+            documents: texts.len(),
+            weights: document_frequency
+                .iter()
+                .map(|&df| ((1.0 + n) / (1.0 + df as f32)).ln() + 1.0)
+                .collect(),
+        }
+    }
+
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0f32; self.dim];
+        self.features(text, |hash, weight| {
+            // The top bit picks a sign so colliding features tend to cancel out.
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[self.bucket(hash)] += sign * weight;
+        });
+        if let Some(idf) = &self.idf {
+            vector.iter_mut().zip(&idf.weights).for_each(|(x, w)| *x *= w);
+        }
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        vector
+    }
+
+    fn bucket(&self, hash: u64) -> usize {
+        (hash % self.dim as u64) as usize
+    }
+
+    /// Calls `emit` with the hash and weight of every feature of `text`: each
+    /// lowercased word, each pair of adjacent words at half weight, and the
+    /// character n-grams of each word, weighted so that they have the same norm
+    /// as the word itself.
+    fn features(&self, text: &str, mut emit: impl FnMut(u64, f32)) {
+        let lowercase = text.to_lowercase();
+        let words: Vec<&str> = lowercase
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        for word in &words {
+            emit(fnv1a(&[b"w:", word.as_bytes()]), 1.0);
+
+            let padded: Vec<char> = format!("<{}>", word).chars().collect();
+            let ngrams: Vec<String> = Self::CHAR_NGRAMS
+                .flat_map(|n| padded.windows(n).map(|w| w.iter().collect()))
+                .collect();
+            for ngram in &ngrams {
+                emit(fnv1a(&[b"c:", ngram.as_bytes()]), (ngrams.len() as f32).sqrt().recip());
+            }
+        }
+        for pair in words.windows(2) {
+            emit(fnv1a(&[b"b:", pair[0].as_bytes(), b" ", pair[1].as_bytes()]), 0.5);
+        }
+    }
+}
+
+impl Embedder for HashingEmbedder {
+    fn model(&self) -> &str {
+        Self::MODEL
+    }
+
+    fn dim(&self) -> usize {
+        self.dim
+    }
+
+    fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, DiskError> {
+        Ok(texts.iter().map(|text| self.embed_one(text)).collect())
+    }
+}
+
+/// 64-bit FNV-1a over the concatenation of `parts`, which is stable across
+/// platforms and releases unlike `std`'s hasher.
+fn fnv1a(parts: &[&[u8]]) -> u64 {
+    let mut hash = 0xcbf29ce484222325u64;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value as Json;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
+    /// A reply from the mock server: a status, a body and how long to wait
+    /// before sending them.
+    struct Reply {
+        status: u16,
+        body: String,
+        delay: Duration,
+    }
+
+    impl Reply {
+        /// A successful response with one `dim`-dimensional embedding per
+        /// input, listed in reverse order. Each vector is filled with the
+        /// length of its input text.
+        fn embeddings(request: &Json, dim: usize) -> Self {
+            let inputs = request["input"].as_array().unwrap();
+            let data: Vec<Json> = inputs
+                .iter()
+                .enumerate()
+                .rev()
+                .map(|(index, text)| {
+                    let len = text.as_str().unwrap().len() as f32;
+                    json!({ "index": index, "embedding": vec![len; dim] })
+                })
+                .collect();
+            Reply { status: 200, body: json!({ "data": data }).to_string(), delay: Duration::ZERO }
+        }
+
+        fn status(status: u16) -> Self {
+            Reply { status, body: "{}".into(), delay: Duration::ZERO }
+        }
+    }
+
+    /// When each request reached the mock server, and its JSON body.
+    type RequestLog = Arc<Mutex<Vec<(Instant, Json)>>>;
+
+    /// Serves `reply(n, body)` for the `n`th request on a local port, one
+    /// thread per connection.
+    fn serve(reply: impl Fn(usize, &Json) -> Reply + Send + Sync + 'static) -> (String, RequestLog) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = RequestLog::default();
+        let log = Arc::clone(&requests);
+        let reply = Arc::new(reply);
+        thread::spawn(move || {
+            for (n, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                let log = Arc::clone(&log);
+                let reply = Arc::clone(&reply);
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut content_length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        let line = line.trim_end();
+                        if line.is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    let mut body = vec![0; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    let body: Json = serde_json::from_slice(&body).unwrap();
+                    log.lock().unwrap().push((Instant::now(), body.clone()));
+
+                    let reply = reply(n, &body);
+                    thread::sleep(reply.delay);
+                    // The client may have given up already.
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        reply.status,
+                        reply.body.len(),
+                        reply.body
+                    );
+                });
+            }
+        });
+        (url, requests)
+    }
+
+    fn input_lens(requests: &[(Instant, Json)]) -> Vec<usize> {
+        requests.iter().map(|(_, body)| body["input"].as_array().unwrap().len()).collect()
+    }
+
+    #[test]
+    fn openai_embedder_splits_texts_into_batches() {
+        let (url, requests) = serve(|_, body| Reply::embeddings(body, 3));
+        let embedder = OpenAiEmbedder::new(&url, "mock", 3).with_batch_size(2);
+
+        let texts = ["a", "bb", "ccc", "dddd", "eeeee"];
+        let vectors = embedder.embed(&texts).unwrap();
+
+        assert_eq!(input_lens(&requests.lock().unwrap()), vec![2, 2, 1]);
+        let lens: Vec<f32> = vectors.iter().map(|v| v[0]).collect();
+        assert_eq!(lens, vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(requests.lock().unwrap()[0].1["model"], "mock");
+    }
+
+    #[test]
+    fn openai_embedder_retries_429_and_5xx_with_backoff() {
+        let (url, requests) = serve(|n, body| match n {
+            0 => Reply::status(429),
+            1 => Reply::status(503),
+            _ => Reply::embeddings(body, 2),
+        });
+        let backoff = Duration::from_millis(50);
+        let embedder = OpenAiEmbedder::new(&url, "mock", 2).with_retries(3, backoff);
+
+        let vectors = embedder.embed(&["text"]).unwrap();
+        assert_eq!(vectors, vec![vec![4.0, 4.0]]);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].0 - requests[0].0 >= backoff);
+        assert!(requests[2].0 - requests[1].0 >= backoff * 2);
+    }
+
+    #[test]
+    fn openai_embedder_gives_up_after_max_retries() {
+        let (url, requests) = serve(|_, _| Reply::status(500));
+        let embedder = OpenAiEmbedder::new(&url, "mock", 2).with_retries(2, Duration::from_millis(1));
+
+        let error = embedder.embed(&["text"]).unwrap_err();
+        assert!(error.to_string().contains("HTTP 500"), "{error}");
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn openai_embedder_does_not_retry_client_errors() {
+        let (url, requests) = serve(|_, _| Reply::status(400));
+        let embedder = OpenAiEmbedder::new(&url, "mock", 2).with_retries(3, Duration::from_millis(1));
+
+        let error = embedder.embed(&["text"]).unwrap_err();
+        assert!(error.to_string().contains("HTTP 400"), "{error}");
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn openai_embedder_times_out_slow_requests() {
+        let (url, requests) = serve(|_, body| Reply {
+            delay: Duration::from_secs(2),
+            ..Reply::embeddings(body, 2)
+        });
+        let embedder = OpenAiEmbedder::new(&url, "mock", 2)
+            .with_timeout(Duration::from_millis(100))
+            .with_retries(1, Duration::from_millis(1));
+
+        let started = Instant::now();
+        let error = embedder.embed(&["text"]).unwrap_err();
+        assert!(matches!(error, DiskError::Embedding(_)), "{error}");
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn openai_embedder_rejects_vectors_of_the_wrong_dimension() {
+        let (url, _) = serve(|_, body| Reply::embeddings(body, 3));
+        let embedder = OpenAiEmbedder::new(&url, "mock", 4);
+
+        let error = embedder.embed(&["text"]).unwrap_err();
+        assert!(matches!(error, DiskError::DimensionMismatch { expected: 4, got: 3 }), "{error}");
+    }
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[test]
+    fn hashing_embedder_needs_a_dimension() {
+        assert!(matches!(HashingEmbedder::new(0), Err(DiskError::InvalidData(_))));
+    }
+
+    #[test]
+    fn hashing_embeddings_are_deterministic_unit_vectors() {
+        let embedder = HashingEmbedder::new(512).unwrap();
+        let texts = ["The quick brown fox", "the QUICK brown fox!", "lorem ipsum", ""];
+        let vectors = embedder.embed(&texts).unwrap();
+        assert_eq!(vectors, HashingEmbedder::new(512).unwrap().embed(&texts).unwrap());
+
+        for vector in &vectors[..3] {
+            assert_eq!(vector.len(), 512);
+            assert!((cosine(vector, vector) - 1.0).abs() < 1e-5);
+        }
+        // Text without features embeds as the zero vector rather than NaN.
+        assert_eq!(vectors[3], vec![0.0; 512]);
+
+        // Case and punctuation are ignored; shared words score as similar.
+        assert!((cosine(&vectors[0], &vectors[1]) - 1.0).abs() < 1e-5);
+        let quick_fox = embedder.embed(&["a quick fox"]).unwrap().remove(0);
+        assert!(cosine(&vectors[0], &quick_fox) > cosine(&vectors[0], &vectors[2]));
+    }
+
+    #[test]
+    fn idf_weights_must_match_the_dimension() {
+        let embedder = HashingEmbedder::new(32).unwrap();
+        let corpus = ["common words here", "common words there", "rare zebra"];
+        let idf = embedder.fit_idf(&corpus);
+        assert_eq!(idf.documents, 3);
+        assert_eq!(idf.weights.len(), 32);
+        // Buckets of features in every text get the lowest weight, `1`.
+        assert!(idf.weights.iter().all(|&w| w >= 1.0 - 1e-6));
+
+        let weighted = embedder.clone().with_idf(idf.clone()).unwrap();
+        assert_eq!(weighted.idf(), Some(&idf));
+        let vector = weighted.embed(&["rare zebra words"]).unwrap().remove(0);
+        assert!((cosine(&vector, &vector) - 1.0).abs() < 1e-5);
+        assert_ne!(vector, embedder.embed(&["rare zebra words"]).unwrap().remove(0));
+
+        let error = HashingEmbedder::new(16).unwrap().with_idf(idf.clone()).unwrap_err();
+        assert!(matches!(error, DiskError::DimensionMismatch { expected: 16, got: 32 }), "{error}");
+    }
+}
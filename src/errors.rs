@@ -25,6 +25,46 @@ pub enum DiskError {
     #[error("Chunk or resource not found: {0}")]
     NotFound(String),
 
+    #[error("Invalid model signature: {0}")]
+    InvalidSignature(String),
+
+    #[error("Unsupported model signature: {0}")]
+    UnsupportedSignature(String),
+
+    #[error("Vector dimension mismatch: expected {expected}, got {got}")]
+    DimensionMismatch { expected: usize, got: usize },
+
+    #[error("Corrupt vector stored for chunk {chunk_id}")]
+    CorruptVector { chunk_id: String },
+
+    #[error("Unsupported spec version {found} (this library writes {supported})")]
+    SpecVersion { found: String, supported: String },
+
+    #[error("Manifest key '{0}' is managed by the library")]
+    ReservedManifestKey(String),
+
+    #[error("Invalid metadata filter: {0}")]
+    InvalidFilter(String),
+
+    #[error("Batch item {index} failed: {source}")]
+    BatchItem {
+        index: usize,
+        #[source]
+        source: Box<DiskError>,
+    },
+
+    #[error("Connection pool error: {0}")]
+    Pool(#[from] r2d2::Error),
+
+    #[error("Blocking task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+
+    #[error("Blocking call was cancelled")]
+    Cancelled,
+
+    #[error("Embedding error: {0}")]
+    Embedding(String),
+
     #[error("HNSW_RS error: {0}")]
     Hnsw(String), // hnsw_rs errors are often strings or require specific handling
 }
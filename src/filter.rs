@@ -0,0 +1,435 @@
+use std::cmp::Ordering;
+
+use rusqlite::types::Value as SqlValue;
+use serde_json::{Map, Value as Json};
+
+use crate::errors::DiskError;
+
+/// A parsed metadata filter, matched against the JSON `metadata` of a chunk.
+///
+/// Filters are written as JSON objects in a MongoDB-like syntax:
+///
+/// * `{"source_file": "a.md"}` - equality on a field.
+/// * `{"chunk_index": {"$gte": 10, "$lt": 50}}` - comparisons with `$eq`, `$ne`,
+///   `$gt`, `$gte`, `$lt` and `$lte`. Numbers compare with numbers and strings
+///   with strings; any other pairing never matches.
+/// * `{"lang": {"$in": ["en", "de"]}}` and `$nin` - membership in a list.
+/// * `{"reviewed": {"$exists": true}}` - presence of a field.
+/// * `{"$and": [...]}`, `{"$or": [...]}` and `{"$not": {...}}` - combinators.
+///
+/// Several entries in one object must all match. Field names may use dots to
+/// reach into nested objects, e.g. `"source.page"`, and cannot contain `"`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataFilter {
+    And(Vec<MetadataFilter>),
+    Or(Vec<MetadataFilter>),
+    Not(Box<MetadataFilter>),
+    Field { path: String, condition: Condition },
+}
+
+/// A test applied to the value found at a field path.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Eq(Json),
+    Ne(Json),
+    Gt(Json),
+    Gte(Json),
+    Lt(Json),
+    Lte(Json),
+    In(Vec<Json>),
+    Nin(Vec<Json>),
+    Exists(bool),
+}
+
+impl MetadataFilter {
+    /// Parses a filter from its JSON form.
+    pub fn from_json(filter: &Json) -> Result<Self, DiskError> {
+        let object = filter
+            .as_object()
+            .ok_or_else(|| invalid(format!("expected an object, got {}", filter)))?;
+        let mut clauses = Vec::with_capacity(object.len());
+        for (key, value) in object {
+            clauses.push(match key.as_str() {
+                "$and" => MetadataFilter::And(Self::parse_list(key, value)?),
+                "$or" => MetadataFilter::Or(Self::parse_list(key, value)?),
+                "$not" => MetadataFilter::Not(Box::new(Self::from_json(value)?)),
+                op if op.starts_with('$') => return Err(invalid(format!("unknown operator '{}'", op))),
+                path => Self::parse_field(path, value)?,
+            });
+        }
+        Ok(if clauses.len() == 1 {
+            clauses.remove(0)
+        } else {
+            MetadataFilter::And(clauses)
+        })
+    }
+
+    fn parse_list(op: &str, value: &Json) -> Result<Vec<MetadataFilter>, DiskError> {
+        value
+            .as_array()
+            .ok_or_else(|| invalid(format!("'{}' expects an array of filters", op)))?
+            .iter()
+            .map(Self::from_json)
+            .collect()
+    }
+
+    /// Parses the conditions on one field. A non-operator value is an equality test.
+    fn parse_field(path: &str, value: &Json) -> Result<MetadataFilter, DiskError> {
+        // SQLite JSON paths have no way to escape a quote inside a key.
+        if path.contains('"') {
+            return Err(invalid(format!("field name '{}' contains '\"'", path)));
+        }
+        let field = |condition| MetadataFilter::Field {
+            path: path.to_string(),
+            condition,
+        };
+        let Some(operators) = value.as_object().filter(|o| is_operator_object(o)) else {
+            return Ok(field(Condition::Eq(value.clone())));
+        };
+
+        let mut conditions = Vec::with_capacity(operators.len());
+        for (op, operand) in operators {
+            let list = || {
+                operand
+                    .as_array()
+                    .cloned()
+                    .ok_or_else(|| invalid(format!("'{}' on '{}' expects an array", op, path)))
+            };
+            conditions.push(field(match op.as_str() {
+                "$eq" => Condition::Eq(operand.clone()),
+                "$ne" => Condition::Ne(operand.clone()),
+                "$gt" => Condition::Gt(operand.clone()),
+                "$gte" => Condition::Gte(operand.clone()),
+                "$lt" => Condition::Lt(operand.clone()),
+                "$lte" => Condition::Lte(operand.clone()),
+                "$in" => Condition::In(list()?),
+                "$nin" => Condition::Nin(list()?),
+                "$exists" => Condition::Exists(operand.as_bool().ok_or_else(|| {
+                    invalid(format!("'$exists' on '{}' expects a boolean", path))
+                })?),
+                _ => return Err(invalid(format!("unknown operator '{}' on '{}'", op, path))),
+            }));
+        }
+        Ok(if conditions.len() == 1 {
+            conditions.remove(0)
+        } else {
+            MetadataFilter::And(conditions)
+        })
+    }
+
+    /// Whether a chunk with this metadata passes the filter.
+    pub fn matches(&self, metadata: &Json) -> bool {
+        match self {
+            MetadataFilter::And(filters) => filters.iter().all(|f| f.matches(metadata)),
+            MetadataFilter::Or(filters) => filters.iter().any(|f| f.matches(metadata)),
+            MetadataFilter::Not(filter) => !filter.matches(metadata),
+            MetadataFilter::Field { path, condition } => {
+                condition.test(lookup(metadata, path))
+            }
+        }
+    }
+
+    /// Translates the filter into an SQL condition on the JSON text in
+    /// `column`, appending its parameters to `params`.
+    ///
+    /// The condition is never NULL and agrees with [`MetadataFilter::matches`]
+    /// on metadata written by this library; `column` must hold valid JSON.
+    pub(crate) fn to_sql(&self, column: &str, params: &mut Vec<SqlValue>) -> String {
+        let mut join = |filters: &[MetadataFilter], separator: &str, empty: &str| {
+            if filters.is_empty() {
+                return empty.to_string();
+            }
+            let clauses: Vec<String> = filters.iter().map(|f| f.to_sql(column, params)).collect();
+            format!("({})", clauses.join(separator))
+        };
+        match self {
+            MetadataFilter::And(filters) => join(filters, " AND ", "1"),
+            MetadataFilter::Or(filters) => join(filters, " OR ", "0"),
+            MetadataFilter::Not(filter) => format!("NOT {}", filter.to_sql(column, params)),
+            MetadataFilter::Field { path, condition } => {
+                let field = SqlField { column, path: json_path(path) };
+                condition.to_sql(&field, params)
+            }
+        }
+    }
+}
+
+impl TryFrom<&Json> for MetadataFilter {
+    type Error = DiskError;
+
+    fn try_from(filter: &Json) -> Result<Self, Self::Error> {
+        MetadataFilter::from_json(filter)
+    }
+}
+
+impl Condition {
+    fn test(&self, value: Option<&Json>) -> bool {
+        let ordered = |operand: &Json, accept: fn(Ordering) -> bool| {
+            value.and_then(|v| compare(v, operand)).is_some_and(accept)
+        };
+        match self {
+            Condition::Eq(operand) => value.is_some_and(|v| json_eq(v, operand)),
+            Condition::Ne(operand) => !value.is_some_and(|v| json_eq(v, operand)),
+            Condition::Gt(operand) => ordered(operand, Ordering::is_gt),
+            Condition::Gte(operand) => ordered(operand, Ordering::is_ge),
+            Condition::Lt(operand) => ordered(operand, Ordering::is_lt),
+            Condition::Lte(operand) => ordered(operand, Ordering::is_le),
+            Condition::In(list) => value.is_some_and(|v| list.iter().any(|o| json_eq(v, o))),
+            Condition::Nin(list) => !value.is_some_and(|v| list.iter().any(|o| json_eq(v, o))),
+            Condition::Exists(expected) => value.is_some() == *expected,
+        }
+    }
+}
+
+/// A metadata field as seen from SQL.
+struct SqlField<'a> {
+    column: &'a str,
+    path: String,
+}
+
+impl SqlField<'_> {
+    /// The field's JSON type name, or NULL when it is missing.
+    fn json_type(&self, params: &mut Vec<SqlValue>) -> String {
+        params.push(SqlValue::Text(self.path.clone()));
+        format!("json_type({}, ?)", self.column)
+    }
+
+    /// The field's value: SQL text or a number for scalars, minified JSON for
+    /// arrays and objects.
+    fn value(&self, params: &mut Vec<SqlValue>) -> String {
+        params.push(SqlValue::Text(self.path.clone()));
+        format!("json_extract({}, ?)", self.column)
+    }
+
+    /// Tests the field's value against `operand` with `op`, if the two have
+    /// comparable types.
+    fn compare(&self, op: &str, operand: &Json, params: &mut Vec<SqlValue>) -> String {
+        let (types, operand) = match operand {
+            Json::Number(n) => ("'integer', 'real'", SqlValue::Real(n.as_f64().unwrap_or(f64::NAN))),
+            Json::String(s) => ("'text'", SqlValue::Text(s.clone())),
+            _ => return "0".to_string(),
+        };
+        let json_type = self.json_type(params);
+        let value = self.value(params);
+        params.push(operand);
+        format!("({} IN ({}) IS 1 AND {} {} ?)", json_type, types, value, op)
+    }
+
+    /// The SQL form of [`json_eq`].
+    fn equals(&self, operand: &Json, params: &mut Vec<SqlValue>) -> String {
+        let json_type = match operand {
+            Json::Null => "'null'",
+            Json::Bool(true) => "'true'",
+            Json::Bool(false) => "'false'",
+            Json::Number(_) | Json::String(_) => return self.compare("=", operand, params),
+            Json::Array(_) => "'array'",
+            Json::Object(_) => "'object'",
+        };
+        let type_matches = format!("{} IS {}", self.json_type(params), json_type);
+        if !matches!(operand, Json::Array(_) | Json::Object(_)) {
+            return type_matches;
+        }
+        // Both sides are serialized by serde_json, so equal values have equal text.
+        let value = self.value(params);
+        params.push(SqlValue::Text(operand.to_string()));
+        format!("({} AND {} = ?)", type_matches, value)
+    }
+
+    fn equals_any(&self, list: &[Json], params: &mut Vec<SqlValue>) -> String {
+        if list.is_empty() {
+            return "0".to_string();
+        }
+        let clauses: Vec<String> = list.iter().map(|o| self.equals(o, params)).collect();
+        format!("({})", clauses.join(" OR "))
+    }
+}
+
+impl Condition {
+    fn to_sql(&self, field: &SqlField, params: &mut Vec<SqlValue>) -> String {
+        match self {
+            Condition::Eq(operand) => field.equals(operand, params),
+            Condition::Ne(operand) => format!("NOT {}", field.equals(operand, params)),
+            Condition::Gt(operand) => field.compare(">", operand, params),
+            Condition::Gte(operand) => field.compare(">=", operand, params),
+            Condition::Lt(operand) => field.compare("<", operand, params),
+            Condition::Lte(operand) => field.compare("<=", operand, params),
+            Condition::In(list) => field.equals_any(list, params),
+            Condition::Nin(list) => format!("NOT {}", field.equals_any(list, params)),
+            Condition::Exists(true) => format!("{} IS NOT NULL", field.json_type(params)),
+            Condition::Exists(false) => format!("{} IS NULL", field.json_type(params)),
+        }
+    }
+}
+
+/// The SQLite JSON path of a dotted field path, with every key quoted.
+fn json_path(path: &str) -> String {
+    path.split('.').fold("$".to_string(), |json_path, key| format!("{}.\"{}\"", json_path, key))
+}
+
+/// An object is an operator object when all of its keys start with `$`.
+fn is_operator_object(object: &Map<String, Json>) -> bool {
+    !object.is_empty() && object.keys().all(|k| k.starts_with('$'))
+}
+
+/// Follows a dotted path into nested objects.
+fn lookup<'a>(metadata: &'a Json, path: &str) -> Option<&'a Json> {
+    path.split('.').try_fold(metadata, |value, key| value.get(key))
+}
+
+/// JSON equality that treats `1` and `1.0` as equal.
+fn json_eq(a: &Json, b: &Json) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => x == y,
+        _ => a == b,
+    }
+}
+
+/// Orders two numbers or two strings; other pairs are incomparable.
+fn compare(a: &Json, b: &Json) -> Option<Ordering> {
+    match (a, b) {
+        (Json::Number(x), Json::Number(y)) => x.as_f64()?.partial_cmp(&y.as_f64()?),
+        (Json::String(x), Json::String(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
+fn invalid(reason: String) -> DiskError {
+    DiskError::InvalidFilter(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+    use serde_json::json;
+
+    fn filter(filter: Json) -> MetadataFilter {
+        MetadataFilter::from_json(&filter).unwrap()
+    }
+
+    fn documents() -> Vec<Json> {
+        vec![
+            json!({ "lang": "en", "page": 1, "score": 0.5, "draft": true, "source": { "file": "a.md" } }),
+            json!({ "lang": "de", "page": 2.0, "tags": ["x", "y"], "draft": false }),
+            json!({ "lang": "fr", "page": 10, "reviewer": null, "source": { "file": "b.md", "page": 3 } }),
+            json!({ "lang": 1, "tags": { "k": "v" } }),
+            json!({}),
+        ]
+    }
+
+    /// The indices of the documents matched by `filter`, checking that the SQL
+    /// translation agrees with `matches`.
+    fn matching(filter_json: Json) -> Vec<usize> {
+        let filter = filter(filter_json);
+        let documents = documents();
+        let expected: Vec<usize> = (0..documents.len()).filter(|&i| filter.matches(&documents[i])).collect();
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE docs (i INTEGER, metadata TEXT)").unwrap();
+        for (i, document) in documents.iter().enumerate() {
+            conn.execute("INSERT INTO docs VALUES (?1, ?2)", rusqlite::params![i, document.to_string()])
+                .unwrap();
+        }
+        let mut params = Vec::new();
+        let sql = format!("SELECT i FROM docs WHERE {} ORDER BY i", filter.to_sql("metadata", &mut params));
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let from_sql: Vec<usize> = stmt
+            .query_map(rusqlite::params_from_iter(params), |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(from_sql, expected, "SQL disagrees with matches() for {}", sql);
+        expected
+    }
+
+    #[test]
+    fn equality_compares_numbers_by_value_and_other_types_exactly() {
+        assert_eq!(matching(json!({ "lang": "en" })), vec![0]);
+        assert_eq!(matching(json!({ "page": 2 })), vec![1]);
+        assert_eq!(matching(json!({ "page": { "$eq": 1.0 } })), vec![0]);
+        assert_eq!(matching(json!({ "lang": "1" })), Vec::<usize>::new());
+        assert_eq!(matching(json!({ "draft": true })), vec![0]);
+        assert_eq!(matching(json!({ "reviewer": null })), vec![2]);
+        assert_eq!(matching(json!({ "tags": { "$eq": ["x", "y"] } })), vec![1]);
+        assert_eq!(matching(json!({ "tags": { "$eq": { "k": "v" } } })), vec![3]);
+    }
+
+    #[test]
+    fn ne_matches_missing_fields() {
+        assert_eq!(matching(json!({ "lang": { "$ne": "en" } })), vec![1, 2, 3, 4]);
+        assert_eq!(matching(json!({ "draft": { "$ne": false } })), vec![0, 2, 3, 4]);
+    }
+
+    #[test]
+    fn comparisons_only_order_numbers_with_numbers_and_strings_with_strings() {
+        assert_eq!(matching(json!({ "page": { "$gt": 1 } })), vec![1, 2]);
+        assert_eq!(matching(json!({ "page": { "$gte": 2 } })), vec![1, 2]);
+        assert_eq!(matching(json!({ "page": { "$lt": 10 } })), vec![0, 1]);
+        assert_eq!(matching(json!({ "page": { "$lte": 10, "$gt": 1 } })), vec![1, 2]);
+        assert_eq!(matching(json!({ "lang": { "$gte": "en" } })), vec![0, 2]);
+        assert_eq!(matching(json!({ "lang": { "$gt": 0 } })), vec![3]);
+        assert_eq!(matching(json!({ "draft": { "$gt": false } })), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn in_and_nin_test_membership() {
+        assert_eq!(matching(json!({ "lang": { "$in": ["en", "fr", 1] } })), vec![0, 2, 3]);
+        assert_eq!(matching(json!({ "lang": { "$in": [] } })), Vec::<usize>::new());
+        assert_eq!(matching(json!({ "lang": { "$nin": ["en", "de"] } })), vec![2, 3, 4]);
+        assert_eq!(matching(json!({ "lang": { "$nin": [] } })), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn exists_tests_presence_including_null_values() {
+        assert_eq!(matching(json!({ "reviewer": { "$exists": true } })), vec![2]);
+        assert_eq!(matching(json!({ "tags": { "$exists": false } })), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn dotted_paths_reach_into_nested_objects() {
+        assert_eq!(matching(json!({ "source.file": "b.md" })), vec![2]);
+        assert_eq!(matching(json!({ "source.page": { "$exists": true } })), vec![2]);
+        assert_eq!(matching(json!({ "tags.0": "x" })), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn combinators_nest() {
+        assert_eq!(matching(json!({ "$and": [{ "lang": "en" }, { "page": 1 }] })), vec![0]);
+        assert_eq!(matching(json!({ "$or": [{ "lang": "de" }, { "page": 10 }] })), vec![1, 2]);
+        assert_eq!(matching(json!({ "$not": { "lang": { "$in": ["en", "de"] } } })), vec![2, 3, 4]);
+        assert_eq!(matching(json!({ "lang": "en", "draft": false })), Vec::<usize>::new());
+        assert_eq!(matching(json!({ "$and": [] })), vec![0, 1, 2, 3, 4]);
+        assert_eq!(matching(json!({ "$or": [] })), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn malformed_filters_are_rejected() {
+        for malformed in [
+            json!(["lang", "en"]),
+            json!("lang"),
+            json!({ "$xor": [] }),
+            json!({ "$and": { "lang": "en" } }),
+            json!({ "$or": [1] }),
+            json!({ "$not": "en" }),
+            json!({ "lang": { "$in": "en" } }),
+            json!({ "lang": { "$nin": { "en": 1 } } }),
+            json!({ "lang": { "$exists": 1 } }),
+            json!({ "lang": { "$regex": "e.*" } }),
+            json!({ "la\"ng": "en" }),
+        ] {
+            let error = MetadataFilter::from_json(&malformed).unwrap_err();
+            assert!(matches!(error, DiskError::InvalidFilter(_)), "{} gave {}", malformed, error);
+        }
+    }
+
+    #[test]
+    fn objects_mixing_operators_and_fields_are_equality_tests() {
+        assert_eq!(
+            filter(json!({ "source": { "$eq": 1, "file": "a.md" } })),
+            MetadataFilter::Field {
+                path: "source".into(),
+                condition: Condition::Eq(json!({ "$eq": 1, "file": "a.md" })),
+            }
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::errors::DiskError;
+
+/// An FTS5 index over `chunks.content`, maintained by triggers so every write to
+/// `chunks` keeps it in sync. Rows are keyed by `chunk_id` rather than the
+/// `chunks` rowid, which is not stable across `VACUUM`.
+const CREATE_FTS_SQL: &str = r#"
+CREATE VIRTUAL TABLE chunks_fts USING fts5(chunk_id UNINDEXED, content);
+
+CREATE TRIGGER IF NOT EXISTS chunks_fts_insert AFTER INSERT ON chunks BEGIN
+    INSERT INTO chunks_fts (chunk_id, content) VALUES (new.chunk_id, new.content);
+END;
+CREATE TRIGGER IF NOT EXISTS chunks_fts_update AFTER UPDATE OF chunk_id, content ON chunks BEGIN
+    DELETE FROM chunks_fts WHERE chunk_id = old.chunk_id;
+    INSERT INTO chunks_fts (chunk_id, content) VALUES (new.chunk_id, new.content);
+END;
+CREATE TRIGGER IF NOT EXISTS chunks_fts_delete AFTER DELETE ON chunks BEGIN
+    DELETE FROM chunks_fts WHERE chunk_id = old.chunk_id;
+END;
+"#;
+
+/// Creates the keyword index if the disk does not have one yet, back-filling it
+/// from the chunks already stored. Run inside a transaction so a partial
+/// back-fill is never left behind.
+pub(crate) fn ensure_fts(conn: &Connection) -> Result<(), DiskError> {
+    let exists = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'chunks_fts'",
+            [],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if exists {
+        return Ok(());
+    }
+
+    conn.execute_batch(CREATE_FTS_SQL)?;
+    conn.execute(
+        "INSERT INTO chunks_fts (chunk_id, content) SELECT chunk_id, content FROM chunks",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Turns free text into an FTS5 query matching chunks that contain every term,
+/// or any term when `match_any` is set.
+///
+/// Each whitespace-separated term is quoted so punctuation such as the dashes
+/// in error codes is matched literally instead of parsed as query syntax. Text
+/// inside double quotes is kept together as an exact phrase.
+pub(crate) fn to_match_query(text: &str, match_any: bool) -> String {
+    let mut terms = Vec::new();
+    for (i, part) in text.split('"').enumerate() {
+        if i % 2 == 1 {
+            // Inside a pair of quotes: one phrase.
+            if !part.trim().is_empty() {
+                terms.push(part);
+            }
+        } else {
+            terms.extend(part.split_whitespace());
+        }
+    }
+    terms
+        .iter()
+        .map(|term| format!("\"{}\"", term))
+        .collect::<Vec<_>>()
+        .join(if match_any { " OR " } else { " " })
+}
@@ -0,0 +1,369 @@
+use std::fs;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use hnsw_rs::hnswio::HnswIo;
+use hnsw_rs::prelude::*;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::errors::DiskError;
+use crate::models::{IndexConfig, ModelSignature};
+
+/// Basename used for the temporary dump files.
+const DUMP_BASENAME: &str = "idz_graph";
+
+/// A reloaded graph together with its HNSW id to `chunk_id` map.
+pub(crate) type StoredGraph<T, D> = (Graph<T, D>, Vec<Option<String>>);
+
+/// An HNSW graph that owns the loader it was reloaded from, if any.
+///
+/// hnsw_rs ties a reloaded graph to the lifetime of its `HnswIo`, so the loader
+/// is kept here for as long as the graph lives instead of being leaked.
+///
+/// The `'static` lifetime of `hnsw` is a lie for reloaded graphs: it borrows
+/// from `_loader`. This is sound only while `hnsw` is dropped before
+/// `_loader` and never moved out, which `Drop` enforces below rather than
+/// leaving it to field declaration order.
+pub struct Graph<T, D>
+where
+    T: Clone + Send + Sync + 'static,
+    D: Distance<T>,
+{
+    hnsw: ManuallyDrop<Hnsw<'static, T, D>>,
+    _loader: Option<Arc<HnswIo>>,
+}
+
+impl<T, D> From<Hnsw<'static, T, D>> for Graph<T, D>
+where
+    T: Clone + Send + Sync + 'static,
+    D: Distance<T>,
+{
+    fn from(hnsw: Hnsw<'static, T, D>) -> Self {
+        Graph { hnsw: ManuallyDrop::new(hnsw), _loader: None }
+    }
+}
+
+impl<T, D> Drop for Graph<T, D>
+where
+    T: Clone + Send + Sync + 'static,
+    D: Distance<T>,
+{
+    fn drop(&mut self) {
+        // SAFETY: `hnsw` is not used after this, and it is dropped while the
+        // loader it may borrow from is still alive; `_loader` is only dropped
+        // once this returns.
+        unsafe { ManuallyDrop::drop(&mut self.hnsw) }
+    }
+}
+
+impl<T, D> Deref for Graph<T, D>
+where
+    T: Clone + Send + Sync + 'static,
+    D: Distance<T>,
+{
+    type Target = Hnsw<'static, T, D>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.hnsw
+    }
+}
+
+/// Built graphs are stored next to a checksum of the `indices` rows they were
+/// built from, and are only reused while that checksum still matches.
+pub(crate) const CREATE_GRAPH_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS hnsw_graphs (
+    model_signature TEXT PRIMARY KEY,
+    checksum TEXT NOT NULL,
+    id_map TEXT NOT NULL, -- JSON array mapping HNSW ids to chunk_ids (null = tombstone)
+    graph BLOB NOT NULL,
+    data BLOB NOT NULL
+);
+"#;
+
+/// hnsw_rs can only dump graphs built with the maximum number of layers.
+pub(crate) fn can_persist(index_config: &IndexConfig) -> bool {
+    index_config.max_layers == IndexConfig::MAX_LAYERS
+}
+
+/// Computes a checksum over every `indices` row stored for `model_signature`
+/// and the construction parameters of `index_config`, so a graph is rebuilt
+/// when either changes.
+///
+/// # Returns
+/// The hex checksum and the number of rows it covers.
+pub(crate) fn indices_checksum(
+    conn: &Connection,
+    model_signature: &ModelSignature,
+    index_config: &IndexConfig,
+) -> Result<(String, usize), DiskError> {
+    let mut stmt = conn.prepare(
+        "SELECT chunk_id, data FROM indices WHERE model_signature = ?1 ORDER BY chunk_id",
+    )?;
+    let mut rows = stmt.query(params![model_signature])?;
+
+    let mut checksum = IndicesChecksum::new(index_config);
+    while let Some(row) = rows.next()? {
+        let chunk_id: String = row.get(0)?;
+        let data = row.get_ref(1)?.as_blob().map_err(rusqlite::Error::from)?;
+        checksum.add(&chunk_id, data);
+    }
+    Ok(checksum.finish())
+}
+
+/// The checksum of [`indices_checksum`], fed one `indices` row at a time in
+/// `chunk_id` order, for callers that read the rows themselves.
+pub(crate) struct IndicesChecksum {
+    hasher: blake3::Hasher,
+    count: usize,
+}
+
+impl IndicesChecksum {
+    pub(crate) fn new(index_config: &IndexConfig) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        for param in [
+            index_config.max_nb_connection,
+            index_config.ef_construction,
+            index_config.max_layers,
+        ] {
+            hasher.update(&(param as u64).to_le_bytes());
+        }
+        IndicesChecksum { hasher, count: 0 }
+    }
+
+    pub(crate) fn add(&mut self, chunk_id: &str, data: &[u8]) {
+        self.hasher.update(chunk_id.as_bytes());
+        self.hasher.update(&[0]);
+        self.hasher.update(&(data.len() as u64).to_le_bytes());
+        self.hasher.update(data);
+        self.count += 1;
+    }
+
+    /// The hex checksum and the number of rows it covers.
+    pub(crate) fn finish(self) -> (String, usize) {
+        (self.hasher.finalize().to_hex().to_string(), self.count)
+    }
+}
+
+/// SQLite's `data_version` of `conn`, which changes whenever another connection
+/// commits to the database.
+pub(crate) fn data_version(conn: &Connection) -> Result<i64, DiskError> {
+    Ok(conn.query_row("PRAGMA data_version", [], |row| row.get(0))?)
+}
+
+/// Loads the stored graph for `model_signature` if it was built from `checksum`.
+///
+/// `distance` must be the distance the graph was built with. Returns `Ok(None)`
+/// when no graph is stored or the stored one is stale.
+pub(crate) fn load_graph<T, D>(
+    conn: &Connection,
+    model_signature: &ModelSignature,
+    checksum: &str,
+    distance: D,
+) -> Result<Option<StoredGraph<T, D>>, DiskError>
+where
+    T: 'static + Serialize + DeserializeOwned + Clone + Send + Sync + std::fmt::Debug,
+    D: Distance<T> + Send + Sync,
+{
+    let stored = conn
+        .query_row(
+            "SELECT id_map, graph, data FROM hnsw_graphs WHERE model_signature = ?1 AND checksum = ?2",
+            params![model_signature, checksum],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Vec<u8>>(1)?,
+                    row.get::<_, Vec<u8>>(2)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some((id_map_json, graph, data)) = stored else {
+        return Ok(None);
+    };
+    let id_map: Vec<Option<String>> = serde_json::from_str(&id_map_json)?;
+
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join(format!("{DUMP_BASENAME}.hnsw.graph")), graph)?;
+    fs::write(dir.path().join(format!("{DUMP_BASENAME}.hnsw.data")), data)?;
+
+    let loader = Arc::new(HnswIo::new(dir.path(), DUMP_BASENAME));
+    // SAFETY: the loader is never mutated, and the returned `Graph` holds it
+    // until after the graph borrowing from it has been dropped (see its `Drop`).
+    let io: &'static HnswIo = unsafe { &*Arc::as_ptr(&loader) };
+    let hnsw = io
+        .load_hnsw_with_dist::<T, D>(distance)
+        .map_err(|e| DiskError::Hnsw(e.to_string()))?;
+
+    Ok(Some((Graph { hnsw: ManuallyDrop::new(hnsw), _loader: Some(loader) }, id_map)))
+}
+
+/// Dumps `hnsw` and stores it for `model_signature`, replacing any previous graph.
+pub(crate) fn save_graph<T, D>(
+    conn: &Connection,
+    model_signature: &ModelSignature,
+    checksum: &str,
+    hnsw: &Hnsw<'static, T, D>,
+    id_map: &[Option<String>],
+) -> Result<(), DiskError>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync,
+    D: Distance<T> + Send + Sync,
+{
+    let dir = tempfile::tempdir()?;
+    let basename = hnsw
+        .file_dump(dir.path(), DUMP_BASENAME)
+        .map_err(|e| DiskError::Hnsw(e.to_string()))?;
+    let graph = fs::read(dir.path().join(format!("{basename}.hnsw.graph")))?;
+    let data = fs::read(dir.path().join(format!("{basename}.hnsw.data")))?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO hnsw_graphs (model_signature, checksum, id_map, graph, data)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            model_signature,
+            checksum,
+            serde_json::to_string(id_map)?,
+            graph,
+            data
+        ],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Weak;
+
+    use super::*;
+    use crate::embedding::{Embedder, HashingEmbedder};
+    use crate::models::{QueryVector, SearchMode, SearchOptions};
+    use crate::{FloatIndex, IdentityDisk, SearchIndex};
+
+    const SIGNATURE: &str = "local/hashing-16_fp32";
+
+    fn embed(text: &str) -> Vec<f32> {
+        HashingEmbedder::new(16).unwrap().embed(&[text]).unwrap().remove(0)
+    }
+
+    fn create_disk(path: &Path, chunks: usize) -> (IdentityDisk, Vec<String>) {
+        let mut disk = IdentityDisk::create(path, SIGNATURE).unwrap();
+        let chunk_ids = (0..chunks)
+            .map(|i| {
+                let content = format!("chunk {} of the test disk", i);
+                disk.add_chunk(&content, QueryVector::F32(&embed(&content)), None).unwrap()
+            })
+            .collect();
+        (disk, chunk_ids)
+    }
+
+    fn approximate(disk: &IdentityDisk, text: &str) -> Vec<(String, f32)> {
+        let options = SearchOptions::default().with_mode(SearchMode::Approximate);
+        disk.search_with_options(QueryVector::F32(&embed(text)), 5, &options)
+            .unwrap()
+            .into_iter()
+            .map(|result| (result.chunk.chunk_id, result.distance))
+            .collect()
+    }
+
+    /// Whether the stored graph was built from exactly the current rows.
+    fn stored_graph_is_current(disk: &IdentityDisk) -> bool {
+        let signature: ModelSignature = SIGNATURE.parse().unwrap();
+        let (checksum, _) = indices_checksum(&disk.conn, &signature, &IndexConfig::default()).unwrap();
+        disk.conn
+            .query_row("SELECT checksum FROM hnsw_graphs WHERE model_signature = ?1", params![signature], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()
+            .unwrap()
+            == Some(checksum)
+    }
+
+    #[test]
+    fn persisted_graphs_reload_with_identical_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        let (disk, _) = create_disk(&path, 40);
+        disk.persist_index().unwrap();
+        assert!(stored_graph_is_current(&disk));
+        let queries = ["chunk 3", "chunk 17 of the disk", "test disk"];
+        let before: Vec<_> = queries.iter().map(|q| approximate(&disk, q)).collect();
+        drop(disk);
+
+        let disk = IdentityDisk::open(&path, SIGNATURE).unwrap();
+        let after: Vec<_> = queries.iter().map(|q| approximate(&disk, q)).collect();
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn graphs_are_rebuilt_after_the_rows_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        let (mut disk, chunk_ids) = create_disk(&path, 20);
+        disk.persist_index().unwrap();
+
+        // Updating a chunk keeps the row count but changes the rows.
+        let content = "an entirely different text";
+        disk.update_chunk(&chunk_ids[0], content, QueryVector::F32(&embed(content)), None).unwrap();
+        drop(disk);
+        let disk = IdentityDisk::open(&path, SIGNATURE).unwrap();
+        assert!(stored_graph_is_current(&disk));
+        assert_eq!(approximate(&disk, content)[0].0, chunk_ids[0]);
+
+        // A change through another connection makes persisting rebuild rather
+        // than store the stale in-memory graph under the new rows' checksum.
+        let mut other = IdentityDisk::open(&path, SIGNATURE).unwrap();
+        let content = "yet another replacement";
+        other.update_chunk(&chunk_ids[1], content, QueryVector::F32(&embed(content)), None).unwrap();
+        disk.persist_index().unwrap();
+        assert!(stored_graph_is_current(&disk));
+        assert_eq!(approximate(&disk, content)[0].0, chunk_ids[1]);
+        drop((disk, other));
+        let disk = IdentityDisk::open(&path, SIGNATURE).unwrap();
+        assert_eq!(approximate(&disk, content)[0].0, chunk_ids[1]);
+    }
+
+    #[test]
+    fn vectors_inserted_into_a_reloaded_graph_are_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        let (disk, _) = create_disk(&path, 20);
+        disk.persist_index().unwrap();
+        drop(disk);
+
+        let mut disk = IdentityDisk::open(&path, SIGNATURE).unwrap();
+        assert!(loader(&disk).is_some());
+        let content = "a chunk added after reloading";
+        let chunk_id = disk.add_chunk(content, QueryVector::F32(&embed(content)), None).unwrap();
+        assert_eq!(approximate(&disk, content)[0].0, chunk_id);
+    }
+
+    /// The loader a reloaded graph borrows from, if the disk's graph was reloaded.
+    fn loader(disk: &IdentityDisk) -> Option<Weak<HnswIo>> {
+        match &*disk.index.read().unwrap() {
+            SearchIndex::F32(FloatIndex::Cosine(graph)) => graph._loader.as_ref().map(Arc::downgrade),
+            _ => unreachable!("{} is an fp32 cosine signature", SIGNATURE),
+        }
+    }
+
+    #[test]
+    fn dropping_a_reloaded_graph_frees_its_loader() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        let (disk, _) = create_disk(&path, 20);
+        disk.persist_index().unwrap();
+        let before = approximate(&disk, "chunk 3");
+        drop(disk);
+
+        // Searching and then dropping the graph must not touch a freed loader.
+        let disk = IdentityDisk::open(&path, SIGNATURE).unwrap();
+        let loader = loader(&disk).unwrap();
+        assert_eq!(approximate(&disk, "chunk 3"), before);
+        assert_eq!(loader.strong_count(), 1);
+        drop(disk);
+        assert!(loader.upgrade().is_none());
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+
+/// Maps HNSW internal sequential ids to chunk ids, and live chunks back to
+/// their node.
+///
+/// HNSW has no removal, so deleted chunks are tombstoned as `None` and
+/// filtered out during search. Dereferences to the `hnsw id -> chunk id`
+/// slice stored alongside persisted graphs.
+#[derive(Debug, Default)]
+pub(crate) struct IdMap {
+    chunk_ids: Vec<Option<String>>,
+    // The live HNSW id of each chunk, so tombstoning does not scan `chunk_ids`.
+    hnsw_ids: HashMap<String, usize>,
+}
+
+impl IdMap {
+    /// Appends a node for `chunk_id`, retiring any earlier node of the chunk.
+    pub(crate) fn push(&mut self, chunk_id: String) {
+        let hnsw_id = self.chunk_ids.len();
+        if let Some(previous) = self.hnsw_ids.insert(chunk_id.clone(), hnsw_id) {
+            self.chunk_ids[previous] = None;
+        }
+        self.chunk_ids.push(Some(chunk_id));
+    }
+
+    /// Tombstones the node of `chunk_id`.
+    ///
+    /// # Returns
+    /// Whether the chunk had a live node.
+    pub(crate) fn retire(&mut self, chunk_id: &str) -> bool {
+        match self.hnsw_ids.remove(chunk_id) {
+            Some(hnsw_id) => {
+                self.chunk_ids[hnsw_id] = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The number of live nodes.
+    pub(crate) fn live(&self) -> usize {
+        self.hnsw_ids.len()
+    }
+}
+
+impl From<Vec<Option<String>>> for IdMap {
+    fn from(chunk_ids: Vec<Option<String>>) -> Self {
+        let mut id_map = IdMap {
+            chunk_ids: Vec::with_capacity(chunk_ids.len()),
+            hnsw_ids: HashMap::with_capacity(chunk_ids.len()),
+        };
+        for chunk_id in chunk_ids {
+            match chunk_id {
+                Some(chunk_id) => id_map.push(chunk_id),
+                None => id_map.chunk_ids.push(None),
+            }
+        }
+        id_map
+    }
+}
+
+impl Deref for IdMap {
+    type Target = [Option<String>];
+
+    fn deref(&self) -> &[Option<String>] {
+        &self.chunk_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retiring_and_re_adding_a_chunk_leaves_one_live_node() {
+        let mut id_map = IdMap::from(vec![Some("a".to_string()), None, Some("b".to_string())]);
+        assert_eq!(id_map.live(), 2);
+
+        assert!(id_map.retire("a"));
+        assert!(!id_map.retire("a"));
+        assert!(!id_map.retire("missing"));
+        id_map.push("b".to_string());
+        assert_eq!(&*id_map, &[None, None, None, Some("b".to_string())]);
+        assert_eq!(id_map.live(), 1);
+
+        // A stored map listing a chunk twice keeps only its last node.
+        let id_map = IdMap::from(vec![Some("a".to_string()), Some("a".to_string())]);
+        assert_eq!(&*id_map, &[None, Some("a".to_string())]);
+        assert_eq!(id_map.live(), 1);
+    }
+}
@@ -1,10 +1,15 @@
 // Re-used and new imports aligned with the new spec.
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::Path;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
+use half::f16;
 use hnsw_rs::prelude::*;
 use rusqlite::backup;
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
 use serde_json::Value as Json;
 use uuid::Uuid;
 
@@ -14,23 +19,181 @@ use uuid::Uuid;
 pub mod errors;
 /// Defines the data models used in the library's public API.
 pub mod models;
+/// Stores built HNSW graphs inside the disk so they can be reloaded on open.
+mod graph;
+/// Maps HNSW node ids to chunk ids and back.
+mod id_map;
+/// Converts embeddings to and from their stored byte representation.
+mod codec;
+/// Scalar quantization of embeddings and the distances used on quantized indices.
+pub mod quantization;
+/// Distances backing the selectable metrics and the scores they report.
+pub mod distance;
+/// Parses and evaluates JSON filters over chunk metadata.
+pub mod filter;
+/// Keeps an FTS5 keyword index in sync with chunk contents.
+mod fts;
+/// Schema for the documents that chunks are grouped under.
+mod documents;
+/// Validates spec versions and upgrades older disks.
+pub mod migrations;
+/// Strategies for splitting source text into chunks.
+pub mod chunking;
+/// Turns text into embedding vectors through pluggable providers.
+pub mod embedding;
+/// A cloneable handle for sharing a disk between threads.
+pub mod shared;
+/// An async API that runs disk operations on tokio's blocking pool.
+pub mod async_disk;
 
+use crate::distance::{inner_product_distance, reported_distance, DistInnerProduct};
+use crate::embedding::{Embedder, HashingEmbedder, IdfWeights};
 use crate::errors::DiskError;
-use crate::models::{Chunk, QueryVector, SearchResult};
+use crate::filter::MetadataFilter;
+pub use crate::graph::Graph;
+use crate::id_map::IdMap;
+use crate::models::{
+    Chunk, Document, Dtype, Fusion, HybridWeights, IndexConfig, Metric, ModelSignature, NewChunk,
+    QueryVector, RankedScore, RecallReport, ReembedOptions, ReembedReport, ScoreMetric, SearchMode, SearchOptions,
+    SearchResult, SignatureInfo,
+};
+use crate::quantization::{binarize, DistBinary, DistInt8, Int8Calibration};
+use crate::shared::DiskConnection;
 
 /// An enum to hold a type-erased HNSW index.
 /// This allows the IdentityDisk to handle different vector types (f32, i8, etc.)
 /// discovered at runtime from the model_signature.
 pub enum SearchIndex { // Made public
-    F32(Hnsw<'static, f32, DistCosine>),
-    // TODO: Add variants for I8, F16 with appropriate distance metrics
-    // I8(Hnsw<i8, SomeIntDistance>),
-    None, // For disks opened without a supported index
+    F32(FloatIndex),
+    /// Vectors are stored as fp16 and widened to f32 for HNSW.
+    F16(FloatIndex),
+    /// Vectors are scalar-quantized to int8 with the disk's calibration.
+    I8(Graph<i8, DistInt8>),
+    /// Vectors are sign-quantized to packed bits and compared by Hamming distance.
+    Bin(Graph<u8, DistBinary>),
+}
+
+/// An f32 HNSW index built with the disk's metric.
+pub enum FloatIndex {
+    Cosine(Graph<f32, DistCosine>),
+    L2(Graph<f32, DistL2>),
+    Dot(Graph<f32, DistInnerProduct>),
+}
+
+impl FloatIndex {
+    fn search_filter(
+        &self,
+        query: &[f32],
+        knbn: usize,
+        ef: usize,
+        filter: Option<&dyn FilterT>,
+    ) -> Vec<Neighbour> {
+        match self {
+            FloatIndex::Cosine(hnsw) => hnsw.search_filter(query, knbn, ef, filter),
+            FloatIndex::L2(hnsw) => hnsw.search_filter(query, knbn, ef, filter),
+            FloatIndex::Dot(hnsw) => hnsw.search_filter(query, knbn, ef, filter),
+        }
+    }
+
+    fn insert(&self, data: &Vec<(&[f32], usize)>) {
+        match self {
+            FloatIndex::Cosine(hnsw) => IdentityDisk::insert_into_graph(hnsw, data),
+            FloatIndex::L2(hnsw) => IdentityDisk::insert_into_graph(hnsw, data),
+            FloatIndex::Dot(hnsw) => IdentityDisk::insert_into_graph(hnsw, data),
+        }
+    }
+
+    fn save(
+        &self,
+        conn: &Connection,
+        model_signature: &ModelSignature,
+        checksum: &str,
+        id_map: &[Option<String>],
+    ) -> Result<(), DiskError> {
+        match self {
+            FloatIndex::Cosine(hnsw) => graph::save_graph(conn, model_signature, checksum, hnsw, id_map),
+            FloatIndex::L2(hnsw) => graph::save_graph(conn, model_signature, checksum, hnsw, id_map),
+            FloatIndex::Dot(hnsw) => graph::save_graph(conn, model_signature, checksum, hnsw, id_map),
+        }
+    }
+}
+
+/// A vector in the element type of the active HNSW index.
+enum IndexVector<'v> {
+    F32(Cow<'v, [f32]>),
+    I8(Vec<i8>),
+    Bin(Cow<'v, [u8]>),
+}
+
+/// An embedding validated against the active signature and ready to be stored.
+struct EncodedEmbedding<'v> {
+    /// The blob stored under the active model signature.
+    blob: Vec<u8>,
+    /// The vector inserted into HNSW.
+    vector: IndexVector<'v>,
+    /// The full-precision blob kept alongside binary vectors for rescoring.
+    full_precision: Option<Vec<u8>>,
+}
+
+/// How far a re-embedding run has got, kept in the `manifest` between batches.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct ReembedProgress {
+    /// The last chunk embedded; chunks are embedded in `chunk_id` order.
+    cursor: Option<String>,
+    /// Chunks embedded so far, across resumed runs.
+    embedded: usize,
+}
+
+/// A re-embedding run in progress, advanced one batch at a time so a
+/// [`SharedDisk`](crate::shared::SharedDisk) only holds its writer while a
+/// batch is stored.
+pub(crate) struct ReembedRun {
+    target: ModelSignature,
+    /// The default signature to drop once every chunk is embedded.
+    old_default: Option<ModelSignature>,
+    batch_size: usize,
+    progress_key: String,
+    progress: ReembedProgress,
+    resumed: bool,
+    /// Chunks embedded by this run.
+    embedded: usize,
 }
 
 // --- Constants ---
 
-const SPEC_VERSION: &str = "1.0";
+/// Inserts or replaces the embedding of a chunk under one model signature.
+const UPSERT_INDEX_SQL: &str = "INSERT INTO indices (chunk_id, index_type, model_signature, data) VALUES (?1, ?2, ?3, ?4)
+    ON CONFLICT (chunk_id, model_signature) DO UPDATE SET data = excluded.data";
+
+/// How many Hamming candidates per requested result are rescored on binary disks.
+const BIN_RESCORE_FACTOR: usize = 4;
+
+/// How many candidates per requested result each retriever contributes to hybrid search.
+const HYBRID_DEPTH_FACTOR: usize = 4;
+
+/// Disks with at most this many vectors are searched exactly by default.
+const DEFAULT_EXACT_SEARCH_THRESHOLD: usize = 1_000;
+
+/// Selects `Document` rows with their chunk counts; callers append the
+/// `WHERE`, `GROUP BY` and `ORDER BY` clauses.
+const SELECT_DOCUMENTS_SQL: &str = "SELECT d.document_id, d.uri, d.title, d.content_hash, d.ingested_at, COUNT(c.chunk_id)
+    FROM documents d LEFT JOIN chunks c ON c.document_id = d.document_id";
+
+/// `manifest` keys managed by the library, which cannot be set or removed
+/// through the manifest API.
+const RESERVED_MANIFEST_KEYS: &[&str] = &[
+    "spec_version",
+    "model_signature",
+    "metric",
+    "index_config",
+    "migration_history",
+];
+
+/// Prefixes of library-managed per-signature `manifest` keys.
+const RESERVED_MANIFEST_PREFIXES: &[&str] = &["int8_calibration:", "hashing_idf:", "reembed:"];
+
+/// Library-managed `manifest` keys stored as plain text rather than JSON.
+const PLAIN_TEXT_MANIFEST_KEYS: &[&str] = &["spec_version", "model_signature", "metric"];
 
 const CREATE_DB_SQL: &str = r#"
 BEGIN;
@@ -71,12 +234,24 @@ COMMIT;
 /// This struct holds a connection to the SQLite database and manages an
 /// in-memory HNSW index for fast semantic search.
 pub struct IdentityDisk {
-    conn: Connection,
+    conn: DiskConnection,
     index: Arc<RwLock<SearchIndex>>,
-    // Maps the HNSW internal sequential ID to the database chunk_id (UUID)
-    id_to_chunk_id: Arc<RwLock<Vec<String>>>,
+    // Maps the HNSW internal sequential ID to the database chunk_id (UUID).
+    // HNSW has no removal, so deleted chunks are tombstoned as `None` and
+    // filtered out during search.
+    id_to_chunk_id: Arc<RwLock<IdMap>>,
     // The model signature this disk instance is actively managing
-    model_signature: String,
+    model_signature: ModelSignature,
+    // The number of live nodes in `id_to_chunk_id`, kept so
+    // `SearchMode::Auto` need not count them on every search.
+    live_vectors: Arc<AtomicUsize>,
+    // `SearchMode::Auto` scans exactly at or below this many live vectors.
+    exact_search_threshold: usize,
+    // HNSW parameters, persisted in the `manifest`.
+    index_config: IndexConfig,
+    // `PRAGMA data_version` of `conn` before the index was loaded. It changes
+    // when another connection commits, after which the index may be stale.
+    index_data_version: AtomicI64,
 }
 
 impl IdentityDisk {
@@ -87,23 +262,57 @@ impl IdentityDisk {
     /// # Arguments
     /// * `path` - The file path for the new disk.
     /// * `model_signature` - The model signature for the embeddings that will be stored.
-    ///   e.g., "openai/text-embedding-3-small-1536_fp16"
+    ///   e.g., "openai/text-embedding-3-small-1536_fp16". It is recorded in the
+    ///   `manifest` as the disk's default signature. An optional `_l2` or `_dot`
+    ///   suffix selects the distance metric, which is also recorded in the
+    ///   `manifest`; the default is cosine.
     pub fn create<P: AsRef<Path>>(path: P, model_signature: &str) -> Result<Self, DiskError> {
+        Self::create_with_config(path, model_signature, IndexConfig::default())
+    }
+
+    /// Creates a new, empty Identity Disk whose HNSW index uses `index_config`.
+    ///
+    /// The config is recorded in the `manifest` and used by every later `open`
+    /// that does not pass its own. See [`IdentityDisk::create`] for the other
+    /// arguments.
+    pub fn create_with_config<P: AsRef<Path>>(
+        path: P,
+        model_signature: &str,
+        index_config: IndexConfig,
+    ) -> Result<Self, DiskError> {
+        let model_signature: ModelSignature = model_signature.parse()?;
+        index_config.validate()?;
+
         // Ensure we overwrite by deleting if it exists
         if path.as_ref().exists() {
             std::fs::remove_file(&path)?;
         }
 
-        let conn = Connection::open(&path)?;
-        conn.execute_batch(&CREATE_DB_SQL.replace("?1", &format!("'{}'", SPEC_VERSION)))?;
+        let mut conn = Connection::open(&path)?;
+        conn.execute_batch(&CREATE_DB_SQL.replace("?1", &format!("'{}'", migrations::BASE_SPEC_VERSION)))?;
+        Self::configure_connection(&conn)?;
+        migrations::initialize(&mut conn)?;
+        conn.execute(
+            "INSERT INTO manifest (key, value) VALUES ('model_signature', ?1)",
+            params![&model_signature],
+        )?;
+        conn.execute(
+            "INSERT OR REPLACE INTO manifest (key, value) VALUES ('metric', ?1)",
+            params![model_signature.metric.as_str()],
+        )?;
+        Self::store_index_config(&conn, &index_config)?;
 
-        let (index, _) = Self::load_index_from_db(&conn, model_signature)?;
+        let (index, _, data_version) = Self::load_index_from_db(&conn, &model_signature, &index_config)?;
 
         Ok(Self {
-            conn,
+            conn: DiskConnection::Owned(conn),
             index: Arc::new(RwLock::new(index)),
-            id_to_chunk_id: Arc::new(RwLock::new(Vec::new())),
-            model_signature: model_signature.to_string(),
+            id_to_chunk_id: Arc::new(RwLock::new(IdMap::default())),
+            live_vectors: Arc::new(AtomicUsize::new(0)),
+            model_signature,
+            exact_search_threshold: DEFAULT_EXACT_SEARCH_THRESHOLD,
+            index_config,
+            index_data_version: AtomicI64::new(data_version),
         })
     }
 
@@ -113,19 +322,66 @@ impl IdentityDisk {
     /// into an in-memory HNSW index for fast searching. If the signature is not
     /// found, it will open the disk with an empty search index.
     ///
+    /// Disks written by an older spec version are migrated in place after a
+    /// backup is written next to them as `{path}.v{old_version}.bak`. Disks from
+    /// a newer major spec version fail with `DiskError::SpecVersion`.
+    ///
     /// # Arguments
     /// * `path` - The file path of the disk to open.
     /// * `model_signature` - The specific model signature to load for searching.
+    ///   Without a metric suffix it uses the metric recorded when the disk was
+    ///   created; an explicit suffix naming another metric is rejected.
     pub fn open<P: AsRef<Path>>(path: P, model_signature: &str) -> Result<Self, DiskError> {
-        // TODO: Validate spec version from manifest table
-        let conn = Connection::open(path)?;
+        Self::open_with_config(path, model_signature, None)
+    }
+
+    /// Opens an existing Identity Disk with its default model signature, see
+    /// [`IdentityDisk::default_model_signature`] and [`IdentityDisk::open`].
+    ///
+    /// Fails with `DiskError::NotFound` if the disk has no default signature.
+    pub fn open_default<P: AsRef<Path>>(path: P) -> Result<Self, DiskError> {
+        let model_signature = Self::read_default_signature(&Connection::open(&path)?)?
+            .ok_or_else(|| DiskError::NotFound("default model signature".into()))?;
+        Self::open(path, &model_signature)
+    }
+
+    /// Opens an existing Identity Disk, optionally replacing its HNSW parameters.
+    ///
+    /// With `Some(config)` the config is recorded in the `manifest` and the graph
+    /// is rebuilt if it was built with other construction parameters. With `None`
+    /// the recorded config is used. See [`IdentityDisk::open`] for the other
+    /// arguments.
+    pub fn open_with_config<P: AsRef<Path>>(
+        path: P,
+        model_signature: &str,
+        index_config: Option<IndexConfig>,
+    ) -> Result<Self, DiskError> {
+        // Fail on a malformed signature before touching the file.
+        Self::check_signature_shape(model_signature)?;
+        let mut conn = Connection::open(&path)?;
+        Self::configure_connection(&conn)?;
+        migrations::upgrade(&mut conn, Some(path.as_ref()))?;
+        let model_signature = Self::disk_signature(&conn, model_signature)?;
+        let index_config = match index_config {
+            Some(config) => {
+                config.validate()?;
+                Self::store_index_config(&conn, &config)?;
+                config
+            }
+            None => Self::load_index_config(&conn)?,
+        };
 
-        let (index, id_to_chunk_id) = Self::load_index_from_db(&conn, model_signature)?;
+        let (index, id_to_chunk_id, data_version) =
+            Self::load_index_from_db(&conn, &model_signature, &index_config)?;
         Ok(Self {
-            conn,
+            conn: DiskConnection::Owned(conn),
             index: Arc::new(RwLock::new(index)),
+            live_vectors: Arc::new(AtomicUsize::new(id_to_chunk_id.live())),
             id_to_chunk_id: Arc::new(RwLock::new(id_to_chunk_id)),
-            model_signature: model_signature.to_string(),
+            model_signature,
+            exact_search_threshold: DEFAULT_EXACT_SEARCH_THRESHOLD,
+            index_config,
+            index_data_version: AtomicI64::new(data_version),
         })
     }
 
@@ -133,6 +389,7 @@ impl IdentityDisk {
         path: P,
         model_signature: &str,
     ) -> Result<Self, DiskError> {
+        Self::check_signature_shape(model_signature)?;
         let disk_conn = Connection::open(path)?;
         let mut mem_conn = Connection::open_in_memory()?;
 
@@ -141,14 +398,24 @@ impl IdentityDisk {
             let backup = backup::Backup::new(&disk_conn, &mut mem_conn)?;
             backup.run_to_completion(5, std::time::Duration::from_millis(250), None)?;
         } // backup is dropped here, releasing the borrow
+        Self::configure_connection(&mem_conn)?;
+        // Only the in-memory copy is upgraded, so no backup is needed.
+        migrations::upgrade(&mut mem_conn, None)?;
+        let model_signature = Self::disk_signature(&mem_conn, model_signature)?;
+        let index_config = Self::load_index_config(&mem_conn)?;
 
-        let (index, id_to_chunk_id) = Self::load_index_from_db(&mem_conn, model_signature)?;
+        let (index, id_to_chunk_id, data_version) =
+            Self::load_index_from_db(&mem_conn, &model_signature, &index_config)?;
 
         Ok(Self {
-            conn: mem_conn,
+            conn: DiskConnection::Owned(mem_conn),
             index: Arc::new(RwLock::new(index)),
+            live_vectors: Arc::new(AtomicUsize::new(id_to_chunk_id.live())),
             id_to_chunk_id: Arc::new(RwLock::new(id_to_chunk_id)),
-            model_signature: model_signature.to_string(),
+            model_signature,
+            exact_search_threshold: DEFAULT_EXACT_SEARCH_THRESHOLD,
+            index_config,
+            index_data_version: AtomicI64::new(data_version),
         })
     }
 
@@ -172,24 +439,7 @@ impl IdentityDisk {
     ) -> Result<String, DiskError> {
         let chunk_id = Uuid::new_v4().to_string();
         let metadata_str = metadata.map_or("{}".to_string(), |j| j.to_string());
-
-        let embedding_bytes: Vec<u8>;
-        let vector_for_hnsw: &[f32]; // Temp, will be generic later
-
-        // Match the input vector to serialize it correctly
-        match embedding {
-            QueryVector::F32(v) => {
-                // Ensure the provided vector type matches the disk's index type
-                if !self.model_signature.ends_with("_fp32") && !self.model_signature.contains('_') {
-                    // default is fp32
-                    return Err(DiskError::InvalidData(
-                        "Mismatched vector type: expected fp32".into(),
-                    ));
-                }
-                embedding_bytes = v.iter().flat_map(|f| f.to_le_bytes()).collect();
-                vector_for_hnsw = v;
-            } // TODO: Add cases for I8, F16 etc.
-        }
+        let encoded = self.encode_embedding(&embedding)?;
 
         // Use a transaction for atomicity
         let tx = self.conn.transaction()?;
@@ -201,33 +451,222 @@ impl IdentityDisk {
         )?;
 
         // 2. Insert index
-        tx.execute(
-            "INSERT INTO indices (chunk_id, index_type, model_signature, data) VALUES (?1, ?2, ?3, ?4)",
-            params![&chunk_id, "vector_embedding", &self.model_signature, &embedding_bytes],
-        )?;
+        Self::write_embedding(&tx, &self.model_signature, &chunk_id, &encoded)?;
 
         tx.commit()?;
 
-        // Update in-memory HNSW index using enum dispatch
-        {
-            let mut id_map = self.id_to_chunk_id.write()?;
-            let new_hnsw_id = id_map.len();
-            let mut index = self.index.write()?;
+        self.index_vector(&chunk_id, encoded.vector)?;
 
-            match &mut *index {
-                SearchIndex::F32(ref mut hnsw) => {
-                    hnsw.insert((&vector_for_hnsw, new_hnsw_id));
-                }
-                SearchIndex::None => {
-                    // Cannot insert into a non-existent index.
-                    return Err(DiskError::InvalidData("No supported index loaded.".into()));
-                } // TODO: Handle other types
+        Ok(chunk_id)
+    }
+
+    /// Adds a batch of chunks and their embeddings to the disk.
+    ///
+    /// All rows are written in a single transaction and the vectors are inserted
+    /// into HNSW in parallel. If any item fails, the whole batch is rolled back and
+    /// a `DiskError::BatchItem` identifies the offending item.
+    ///
+    /// # Returns
+    /// The `chunk_id`s of the new chunks, in the same order as `chunks`.
+    pub fn add_chunks(&mut self, chunks: &[NewChunk]) -> Result<Vec<String>, DiskError> {
+        let encoded = self.encode_chunks(chunks)?;
+
+        let tx = self.conn.transaction()?;
+        let chunk_ids = Self::insert_chunks(&tx, &self.model_signature, chunks, &encoded, None)?;
+        tx.commit()?;
+
+        let vectors: Vec<IndexVector> = encoded.into_iter().map(|e| e.vector).collect();
+        self.index_vectors(&chunk_ids, &vectors)?;
+
+        Ok(chunk_ids)
+    }
+
+    /// Adds a document and its chunks, in order, to the disk.
+    ///
+    /// If a document with the same `uri` is already stored, its chunks and their
+    /// indices are replaced by `chunks` and its `document_id` is kept. The document
+    /// and all of its chunks are written in a single transaction.
+    ///
+    /// # Arguments
+    /// * `uri` - Identifies the source, e.g. a file path.
+    /// * `title` - An optional human-readable title.
+    /// * `content` - The full source text, recorded as a content hash.
+    /// * `chunks` - The chunks cut from `content`, in document order.
+    ///
+    /// # Returns
+    /// The `document_id` of the document.
+    pub fn add_document(
+        &mut self,
+        uri: &str,
+        title: Option<&str>,
+        content: &str,
+        chunks: &[NewChunk],
+    ) -> Result<String, DiskError> {
+        let encoded = self.encode_chunks(chunks)?;
+
+        let tx = self.conn.transaction()?;
+        let existing: Option<String> = tx
+            .query_row(
+                "SELECT document_id FROM documents WHERE uri = ?1",
+                params![uri],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let (document_id, replaced) = match existing {
+            Some(document_id) => {
+                let replaced = Self::document_chunk_ids(&tx, &document_id)?;
+                Self::delete_chunk_rows(&tx, &replaced)?;
+                (document_id, replaced)
             }
+            None => (Uuid::new_v4().to_string(), Vec::new()),
+        };
+        // An upsert rather than `INSERT OR REPLACE`, which would cascade to the
+        // new chunks.
+        tx.execute(
+            "INSERT INTO documents (document_id, uri, title, content_hash, ingested_at)
+                VALUES (?1, ?2, ?3, ?4, strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+                ON CONFLICT (document_id) DO UPDATE SET
+                    title = excluded.title,
+                    content_hash = excluded.content_hash,
+                    ingested_at = excluded.ingested_at",
+            params![&document_id, uri, title, documents::content_hash(content)],
+        )?;
+        let chunk_ids =
+            Self::insert_chunks(&tx, &self.model_signature, chunks, &encoded, Some(&document_id))?;
+        tx.commit()?;
 
-            id_map.push(chunk_id.clone());
+        self.tombstone(&replaced)?;
+        let vectors: Vec<IndexVector> = encoded.into_iter().map(|e| e.vector).collect();
+        self.index_vectors(&chunk_ids, &vectors)?;
+
+        Ok(document_id)
+    }
+
+    /// Encodes the embeddings of a batch, identifying the first invalid item.
+    fn encode_chunks<'v>(
+        &self,
+        chunks: &[NewChunk<'v>],
+    ) -> Result<Vec<EncodedEmbedding<'v>>, DiskError> {
+        chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                self.encode_embedding(&chunk.embedding)
+                    .map_err(|e| Self::batch_error(i, e))
+            })
+            .collect()
+    }
+
+    /// Wraps the error of one item in a batch with its position.
+    fn batch_error(index: usize, source: DiskError) -> DiskError {
+        DiskError::BatchItem {
+            index,
+            source: Box::new(source),
         }
+    }
 
-        Ok(chunk_id)
+    /// Writes the rows for a batch of chunks, optionally as the ordered chunks of
+    /// a document.
+    ///
+    /// # Returns
+    /// The new `chunk_id`s, in the same order as `chunks`.
+    fn insert_chunks(
+        conn: &Connection,
+        model_signature: &ModelSignature,
+        chunks: &[NewChunk],
+        encoded: &[EncodedEmbedding],
+        document_id: Option<&str>,
+    ) -> Result<Vec<String>, DiskError> {
+        let mut chunk_ids = Vec::with_capacity(chunks.len());
+        let mut insert_chunk = conn.prepare(
+            "INSERT INTO chunks (chunk_id, content, metadata, document_id, position) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (i, chunk) in chunks.iter().enumerate() {
+            let chunk_id = Uuid::new_v4().to_string();
+            let metadata_str = chunk
+                .metadata
+                .as_ref()
+                .map_or("{}".to_string(), |j| j.to_string());
+            let position = document_id.map(|_| i as i64);
+            insert_chunk
+                .execute(params![&chunk_id, chunk.content, &metadata_str, document_id, position])
+                .map_err(|e| Self::batch_error(i, e.into()))?;
+            Self::write_embedding(conn, model_signature, &chunk_id, &encoded[i])
+                .map_err(|e| Self::batch_error(i, e))?;
+            chunk_ids.push(chunk_id);
+        }
+        Ok(chunk_ids)
+    }
+
+    /// Replaces the content and embedding of an existing chunk, keeping its `chunk_id`.
+    ///
+    /// The `chunks` row and the `indices` row for the active model signature are
+    /// rewritten in one transaction. Embeddings stored under other model signatures
+    /// are left as they are; replace them with `add_embedding`.
+    ///
+    /// On binary signatures the fp32 copy kept for rescoring is rewritten from
+    /// an f32 embedding. A packed binary embedding cannot refresh it, so it is
+    /// rejected with `DiskError::InvalidData` for chunks that have such a copy.
+    ///
+    /// # Arguments
+    /// * `chunk_id` - The chunk to update.
+    /// * `content` - The new text content of the chunk.
+    /// * `embedding` - The embedding of the new content.
+    /// * `metadata` - New JSON metadata, or `None` to keep the existing metadata.
+    pub fn update_chunk(
+        &mut self,
+        chunk_id: &str,
+        content: &str,
+        embedding: QueryVector,
+        metadata: Option<Json>,
+    ) -> Result<(), DiskError> {
+        let encoded = self.encode_embedding(&embedding)?;
+
+        let tx = self.conn.transaction()?;
+
+        let rows_affected = match metadata {
+            Some(metadata) => tx.execute(
+                "UPDATE chunks SET content = ?1, metadata = ?2 WHERE chunk_id = ?3",
+                params![content, metadata.to_string(), chunk_id],
+            )?,
+            None => tx.execute(
+                "UPDATE chunks SET content = ?1 WHERE chunk_id = ?2",
+                params![content, chunk_id],
+            )?,
+        };
+        if rows_affected == 0 {
+            return Err(DiskError::NotFound(chunk_id.to_string()));
+        }
+
+        if self.model_signature.dtype == Dtype::Bin && encoded.full_precision.is_none() {
+            let has_full_precision = tx
+                .query_row(
+                    "SELECT 1 FROM indices WHERE chunk_id = ?1 AND model_signature = ?2",
+                    params![chunk_id, Self::rescoring_key(&self.model_signature)],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if has_full_precision {
+                return Err(DiskError::InvalidData(format!(
+                    "Chunk {} keeps an fp32 copy for rescoring; update it with an f32 embedding",
+                    chunk_id
+                )));
+            }
+        }
+        Self::write_embedding(&tx, &self.model_signature, chunk_id, &encoded)?;
+
+        tx.commit()?;
+
+        // Retire the stale HNSW node and index the new vector under a fresh id,
+        // under one lock so no search sees the chunk missing or twice.
+        let mut index = self.index.write()?;
+        let mut id_map = self.id_to_chunk_id.write()?;
+        let chunk_ids = [chunk_id.to_string()];
+        self.mark_dead(&mut id_map, &chunk_ids);
+        self.insert_vectors(&mut index, &mut id_map, &chunk_ids, &[encoded.vector])?;
+
+        Ok(())
     }
 
     /// Retrieves all chunks from the disk, without their vector embeddings.
@@ -244,6 +683,73 @@ impl IdentityDisk {
         Ok(chunks)
     }
 
+    /// Retrieves up to `limit` chunks in `chunk_id` order, starting after the
+    /// chunk `after` (or from the first chunk if `None`), for reading a disk
+    /// page by page.
+    pub fn get_chunks_page(&self, after: Option<&str>, limit: usize) -> Result<Vec<Chunk>, DiskError> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT chunk_id, content, metadata FROM chunks
+             WHERE ?1 IS NULL OR chunk_id > ?1 ORDER BY chunk_id LIMIT ?2",
+        )?;
+        let chunks = stmt
+            .query_map(params![after, limit as i64], |row| Chunk::try_from(row))?
+            .collect::<Result<_, _>>()?;
+        Ok(chunks)
+    }
+
+    /// Lists every document stored on the disk, ordered by URI.
+    pub fn list_documents(&self) -> Result<Vec<Document>, DiskError> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("{SELECT_DOCUMENTS_SQL} GROUP BY d.document_id ORDER BY d.uri"))?;
+        let documents = stmt
+            .query_map([], |row| Document::try_from(row))?
+            .collect::<Result<_, _>>()?;
+        Ok(documents)
+    }
+
+    /// Retrieves a single document by its `document_id`.
+    pub fn get_document(&self, document_id: &str) -> Result<Document, DiskError> {
+        self.conn
+            .query_row(
+                &format!("{SELECT_DOCUMENTS_SQL} WHERE d.document_id = ?1 GROUP BY d.document_id"),
+                params![document_id],
+                |row| Document::try_from(row),
+            )
+            .optional()?
+            .ok_or_else(|| DiskError::NotFound(document_id.to_string()))
+    }
+
+    /// Retrieves the chunks of a document in document order.
+    pub fn get_document_chunks(&self, document_id: &str) -> Result<Vec<Chunk>, DiskError> {
+        self.get_document(document_id)?;
+        let mut stmt = self.conn.prepare(
+            "SELECT chunk_id, content, metadata FROM chunks WHERE document_id = ?1 ORDER BY position",
+        )?;
+        let chunks = stmt
+            .query_map(params![document_id], |row| Chunk::try_from(row))?
+            .collect::<Result<_, _>>()?;
+        Ok(chunks)
+    }
+
+    /// Deletes a document together with all of its chunks and their indices.
+    ///
+    /// # Returns
+    /// The number of chunks deleted.
+    pub fn delete_document(&mut self, document_id: &str) -> Result<usize, DiskError> {
+        let tx = self.conn.transaction()?;
+        let chunk_ids = Self::document_chunk_ids(&tx, document_id)?;
+        let deleted = Self::delete_chunk_rows(&tx, &chunk_ids)?;
+        if tx.execute("DELETE FROM documents WHERE document_id = ?1", params![document_id])? == 0 {
+            return Err(DiskError::NotFound(document_id.to_string()));
+        }
+        tx.commit()?;
+
+        self.tombstone(&chunk_ids)?;
+
+        Ok(deleted)
+    }
+
     /// Performs a semantic search for the `top_k` most similar chunks.
     ///
     /// # Arguments
@@ -257,125 +763,2777 @@ impl IdentityDisk {
         query_vector: QueryVector,
         top_k: usize,
     ) -> Result<Vec<SearchResult>, DiskError> {
+        self.search_with_options(query_vector, top_k, &SearchOptions::default())
+    }
+
+    /// Performs a semantic search for the `top_k` most similar chunks that
+    /// satisfy `options`.
+    ///
+    /// A metadata filter is first evaluated by SQLite over every chunk, and the
+    /// ids of the matching chunks are held in memory for the query, so a filter
+    /// adds a pass over the `chunks` table to each search. The match set is then
+    /// applied while traversing the HNSW graph, so up to `top_k` matching chunks
+    /// are returned even when the filter is selective.
+    ///
+    /// With `SearchMode::Auto`, disks holding no more vectors than the exact
+    /// search threshold are scanned exactly instead, as are searches whose
+    /// filter matches no more chunks than the threshold: traversal would visit
+    /// most of the graph to find so few.
+    pub fn search_with_options(
+        &self,
+        query_vector: QueryVector,
+        top_k: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>, DiskError> {
+        let allowed = match &options.filter {
+            Some(filter) => {
+                let allowed = self.matching_chunk_ids(&MetadataFilter::from_json(filter)?)?;
+                if allowed.is_empty() {
+                    return Ok(Vec::new());
+                }
+                Some(allowed)
+            }
+            None => None,
+        };
+
+        let few_allowed = allowed.as_ref().is_some_and(|a| a.len() <= self.exact_search_threshold);
+        if self.searches_exactly(options.mode) || (options.mode == SearchMode::Auto && few_allowed) {
+            return self.exact_search(&query_vector, top_k, allowed.as_ref());
+        }
+
+        let ef_search = options.ef_search.unwrap_or(self.index_config.ef_search);
         let index = self.index.read()?;
-        let neighbors = match (&*index, query_vector) {
-            (SearchIndex::F32(hnsw), QueryVector::F32(q)) => {
-                hnsw.search(q, top_k, 100)
+        let id_map = self.id_to_chunk_id.read()?;
+        // Skip tombstoned and filtered-out nodes during traversal so `top_k`
+        // eligible results are still returned.
+        let is_live = |d_id: &usize| match id_map.get(*d_id) {
+            Some(Some(chunk_id)) => allowed.as_ref().is_none_or(|a| a.contains(chunk_id)),
+            _ => false,
+        };
+        let mismatch = || {
+            DiskError::InvalidData(format!(
+                "Search query type does not match index type ({}).",
+                self.model_signature.dtype.as_str()
+            ))
+        };
+        // Set when binary candidates should be rescored at full precision.
+        let mut rescore_query = None;
+        let metric = self.model_signature.metric;
+        let neighbors = match &*index {
+            SearchIndex::F32(hnsw) | SearchIndex::F16(hnsw) => {
+                // Both float index types search in f32, so either float query type works.
+                let query = Self::float_query(&query_vector).ok_or_else(mismatch)?;
+                self.check_dimension(query.len())?;
+                hnsw.search_filter(&query, top_k, ef_search.max(top_k), Some(&is_live))
             },
-            // Mismatched types
-            // (SearchIndex::F32(_), _) => return Err(DiskError::InvalidData("Search query type does not match index type (f32).".into())),
-            (SearchIndex::None, _) => return Ok(Vec::new()), // No index, no results
+            SearchIndex::I8(hnsw) => {
+                let calibration = hnsw.get_distance().calibration();
+                let query: Cow<[i8]> = match query_vector {
+                    QueryVector::I8(q) => Cow::Borrowed(q),
+                    _ => {
+                        let q = Self::float_query(&query_vector).ok_or_else(mismatch)?;
+                        self.check_dimension(q.len())?;
+                        Cow::Owned(calibration.quantize(&q))
+                    }
+                };
+                self.check_dimension(query.len())?;
+                hnsw.search_filter(&query, top_k, ef_search.max(top_k), Some(&is_live))
+            }
+            SearchIndex::Bin(hnsw) => {
+                let query: Cow<[u8]> = match query_vector {
+                    QueryVector::Bin(q) => {
+                        self.check_packed_dimension(q.len())?;
+                        Cow::Borrowed(q)
+                    }
+                    _ => {
+                        let q = Self::float_query(&query_vector).ok_or_else(mismatch)?;
+                        self.check_dimension(q.len())?;
+                        let bits = binarize(&q);
+                        rescore_query = Some(q);
+                        Cow::Owned(bits)
+                    }
+                };
+                // Over-fetch so rescoring can recover neighbours Hamming ranked too low.
+                let candidates = if rescore_query.is_some() { top_k * BIN_RESCORE_FACTOR } else { top_k };
+                hnsw.search_filter(&query, candidates, ef_search.max(candidates), Some(&is_live))
+            }
         };
 
-        let id_map = self.id_to_chunk_id.read()?;
+        let mut stmt = self
+            .conn
+            .prepare("SELECT chunk_id, content, metadata FROM chunks WHERE chunk_id = ?1")?;
+        // Binary indices rank by Hamming distance until rescored below.
+        let score_metric = match &*index {
+            SearchIndex::Bin(_) => ScoreMetric::Hamming,
+            _ => metric.into(),
+        };
         let mut results: Vec<SearchResult> = Vec::with_capacity(neighbors.len());
         for neighbor in neighbors {
-            let chunk_id = &id_map[neighbor.d_id];
+            let Some(Some(chunk_id)) = id_map.get(neighbor.d_id) else {
+                continue;
+            };
 
-            let mut stmt = self
-                .conn
-                .prepare("SELECT chunk_id, content, metadata FROM chunks WHERE chunk_id = ?1")?;
-            let chunk = stmt.query_row(params![chunk_id], |row| Chunk::try_from(row))?;
+            // The row may have been removed by another connection since the index was loaded.
+            let Some(chunk) = stmt
+                .query_row(params![chunk_id], |row| Chunk::try_from(row))
+                .optional()?
+            else {
+                continue;
+            };
 
+            // Dot-product graphs store a positive surrogate; report `1 - dot`.
+            let distance = match score_metric {
+                ScoreMetric::Dot => inner_product_distance(neighbor.distance),
+                _ => neighbor.distance,
+            };
             results.push(SearchResult {
                 chunk,
-                distance: neighbor.distance,
+                distance,
+                metric: score_metric,
+                vector: None,
+                keyword: None,
             });
         }
 
+        if let Some(query) = rescore_query {
+            self.rescore(&mut results, &query)?;
+        }
+        results.truncate(top_k);
+
         Ok(results)
     }
 
-    /// Updates the metadata of an existing chunk.
+    /// Performs a full-text keyword search over chunk contents.
     ///
-    /// Note: This does not allow changing the `content` of a chunk, as that
-    /// would invalidate its embedding.
-    pub fn update_chunk_metadata(
-        &mut self,
-        chunk_id: &str,
-        new_metadata: Json,
-    ) -> Result<(), DiskError> {
-        let metadata_str = new_metadata.to_string();
-        let rows_affected = self.conn.execute(
-            "UPDATE chunks SET metadata = ?1 WHERE chunk_id = ?2",
-            params![metadata_str, chunk_id],
-        )?;
+    /// Every term in `query` must appear in a chunk for it to match; wrap words
+    /// in double quotes to match them as an exact phrase. Matching is
+    /// case-insensitive and punctuation is ignored, so codes like `E-1042` match
+    /// as written.
+    ///
+    /// # Returns
+    /// Up to `top_k` results ordered by BM25, best first. `distance` holds the
+    /// BM25 rank, where more negative is a better match.
+    pub fn keyword_search(&self, query: &str, top_k: usize) -> Result<Vec<SearchResult>, DiskError> {
+        self.fts_search(&fts::to_match_query(query, false), top_k)
+    }
 
-        if rows_affected == 0 {
-            Err(DiskError::NotFound(chunk_id.to_string()))
-        } else {
-            Ok(())
+    /// Runs an FTS5 `MATCH` query and returns hits ordered by BM25.
+    fn fts_search(&self, match_query: &str, top_k: usize) -> Result<Vec<SearchResult>, DiskError> {
+        if match_query.is_empty() {
+            return Ok(Vec::new());
         }
-    }
 
-    /// Helper to load the index, now with type dispatching.
-    fn load_index_from_db(
-        conn: &Connection,
-        model_signature: &str,
-    ) -> Result<(SearchIndex, Vec<String>), DiskError> {
-        // --- This block is now more memory efficient ---
-        // It collects all blobs first, then processes them, avoiding per-row vector allocation.
-        let mut stmt = conn.prepare(
-            "SELECT chunk_id, data FROM indices WHERE model_signature = ?1 ORDER BY chunk_id",
+        let mut stmt = self.conn.prepare(
+            "SELECT c.chunk_id, c.content, c.metadata, bm25(chunks_fts)
+             FROM chunks_fts JOIN chunks c ON c.chunk_id = chunks_fts.chunk_id
+             WHERE chunks_fts MATCH ?1
+             ORDER BY bm25(chunks_fts)
+             LIMIT ?2",
         )?;
-        let mut rows = stmt.query(params![model_signature])?;
+        let results = stmt
+            .query_map(params![match_query, top_k as i64], |row| {
+                Ok(SearchResult {
+                    chunk: Chunk::try_from(row)?,
+                    distance: row.get::<_, f64>(3)? as f32,
+                    metric: ScoreMetric::Bm25,
+                    vector: None,
+                    keyword: None,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(results)
+    }
 
-        let mut id_map = Vec::new();
-        let mut data_blobs = Vec::new();
-        while let Some(row) = rows.next()? {
-            id_map.push(row.get(0)?);
-            data_blobs.push(row.get::<_, Vec<u8>>(1)?);
+    /// Fuses semantic and keyword search into one ranking.
+    ///
+    /// Vector neighbours of `vector` and keyword hits for `text` are retrieved
+    /// separately, several times deeper than `top_k`, and combined as described
+    /// by `weights`. Unlike `keyword_search`, a chunk needs only one of the terms
+    /// in `text` to be a keyword hit; BM25 ranks chunks with more terms higher. A chunk found by only one retriever still ranks, so
+    /// identifiers matched only by keyword and paraphrases matched only by
+    /// vector both surface.
+    ///
+    /// # Returns
+    /// Up to `top_k` results, best first. `distance` is the negated fused score
+    /// so lower is better as for other searches, and `vector` / `keyword` record
+    /// each retriever's own rank and score.
+    pub fn hybrid_search(
+        &self,
+        text: &str,
+        vector: QueryVector,
+        top_k: usize,
+        weights: &HybridWeights,
+    ) -> Result<Vec<SearchResult>, DiskError> {
+        let depth = top_k * HYBRID_DEPTH_FACTOR;
+        let vector_hits = self.search(vector, depth)?;
+        let keyword_hits = self.fts_search(&fts::to_match_query(text, true), depth)?;
+
+        // Merge both lists by chunk, remembering each retriever's rank and score.
+        let mut merged: Vec<SearchResult> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for (from_vector, hits) in [(true, vector_hits), (false, keyword_hits)] {
+            for (i, hit) in hits.into_iter().enumerate() {
+                let ranked = RankedScore {
+                    rank: i + 1,
+                    score: hit.distance,
+                };
+                let pos = *positions.entry(hit.chunk.chunk_id.clone()).or_insert_with(|| {
+                    merged.push(SearchResult {
+                        chunk: hit.chunk,
+                        distance: 0.0,
+                        metric: match weights.fusion {
+                            Fusion::Rrf { .. } => ScoreMetric::Rrf,
+                            Fusion::WeightedScore => ScoreMetric::WeightedScore,
+                        },
+                        vector: None,
+                        keyword: None,
+                    });
+                    merged.len() - 1
+                });
+                if from_vector {
+                    merged[pos].vector = Some(ranked);
+                } else {
+                    merged[pos].keyword = Some(ranked);
+                }
+            }
         }
-        // --- End memory efficient block ---
 
-        if id_map.is_empty() {
-            return Ok((SearchIndex::None, Vec::new()));
+        let score_range = |scores: Vec<f32>| {
+            scores
+                .into_iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), s| (lo.min(s), hi.max(s)))
+        };
+        let vector_range = score_range(merged.iter().filter_map(|r| r.vector.map(|h| h.score)).collect());
+        let keyword_range = score_range(merged.iter().filter_map(|r| r.keyword.map(|h| h.score)).collect());
+        for result in &mut merged {
+            let fused = weights.vector * Self::fusion_contribution(weights.fusion, result.vector, vector_range)
+                + weights.keyword * Self::fusion_contribution(weights.fusion, result.keyword, keyword_range);
+            result.distance = -fused;
         }
 
-        // Dispatch based on signature
-        if model_signature.ends_with("_fp32") || !model_signature.contains('_') {
-            // Default to f32
-            let num_items = id_map.len();
-            let hnsw: Hnsw<'static, f32, DistCosine> =
-                Hnsw::new(16, num_items.max(1), 16, 200, DistCosine {});
+        merged.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        merged.truncate(top_k);
+        Ok(merged)
+    }
 
-            // This loop is still necessary to build the index.
-            for (i, blob) in data_blobs.iter().enumerate() {
-                let vector: Vec<f32> = blob
-                    .chunks_exact(4)
-                    .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
-                    .collect();
-                // We use insert_slice later if we can collect all vectors into one slice
-                hnsw.insert((&vector, i));
-            }
-            Ok((SearchIndex::F32(hnsw), id_map))
-        }
-        // TODO: Add `else if` blocks for `_fp16`, `_int8`, etc.
-        // else if model_signature.ends_with("_int8") { ... }
-        else {
-            // Signature is present but not supported by this library version
-            eprintln!("Warning: Unsupported vector type for model signature '{}'. Search will be disabled.", model_signature);
-            Ok((SearchIndex::None, id_map))
+    /// One retriever's share of a fused score. `range` is the `(min, max)` of
+    /// that retriever's scores, which are lower-is-better.
+    fn fusion_contribution(fusion: Fusion, hit: Option<RankedScore>, (min, max): (f32, f32)) -> f32 {
+        let Some(hit) = hit else {
+            return 0.0;
+        };
+        match fusion {
+            Fusion::Rrf { k } => 1.0 / (k + hit.rank as f32),
+            Fusion::WeightedScore if max > min => (max - hit.score) / (max - min),
+            Fusion::WeightedScore => 1.0,
         }
     }
 
-    /// Retrieves the specification version of the disk.
-    pub fn get_spec_version(&self) -> Result<String, DiskError> {
-        let version = self.conn.query_row(
-            "SELECT value FROM manifest WHERE key = 'spec_version'",
-            [],
-            |row| row.get(0),
-        )?;
-        Ok(version)
+    /// Collects the ids of all chunks whose metadata matches `filter`, which is
+    /// evaluated by SQLite over every row of `chunks`.
+    fn matching_chunk_ids(&self, filter: &MetadataFilter) -> Result<HashSet<String>, DiskError> {
+        let mut params = Vec::new();
+        let condition = filter.to_sql("metadata", &mut params);
+        // Missing or unparseable metadata matches like an empty object, as in `Chunk`.
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT chunk_id FROM (
+                 SELECT chunk_id, CASE WHEN json_valid(metadata) THEN metadata ELSE '{{}}' END AS metadata
+                 FROM chunks
+             )
+             WHERE {}",
+            condition
+        ))?;
+        let allowed = stmt
+            .query_map(rusqlite::params_from_iter(params), |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(allowed)
     }
 
-    /// Returns the type of the currently loaded search index.
-    pub fn get_index_type_description(&self) -> Result<String, DiskError> {
-        let index_guard = self.index.read()?;
-        Ok(match *index_guard {
-            SearchIndex::F32(_) => "F32 (Cosine Distance)".to_string(),
-            SearchIndex::None => "None (No index loaded or supported for current model signature)".to_string(),
-            // Add other types as they are implemented
-        })
+    /// Finds the exact `top_k` neighbours by scanning every stored vector of the
+    /// active signature, optionally restricted to the `allowed` chunks.
+    ///
+    /// Int8 vectors are compared with the index's own distance against the
+    /// quantized query, so distances match those of approximate search. A float
+    /// query on a binary disk is compared with the full-precision vectors kept
+    /// alongside it when every chunk has one.
+    fn exact_search(
+        &self,
+        query_vector: &QueryVector,
+        top_k: usize,
+        allowed: Option<&HashSet<String>>,
+    ) -> Result<Vec<SearchResult>, DiskError> {
+        let dim = self.model_signature.dim;
+        let metric = self.model_signature.metric;
+        let mismatch = || {
+            DiskError::InvalidData(format!(
+                "Search query type does not match index type ({}).",
+                self.model_signature.dtype.as_str()
+            ))
+        };
+
+        let (scored, score_metric) = match self.model_signature.dtype {
+            Dtype::Fp32 | Dtype::Fp16 => {
+                let query = Self::float_query(query_vector).ok_or_else(mismatch)?;
+                self.check_dimension(query.len())?;
+                let decode = match self.model_signature.dtype {
+                    Dtype::Fp16 => codec::decode_f16,
+                    _ => codec::decode_f32,
+                };
+                let scored = self.scan_indices(&self.model_signature, allowed, |blob| {
+                    decode(blob, dim).map(|v| reported_distance(metric, &query, &v))
+                })?;
+                (scored, metric.into())
+            }
+            Dtype::Int8 => {
+                let distance = match &*self.index.read()? {
+                    SearchIndex::I8(hnsw) => hnsw.get_distance().clone(),
+                    _ => return Err(mismatch()),
+                };
+                let query: Cow<[i8]> = match query_vector {
+                    QueryVector::I8(q) => Cow::Borrowed(q),
+                    _ => {
+                        let q = Self::float_query(query_vector).ok_or_else(mismatch)?;
+                        self.check_dimension(q.len())?;
+                        Cow::Owned(distance.calibration().quantize(&q))
+                    }
+                };
+                self.check_dimension(query.len())?;
+                let scored = self.scan_indices(&self.model_signature, allowed, |blob| {
+                    codec::decode_i8(blob, dim).map(|codes| match metric {
+                        // Dot-product distances are the graph's positive surrogate; report `1 - dot`.
+                        Metric::Dot => inner_product_distance(distance.eval(&query, &codes)),
+                        _ => distance.eval(&query, &codes),
+                    })
+                })?;
+                (scored, metric.into())
+            }
+            Dtype::Bin => {
+                let full_precision = Self::rescoring_key(&self.model_signature);
+                let float_query = Self::float_query(query_vector);
+                let rescorable = float_query.is_some() && self.has_full_precision()?;
+                if let Some(query) = float_query.as_ref().filter(|_| rescorable) {
+                    self.check_dimension(query.len())?;
+                    let scored = self.scan_indices(&full_precision, allowed, |blob| {
+                        codec::decode_f32(blob, dim).map(|v| reported_distance(metric, query, &v))
+                    })?;
+                    (scored, metric.into())
+                } else {
+                    let bits: Cow<[u8]> = match (query_vector, &float_query) {
+                        (QueryVector::Bin(q), _) => Cow::Borrowed(q),
+                        (_, Some(q)) => {
+                            self.check_dimension(q.len())?;
+                            Cow::Owned(binarize(q))
+                        }
+                        _ => return Err(mismatch()),
+                    };
+                    self.check_packed_dimension(bits.len())?;
+                    let scored = self.scan_indices(&self.model_signature, allowed, |blob| {
+                        codec::decode_bin(blob, dim).map(|v| DistBinary.eval(&bits, &v))
+                    })?;
+                    (scored, ScoreMetric::Hamming)
+                }
+            }
+        };
+
+        self.load_scored(scored, top_k, score_metric)
+    }
+
+    /// Loads the chunks behind the first `top_k` scored ids that still exist.
+    fn load_scored(
+        &self,
+        scored: Vec<(f32, String)>,
+        top_k: usize,
+        score_metric: ScoreMetric,
+    ) -> Result<Vec<SearchResult>, DiskError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT chunk_id, content, metadata FROM chunks WHERE chunk_id = ?1")?;
+        let mut results = Vec::with_capacity(scored.len().min(top_k));
+        for (distance, chunk_id) in scored {
+            if results.len() == top_k {
+                break;
+            }
+            // The row may have been removed by another connection since the scan.
+            let Some(chunk) = stmt
+                .query_row(params![chunk_id], |row| Chunk::try_from(row))
+                .optional()?
+            else {
+                continue;
+            };
+            results.push(SearchResult {
+                chunk,
+                distance,
+                metric: score_metric,
+                vector: None,
+                keyword: None,
+            });
+        }
+        Ok(results)
+    }
+
+    /// Scores every vector stored under `model_signature`, a signature or a
+    /// rescoring key, with `distance`, which returns `None` for blobs it cannot
+    /// decode.
+    ///
+    /// # Returns
+    /// `(distance, chunk_id)` pairs sorted by ascending distance.
+    fn scan_indices(
+        &self,
+        model_signature: impl rusqlite::ToSql,
+        allowed: Option<&HashSet<String>>,
+        distance: impl Fn(&[u8]) -> Option<f32>,
+    ) -> Result<Vec<(f32, String)>, DiskError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT chunk_id, data FROM indices WHERE model_signature = ?1")?;
+        let mut rows = stmt.query(params![model_signature])?;
+
+        let mut scored = Vec::new();
+        while let Some(row) = rows.next()? {
+            let chunk_id: String = row.get(0)?;
+            if allowed.is_some_and(|a| !a.contains(&chunk_id)) {
+                continue;
+            }
+            let blob = row.get_ref(1)?.as_blob().map_err(rusqlite::Error::from)?;
+            let d = distance(blob).ok_or_else(|| DiskError::CorruptVector {
+                chunk_id: chunk_id.clone(),
+            })?;
+            scored.push((d, chunk_id));
+        }
+        scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(scored)
+    }
+
+    /// Whether every vector of a binary signature has a full-precision companion.
+    fn has_full_precision(&self) -> Result<bool, DiskError> {
+        let (binary, full): (usize, usize) = self.conn.query_row(
+            "SELECT
+                 (SELECT COUNT(*) FROM indices WHERE model_signature = ?1),
+                 (SELECT COUNT(*) FROM indices WHERE model_signature = ?2)",
+            params![&self.model_signature, Self::rescoring_key(&self.model_signature)],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(binary == full)
+    }
+
+    /// Measures how well HNSW search recovers the exact nearest neighbours.
+    ///
+    /// Every query is run once through HNSW and once as an exact scan. Recall@k
+    /// is the fraction of the exact top `k` chunks that HNSW also returned.
+    pub fn evaluate_recall(
+        &self,
+        sample_queries: &[QueryVector],
+        k: usize,
+    ) -> Result<RecallReport, DiskError> {
+        if sample_queries.is_empty() {
+            return Err(DiskError::InvalidData(
+                "At least one sample query is needed to evaluate recall".into(),
+            ));
+        }
+
+        let approximate = SearchOptions::default().with_mode(SearchMode::Approximate);
+        let (mut recall_sum, mut min_recall) = (0.0f32, 1.0f32);
+        let (mut hnsw_time, mut exact_time) = (Duration::ZERO, Duration::ZERO);
+        for query in sample_queries {
+            let start = Instant::now();
+            let found = self.search_with_options(query.clone(), k, &approximate)?;
+            hnsw_time += start.elapsed();
+
+            let start = Instant::now();
+            let expected = self.exact_search(query, k, None)?;
+            exact_time += start.elapsed();
+
+            let recall = if expected.is_empty() {
+                1.0
+            } else {
+                let found: HashSet<&str> = found.iter().map(|r| r.chunk.chunk_id.as_str()).collect();
+                let hits = expected
+                    .iter()
+                    .filter(|r| found.contains(r.chunk.chunk_id.as_str()))
+                    .count();
+                hits as f32 / expected.len() as f32
+            };
+            recall_sum += recall;
+            min_recall = min_recall.min(recall);
+        }
+
+        let n = sample_queries.len();
+        Ok(RecallReport {
+            queries: n,
+            k,
+            recall_at_k: recall_sum / n as f32,
+            min_recall,
+            hnsw_latency: hnsw_time / n as u32,
+            exact_latency: exact_time / n as u32,
+        })
+    }
+
+    /// Whether a search in `mode` scans every stored vector instead of HNSW.
+    fn searches_exactly(&self, mode: SearchMode) -> bool {
+        match mode {
+            SearchMode::Exact => true,
+            SearchMode::Approximate => false,
+            SearchMode::Auto => self.live_vectors.load(Ordering::SeqCst) <= self.exact_search_threshold,
+        }
+    }
+
+    /// Sets how many live vectors a disk may hold for `SearchMode::Auto` to
+    /// search it exactly. `0` uses HNSW for every non-empty disk.
+    pub fn set_exact_search_threshold(&mut self, max_vectors: usize) {
+        self.exact_search_threshold = max_vectors;
+    }
+
+    /// Replaces Hamming distances with distances under the disk's metric to the
+    /// full-precision vectors stored alongside a binary index, and re-sorts the
+    /// results.
+    ///
+    /// Results are left in Hamming order unless every candidate has a
+    /// full-precision vector, so the two scales are never mixed.
+    fn rescore(&self, results: &mut [SearchResult], query: &[f32]) -> Result<(), DiskError> {
+        let full_precision = Self::rescoring_key(&self.model_signature);
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT data FROM indices WHERE chunk_id = ?1 AND model_signature = ?2",
+        )?;
+
+        let mut distances = Vec::with_capacity(results.len());
+        for result in results.iter() {
+            let blob: Option<Vec<u8>> = stmt
+                .query_row(params![&result.chunk.chunk_id, &full_precision], |row| row.get(0))
+                .optional()?;
+            let Some(blob) = blob else {
+                return Ok(());
+            };
+            let vector = codec::decode_f32(&blob, self.model_signature.dim).ok_or_else(|| {
+                DiskError::CorruptVector {
+                    chunk_id: result.chunk.chunk_id.clone(),
+                }
+            })?;
+            distances.push(reported_distance(self.model_signature.metric, query, &vector));
+        }
+
+        for (result, distance) in results.iter_mut().zip(distances) {
+            result.distance = distance;
+            result.metric = self.model_signature.metric.into();
+        }
+        results.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        Ok(())
+    }
+
+    /// Converts a float query to f32, or `None` for quantized query types.
+    fn float_query<'q>(query_vector: &QueryVector<'q>) -> Option<Cow<'q, [f32]>> {
+        match query_vector {
+            QueryVector::F32(q) => Some(Cow::Borrowed(*q)),
+            QueryVector::F16(q) => Some(Cow::Owned(q.iter().map(|h| h.to_f32()).collect())),
+            QueryVector::I8(_) | QueryVector::Bin(_) => None,
+        }
+    }
+
+    /// Updates the metadata of an existing chunk.
+    ///
+    /// Note: This does not allow changing the `content` of a chunk, as that
+    /// would invalidate its embedding. Use `update_chunk` to replace both.
+    pub fn update_chunk_metadata(
+        &mut self,
+        chunk_id: &str,
+        new_metadata: Json,
+    ) -> Result<(), DiskError> {
+        let metadata_str = new_metadata.to_string();
+        let rows_affected = self.conn.execute(
+            "UPDATE chunks SET metadata = ?1 WHERE chunk_id = ?2",
+            params![metadata_str, chunk_id],
+        )?;
+
+        if rows_affected == 0 {
+            Err(DiskError::NotFound(chunk_id.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Deletes a chunk and all of its vector indices.
+    ///
+    /// The chunk's node in the in-memory HNSW index is tombstoned, so it is
+    /// never returned by `search` again.
+    pub fn delete_chunk(&mut self, chunk_id: &str) -> Result<(), DiskError> {
+        let deleted = self.delete_chunk_ids(&[chunk_id.to_string()])?;
+        if deleted == 0 {
+            Err(DiskError::NotFound(chunk_id.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Deletes every chunk for which `filter` returns `true`.
+    ///
+    /// Chunks are read one row at a time, so only the ids of matching chunks
+    /// are held in memory. All matching chunks are removed in a single
+    /// transaction.
+    ///
+    /// # Returns
+    /// The number of chunks deleted.
+    pub fn delete_chunks<F>(&mut self, filter: F) -> Result<usize, DiskError>
+    where
+        F: Fn(&Chunk) -> bool,
+    {
+        let mut chunk_ids = Vec::new();
+        {
+            let mut stmt = self.conn.prepare("SELECT chunk_id, content, metadata FROM chunks")?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let chunk = Chunk::try_from(row)?;
+                if filter(&chunk) {
+                    chunk_ids.push(chunk.chunk_id);
+                }
+            }
+        }
+        self.delete_chunk_ids(&chunk_ids)
+    }
+
+    /// Removes the given chunks from the database and tombstones their HNSW nodes.
+    fn delete_chunk_ids(&mut self, chunk_ids: &[String]) -> Result<usize, DiskError> {
+        if chunk_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let tx = self.conn.transaction()?;
+        let deleted = Self::delete_chunk_rows(&tx, chunk_ids)?;
+        tx.commit()?;
+
+        self.tombstone(chunk_ids)?;
+
+        Ok(deleted)
+    }
+
+    /// Deletes the `chunks` and `indices` rows of the given chunks.
+    fn delete_chunk_rows(conn: &Connection, chunk_ids: &[String]) -> Result<usize, DiskError> {
+        // Indices are removed explicitly so disks written before foreign keys
+        // were enforced are cleaned up as well.
+        let mut delete_indices = conn.prepare("DELETE FROM indices WHERE chunk_id = ?1")?;
+        let mut delete_chunk = conn.prepare("DELETE FROM chunks WHERE chunk_id = ?1")?;
+        let mut deleted = 0;
+        for chunk_id in chunk_ids {
+            delete_indices.execute(params![chunk_id])?;
+            deleted += delete_chunk.execute(params![chunk_id])?;
+        }
+        Ok(deleted)
+    }
+
+    /// The `chunk_id`s of a document's chunks, in document order.
+    fn document_chunk_ids(conn: &Connection, document_id: &str) -> Result<Vec<String>, DiskError> {
+        let mut stmt =
+            conn.prepare("SELECT chunk_id FROM chunks WHERE document_id = ?1 ORDER BY position")?;
+        let chunk_ids = stmt
+            .query_map(params![document_id], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(chunk_ids)
+    }
+
+    /// Validates an embedding against the active index and serializes it for storage.
+    fn encode_embedding<'v>(
+        &self,
+        embedding: &QueryVector<'v>,
+    ) -> Result<EncodedEmbedding<'v>, DiskError> {
+        let index = self.index.read()?;
+        let calibration = match &*index {
+            SearchIndex::I8(hnsw) => Some(hnsw.get_distance().calibration()),
+            _ => None,
+        };
+        Self::encode_for_signature(&self.model_signature, calibration, embedding)
+    }
+
+    /// Validates an embedding against `model_signature` and serializes it for storage.
+    ///
+    /// Produces the little-endian blob for the `indices` table and the vector to
+    /// insert into HNSW. fp16, int8 and binary signatures accept f32 input and
+    /// narrow or quantize it for storage; binary signatures also keep the f32
+    /// input for rescoring. Int8 signatures need their `calibration`.
+    fn encode_for_signature<'v>(
+        model_signature: &ModelSignature,
+        calibration: Option<&Int8Calibration>,
+        embedding: &QueryVector<'v>,
+    ) -> Result<EncodedEmbedding<'v>, DiskError> {
+        let encoded = |blob, vector| EncodedEmbedding {
+            blob,
+            vector,
+            full_precision: None,
+        };
+        let check_dimension = |got: usize| Self::check_signature_dimension(model_signature, got);
+
+        // Match the input vector against the signature's dtype to serialize it correctly
+        match (model_signature.dtype, embedding) {
+            (Dtype::Fp32, QueryVector::F32(v)) => {
+                check_dimension(v.len())?;
+                Ok(encoded(codec::encode_f32(v), IndexVector::F32(Cow::Borrowed(*v))))
+            }
+            (Dtype::Fp16, QueryVector::F32(v)) => {
+                check_dimension(v.len())?;
+                let narrowed: Vec<f16> = v.iter().map(|&f| f16::from_f32(f)).collect();
+                // Index the stored precision so search matches a reopened disk.
+                let widened = narrowed.iter().map(|h| h.to_f32()).collect();
+                Ok(encoded(codec::encode_f16(&narrowed), IndexVector::F32(Cow::Owned(widened))))
+            }
+            (Dtype::Fp16, QueryVector::F16(v)) => {
+                check_dimension(v.len())?;
+                let widened = v.iter().map(|h| h.to_f32()).collect();
+                Ok(encoded(codec::encode_f16(v), IndexVector::F32(Cow::Owned(widened))))
+            }
+            (Dtype::Int8, QueryVector::F32(v)) => {
+                check_dimension(v.len())?;
+                let calibration = calibration.ok_or_else(|| {
+                    DiskError::InvalidData(format!("No int8 calibration for '{}'", model_signature))
+                })?;
+                let codes = calibration.quantize(v);
+                Ok(encoded(codec::encode_i8(&codes), IndexVector::I8(codes)))
+            }
+            (Dtype::Int8, QueryVector::I8(v)) => {
+                check_dimension(v.len())?;
+                Ok(encoded(codec::encode_i8(v), IndexVector::I8(v.to_vec())))
+            }
+            (Dtype::Bin, QueryVector::F32(v)) => {
+                check_dimension(v.len())?;
+                let bits = binarize(v);
+                Ok(EncodedEmbedding {
+                    blob: bits.clone(),
+                    vector: IndexVector::Bin(Cow::Owned(bits)),
+                    full_precision: Some(codec::encode_f32(v)),
+                })
+            }
+            (Dtype::Bin, QueryVector::Bin(v)) => {
+                Self::check_signature_packed_dimension(model_signature, v.len())?;
+                Ok(encoded(v.to_vec(), IndexVector::Bin(Cow::Borrowed(*v))))
+            }
+            _ => Err(DiskError::InvalidData(format!(
+                "Mismatched vector type: expected {}",
+                model_signature.dtype.as_str()
+            ))),
+        }
+    }
+
+    /// Ensures a vector has the dimension declared by the active model signature.
+    fn check_dimension(&self, got: usize) -> Result<(), DiskError> {
+        Self::check_signature_dimension(&self.model_signature, got)
+    }
+
+    /// Ensures a bit-packed vector has `ceil(dim / 8)` bytes.
+    fn check_packed_dimension(&self, got_bytes: usize) -> Result<(), DiskError> {
+        Self::check_signature_packed_dimension(&self.model_signature, got_bytes)
+    }
+
+    /// Ensures a vector has the dimension declared by `model_signature`.
+    fn check_signature_dimension(model_signature: &ModelSignature, got: usize) -> Result<(), DiskError> {
+        let expected = model_signature.dim;
+        if got != expected {
+            return Err(DiskError::DimensionMismatch { expected, got });
+        }
+        Ok(())
+    }
+
+    /// Ensures a bit-packed vector has `ceil(dim / 8)` bytes for `model_signature`.
+    fn check_signature_packed_dimension(
+        model_signature: &ModelSignature,
+        got_bytes: usize,
+    ) -> Result<(), DiskError> {
+        let expected = model_signature.dim.div_ceil(8);
+        if got_bytes != expected {
+            return Err(DiskError::DimensionMismatch {
+                expected,
+                got: got_bytes,
+            });
+        }
+        Ok(())
+    }
+
+    /// Writes the `indices` rows of an encoded embedding, replacing existing ones.
+    fn write_embedding(
+        conn: &Connection,
+        model_signature: &ModelSignature,
+        chunk_id: &str,
+        encoded: &EncodedEmbedding,
+    ) -> Result<(), DiskError> {
+        let mut upsert = conn.prepare_cached(UPSERT_INDEX_SQL)?;
+        upsert.execute(params![chunk_id, "vector_embedding", model_signature, &encoded.blob])?;
+        if let Some(full_precision) = &encoded.full_precision {
+            upsert.execute(params![
+                chunk_id,
+                "rescoring_vector",
+                Self::rescoring_key(model_signature),
+                full_precision
+            ])?;
+        }
+        Ok(())
+    }
+
+    /// Inserts a vector into the in-memory HNSW index under a new sequential id.
+    fn index_vector(&self, chunk_id: &str, vector: IndexVector) -> Result<(), DiskError> {
+        self.index_vectors(&[chunk_id.to_string()], &[vector])
+    }
+
+    /// Inserts vectors into the in-memory HNSW index under new sequential ids.
+    ///
+    /// Batches of more than one vector are inserted in parallel.
+    fn index_vectors(&self, chunk_ids: &[String], vectors: &[IndexVector]) -> Result<(), DiskError> {
+        let mut index = self.index.write()?;
+        let mut id_map = self.id_to_chunk_id.write()?;
+        self.insert_vectors(&mut index, &mut id_map, chunk_ids, vectors)
+    }
+
+    /// `index_vectors` on an index and id map whose write locks are held.
+    fn insert_vectors(
+        &self,
+        index: &mut SearchIndex,
+        id_map: &mut IdMap,
+        chunk_ids: &[String],
+        vectors: &[IndexVector],
+    ) -> Result<(), DiskError> {
+        let first_hnsw_id = id_map.len();
+        let mismatch = || DiskError::InvalidData("Vector type does not match the loaded index.".into());
+
+        // Update in-memory HNSW index using enum dispatch
+        match index {
+            SearchIndex::F32(ref mut hnsw) | SearchIndex::F16(ref mut hnsw) => {
+                let data = vectors
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| match v {
+                        IndexVector::F32(v) => Ok((v.as_ref(), first_hnsw_id + i)),
+                        _ => Err(mismatch()),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                hnsw.insert(&data);
+            }
+            SearchIndex::I8(ref mut hnsw) => {
+                let data = vectors
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| match v {
+                        IndexVector::I8(v) => Ok((v.as_slice(), first_hnsw_id + i)),
+                        _ => Err(mismatch()),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Self::insert_into_graph(hnsw, &data);
+            }
+            SearchIndex::Bin(ref mut hnsw) => {
+                let data = vectors
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| match v {
+                        IndexVector::Bin(v) => Ok((v.as_ref(), first_hnsw_id + i)),
+                        _ => Err(mismatch()),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Self::insert_into_graph(hnsw, &data);
+            }
+        }
+
+        for chunk_id in chunk_ids {
+            id_map.push(chunk_id.clone());
+        }
+        self.live_vectors.store(id_map.live(), Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Inserts a single vector directly and larger batches in parallel.
+    fn insert_into_graph<T, D>(hnsw: &Hnsw<'static, T, D>, data: &Vec<(&[T], usize)>)
+    where
+        T: Clone + Send + Sync,
+        D: Distance<T> + Send + Sync,
+    {
+        if let [single] = data.as_slice() {
+            hnsw.insert_slice(*single);
+        } else {
+            hnsw.parallel_insert_slice(data);
+        }
+    }
+
+    /// Marks the HNSW nodes of the given chunks as dead.
+    fn tombstone(&self, chunk_ids: &[String]) -> Result<(), DiskError> {
+        let mut id_map = self.id_to_chunk_id.write()?;
+        self.mark_dead(&mut id_map, chunk_ids);
+        Ok(())
+    }
+
+    /// `tombstone` on an id map whose write lock is held.
+    fn mark_dead(&self, id_map: &mut IdMap, chunk_ids: &[String]) {
+        for chunk_id in chunk_ids {
+            if id_map.retire(chunk_id) {
+                self.live_vectors.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Applies per-connection settings required by the disk format.
+    fn configure_connection(conn: &Connection) -> Result<(), DiskError> {
+        // Needed for `ON DELETE CASCADE` from `chunks` to `indices`.
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        Ok(())
+    }
+
+    /// Stores the current HNSW graph in the disk so the next `open` can reload it
+    /// instead of rebuilding it from the `indices` rows.
+    ///
+    /// `open` does this automatically whenever it has to rebuild, so calling it is
+    /// only needed to avoid that rebuild after adding, updating or deleting chunks.
+    /// Graphs built with fewer than `IndexConfig::MAX_LAYERS` layers cannot be
+    /// stored, so this does nothing for them.
+    pub fn persist_index(&self) -> Result<(), DiskError> {
+        let mut index = self.index.write()?;
+        let mut id_map = self.id_to_chunk_id.write()?;
+
+        // Checksum the rows and check for other writers in one snapshot, so the
+        // graph is stored under the checksum of exactly the rows it describes.
+        let tx = self.conn.unchecked_transaction()?;
+        let (checksum, row_count) = graph::indices_checksum(&tx, &self.model_signature, &self.index_config)?;
+        if graph::data_version(&tx)? != self.index_data_version.load(Ordering::SeqCst) {
+            // Rows were changed through another connection; the in-memory graph
+            // may no longer describe the stored vectors. Rebuilding stores the
+            // new graph.
+            drop(tx);
+            let (new_index, new_id_map, data_version) =
+                Self::load_index_from_db(&self.conn, &self.model_signature, &self.index_config)?;
+            *index = new_index;
+            self.live_vectors.store(new_id_map.live(), Ordering::SeqCst);
+            *id_map = new_id_map;
+            self.index_data_version.store(data_version, Ordering::SeqCst);
+            return Ok(());
+        }
+
+        if row_count == 0 || !graph::can_persist(&self.index_config) {
+            return Ok(());
+        }
+        match &*index {
+            SearchIndex::F32(hnsw) | SearchIndex::F16(hnsw) => {
+                hnsw.save(&tx, &self.model_signature, &checksum, &id_map)?
+            }
+            SearchIndex::I8(hnsw) => graph::save_graph(&tx, &self.model_signature, &checksum, hnsw, &id_map)?,
+            SearchIndex::Bin(hnsw) => graph::save_graph(&tx, &self.model_signature, &checksum, hnsw, &id_map)?,
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Helper to load the index, now with type dispatching.
+    ///
+    /// Also returns the `data_version` of `conn` from before the rows were read.
+    fn load_index_from_db(
+        conn: &Connection,
+        model_signature: &ModelSignature,
+        index_config: &IndexConfig,
+    ) -> Result<(SearchIndex, IdMap, i64), DiskError> {
+        let data_version = graph::data_version(conn)?;
+        // Dispatch based on signature
+        let dim = model_signature.dim;
+        let (index, id_map) = match model_signature.dtype {
+            Dtype::Fp32 => {
+                let (index, id_map) = Self::load_float_index(conn, model_signature, index_config, codec::decode_f32)?;
+                (SearchIndex::F32(index), id_map)
+            }
+            Dtype::Fp16 => {
+                let (index, id_map) = Self::load_float_index(conn, model_signature, index_config, codec::decode_f16)?;
+                (SearchIndex::F16(index), id_map)
+            }
+            Dtype::Int8 => {
+                let calibration = Self::load_int8_calibration(conn, model_signature)?;
+                let distance = DistInt8::new(calibration, model_signature.metric);
+                let (hnsw, id_map) =
+                    Self::load_or_build_graph(conn, model_signature, index_config, distance, |blob| {
+                        codec::decode_i8(blob, dim)
+                    })?;
+                (SearchIndex::I8(hnsw), id_map)
+            }
+            Dtype::Bin => {
+                let (hnsw, id_map) =
+                    Self::load_or_build_graph(conn, model_signature, index_config, DistBinary, |blob| {
+                        codec::decode_bin(blob, dim)
+                    })?;
+                (SearchIndex::Bin(hnsw), id_map)
+            }
+        };
+        Ok((index, id_map.into(), data_version))
+    }
+
+    /// Loads an f32 index with the distance selected by the signature's metric.
+    fn load_float_index(
+        conn: &Connection,
+        model_signature: &ModelSignature,
+        index_config: &IndexConfig,
+        decode: fn(&[u8], usize) -> Option<Vec<f32>>,
+    ) -> Result<(FloatIndex, Vec<Option<String>>), DiskError> {
+        let dim = model_signature.dim;
+        let decode = |blob: &[u8]| decode(blob, dim);
+        Ok(match model_signature.metric {
+            Metric::Cosine => {
+                let (hnsw, id_map) = Self::load_or_build_graph(conn, model_signature, index_config, DistCosine {}, decode)?;
+                (FloatIndex::Cosine(hnsw), id_map)
+            }
+            Metric::L2 => {
+                let (hnsw, id_map) = Self::load_or_build_graph(conn, model_signature, index_config, DistL2 {}, decode)?;
+                (FloatIndex::L2(hnsw), id_map)
+            }
+            Metric::Dot => {
+                let (hnsw, id_map) = Self::load_or_build_graph(conn, model_signature, index_config, DistInnerProduct, decode)?;
+                (FloatIndex::Dot(hnsw), id_map)
+            }
+        })
+    }
+
+    /// Reloads the stored graph for `model_signature`, or rebuilds it from the
+    /// `indices` rows (decoded with `decode`) and stores the result, failing if
+    /// it cannot be stored.
+    fn load_or_build_graph<T, D>(
+        conn: &Connection,
+        model_signature: &ModelSignature,
+        index_config: &IndexConfig,
+        distance: D,
+        decode: impl Fn(&[u8]) -> Option<Vec<T>>,
+    ) -> Result<graph::StoredGraph<T, D>, DiskError>
+    where
+        T: 'static + serde::Serialize + serde::de::DeserializeOwned + Clone + Send + Sync + std::fmt::Debug,
+        D: Distance<T> + Clone + Send + Sync,
+    {
+        // Reuse the stored graph if it was built from exactly the current rows.
+        let (checksum, row_count) = graph::indices_checksum(conn, model_signature, index_config)?;
+        if row_count > 0 {
+            if let Some(stored) =
+                graph::load_graph::<T, D>(conn, model_signature, &checksum, distance.clone())?
+            {
+                return Ok(stored);
+            }
+        }
+
+        // Decode every stored vector, failing loudly on blobs of the wrong size.
+        // The rows may have changed since the checksum above, so the graph is
+        // stored under a checksum of exactly the rows it is built from.
+        let mut stmt = conn.prepare(
+            "SELECT chunk_id, data FROM indices WHERE model_signature = ?1 ORDER BY chunk_id",
+        )?;
+        let mut rows = stmt.query(params![model_signature])?;
+
+        let mut checksum = graph::IndicesChecksum::new(index_config);
+        let mut id_map = Vec::new();
+        let mut vectors = Vec::new();
+        while let Some(row) = rows.next()? {
+            let chunk_id: String = row.get(0)?;
+            let blob = row.get_ref(1)?.as_blob().map_err(rusqlite::Error::from)?;
+            let vector = decode(blob).ok_or_else(|| DiskError::CorruptVector {
+                chunk_id: chunk_id.clone(),
+            })?;
+            checksum.add(&chunk_id, blob);
+            id_map.push(Some(chunk_id));
+            vectors.push(vector);
+        }
+        let (checksum, row_count) = checksum.finish();
+
+        let hnsw: Hnsw<'static, T, D> = Hnsw::new(
+            index_config.max_nb_connection,
+            row_count.max(1),
+            index_config.max_layers,
+            index_config.ef_construction,
+            distance,
+        );
+        let data: Vec<(&[T], usize)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.as_slice(), i))
+            .collect();
+        hnsw.parallel_insert_slice(&data);
+
+        if row_count > 0 && graph::can_persist(index_config) {
+            graph::save_graph(conn, model_signature, &checksum, &hnsw, &id_map)?;
+        }
+        Ok((hnsw.into(), id_map))
+    }
+
+    /// Records `index_config` in the `manifest`.
+    fn store_index_config(conn: &Connection, index_config: &IndexConfig) -> Result<(), DiskError> {
+        conn.execute(
+            "INSERT OR REPLACE INTO manifest (key, value) VALUES ('index_config', ?1)",
+            params![serde_json::to_string(index_config)?],
+        )?;
+        Ok(())
+    }
+
+    /// Reads the recorded HNSW parameters, defaulting for disks that predate them.
+    fn load_index_config(conn: &Connection) -> Result<IndexConfig, DiskError> {
+        let stored: Option<String> = conn
+            .query_row("SELECT value FROM manifest WHERE key = 'index_config'", [], |row| row.get(0))
+            .optional()?;
+        let index_config = match stored {
+            Some(json) => serde_json::from_str::<IndexConfig>(&json)?,
+            None => IndexConfig::default(),
+        };
+        index_config.validate()?;
+        Ok(index_config)
+    }
+
+    /// Parses `model_signature`, giving it the metric recorded at create if it
+    /// has no metric suffix. An explicit metric must match the recorded one.
+    ///
+    /// Disks created before the metric was recorded are cosine.
+    fn disk_signature(conn: &Connection, model_signature: &str) -> Result<ModelSignature, DiskError> {
+        // Legacy signatures predate metrics, so never name one.
+        let (mut signature, explicit_metric) = match ModelSignature::parse_with_explicit_metric(model_signature) {
+            Ok(parsed) => parsed,
+            Err(error) => (Self::stored_legacy_signature(conn, model_signature)?.ok_or(error)?, None),
+        };
+        let disk_metric = conn
+            .query_row("SELECT value FROM manifest WHERE key = 'metric'", [], |row| row.get::<_, String>(0))
+            .optional()?
+            .map_or(Ok(Metric::Cosine), |metric| metric.parse())?;
+        if explicit_metric.is_some_and(|metric| metric != disk_metric) {
+            return Err(DiskError::InvalidSignature(format!(
+                "'{}': metric '{}' does not match the disk's metric '{}'",
+                model_signature,
+                signature.metric.as_str(),
+                disk_metric.as_str()
+            )));
+        }
+        signature.metric = disk_metric;
+        Ok(signature)
+    }
+
+    /// Fails like `ModelSignature::from_str` unless `model_signature` parses or
+    /// has the shape of a signature written before signatures were parsed.
+    fn check_signature_shape(model_signature: &str) -> Result<(), DiskError> {
+        match model_signature.parse::<ModelSignature>() {
+            Err(_) if ModelSignature::is_legacy(model_signature) => Ok(()),
+            parsed => parsed.map(|_| ()),
+        }
+    }
+
+    /// Finds the stored signature that a pre-parsing signature such as
+    /// `openai/text-embedding-ada-002_fp32` was canonicalized to on migration.
+    fn stored_legacy_signature(
+        conn: &Connection,
+        model_signature: &str,
+    ) -> Result<Option<ModelSignature>, DiskError> {
+        if !ModelSignature::is_legacy(model_signature) {
+            return Ok(None);
+        }
+        let stored: Vec<String> = conn
+            .prepare("SELECT DISTINCT model_signature FROM indices")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(stored
+            .iter()
+            .filter_map(|stored| stored.parse::<ModelSignature>().ok())
+            .find(|stored| ModelSignature::from_legacy(model_signature, stored.dim).as_ref() == Some(stored)))
+    }
+
+    /// The `indices` key of the fp32 copies a binary `model_signature` keeps for
+    /// rescoring. It never parses as a signature, so these rows cannot be
+    /// reached, overwritten or dropped through another signature.
+    fn rescoring_key(model_signature: &ModelSignature) -> String {
+        format!("{}#fp32", model_signature)
+    }
+
+    /// The `manifest` key holding the int8 calibration for `model_signature`.
+    fn int8_calibration_key(model_signature: &ModelSignature) -> String {
+        format!("int8_calibration:{}", model_signature)
+    }
+
+    /// Reads the int8 calibration for `model_signature` from the `manifest`,
+    /// recording the unit-range default if none is stored yet.
+    fn load_int8_calibration(
+        conn: &Connection,
+        model_signature: &ModelSignature,
+    ) -> Result<Int8Calibration, DiskError> {
+        let key = Self::int8_calibration_key(model_signature);
+        let stored: Option<String> = conn
+            .query_row("SELECT value FROM manifest WHERE key = ?1", params![&key], |row| {
+                row.get(0)
+            })
+            .optional()?;
+
+        let calibration = match stored {
+            Some(json) => serde_json::from_str::<Int8Calibration>(&json)?,
+            None => {
+                let calibration = Int8Calibration::unit(model_signature.dim);
+                conn.execute(
+                    "INSERT INTO manifest (key, value) VALUES (?1, ?2)",
+                    params![&key, serde_json::to_string(&calibration)?],
+                )?;
+                calibration
+            }
+        };
+        if calibration.dim() != model_signature.dim || calibration.offset.len() != model_signature.dim {
+            return Err(DiskError::InvalidData(format!(
+                "int8 calibration for '{}' does not cover {} dimensions",
+                model_signature, model_signature.dim
+            )));
+        }
+        Ok(calibration)
+    }
+
+    /// Fits the int8 quantization range of the active signature to `samples`.
+    ///
+    /// The default calibration assumes unit-normalized embeddings in `[-1, 1]`.
+    /// Calibrating from representative vectors keeps more precision for models
+    /// with other ranges. Stored codes cannot be re-interpreted, so this is only
+    /// allowed before any vector has been added under the signature.
+    pub fn calibrate_int8(&mut self, samples: &[&[f32]]) -> Result<(), DiskError> {
+        if self.model_signature.dtype != Dtype::Int8 {
+            return Err(DiskError::InvalidData(format!(
+                "'{}' is not an int8 signature",
+                self.model_signature
+            )));
+        }
+        let stored: usize = self.conn.query_row(
+            "SELECT COUNT(*) FROM indices WHERE model_signature = ?1",
+            params![&self.model_signature],
+            |row| row.get(0),
+        )?;
+        if stored > 0 {
+            return Err(DiskError::InvalidData(
+                "Cannot recalibrate int8 quantization once vectors are stored".into(),
+            ));
+        }
+
+        let calibration = Int8Calibration::from_samples(self.model_signature.dim, samples)?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO manifest (key, value) VALUES (?1, ?2)",
+            params![
+                Self::int8_calibration_key(&self.model_signature),
+                serde_json::to_string(&calibration)?
+            ],
+        )?;
+
+        let (index, id_map, data_version) =
+            Self::load_index_from_db(&self.conn, &self.model_signature, &self.index_config)?;
+        *self.index.write()? = index;
+        self.live_vectors.store(id_map.live(), Ordering::SeqCst);
+        *self.id_to_chunk_id.write()? = id_map;
+        self.index_data_version.store(data_version, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// The `manifest` key holding the hashing embedder's IDF weights for
+    /// `model_signature`.
+    fn hashing_idf_key(model_signature: &ModelSignature) -> String {
+        format!("hashing_idf:{}", model_signature)
+    }
+
+    /// Returns a [`HashingEmbedder`] for the active signature, weighted by the
+    /// IDF weights stored with `set_hashing_idf` if there are any.
+    ///
+    /// Fails with `DiskError::Embedding` unless the signature's model is
+    /// [`HashingEmbedder::MODEL`].
+    pub fn hashing_embedder(&self) -> Result<HashingEmbedder, DiskError> {
+        let embedder = HashingEmbedder::new(self.model_signature.dim)?;
+        embedder.check_signature(&self.model_signature)?;
+
+        let stored: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT value FROM manifest WHERE key = ?1",
+                params![Self::hashing_idf_key(&self.model_signature)],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match stored {
+            Some(json) => embedder.with_idf(serde_json::from_str(&json)?),
+            None => Ok(embedder),
+        }
+    }
+
+    /// Stores IDF weights for the hashing embedder of the active signature, so
+    /// `hashing_embedder` embeds queries the same way the chunks were embedded.
+    /// Usually fitted with [`HashingEmbedder::fit_idf`] on the chunks being added.
+    pub fn set_hashing_idf(&mut self, idf: &IdfWeights) -> Result<(), DiskError> {
+        HashingEmbedder::new(self.model_signature.dim)?
+            .with_idf(idf.clone())?
+            .check_signature(&self.model_signature)?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO manifest (key, value) VALUES (?1, ?2)",
+            params![Self::hashing_idf_key(&self.model_signature), serde_json::to_string(idf)?],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the HNSW parameters this instance builds and searches with.
+    pub fn index_config(&self) -> &IndexConfig {
+        &self.index_config
+    }
+
+    /// Returns the model signature this instance is actively managing.
+    pub fn model_signature(&self) -> &ModelSignature {
+        &self.model_signature
+    }
+
+    /// Lists every model signature with embeddings on the disk, plus the default
+    /// and active signatures even if they have none yet, ordered by signature.
+    ///
+    /// The fp32 copies binary signatures keep for rescoring are not listed.
+    pub fn list_model_signatures(&self) -> Result<Vec<SignatureInfo>, DiskError> {
+        let total_chunks: usize = self.conn.query_row("SELECT COUNT(*) FROM chunks", [], |row| row.get(0))?;
+        let default_signature = self.default_model_signature()?;
+        let active_signature = self.model_signature.to_string();
+
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT model_signature, COUNT(*) FROM indices
+                 WHERE index_type = 'vector_embedding' GROUP BY model_signature",
+            )?;
+        for row in stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (signature, vectors) = row?;
+            counts.insert(signature, vectors);
+        }
+        if let Some(default_signature) = &default_signature {
+            counts.entry(default_signature.clone()).or_insert(0);
+        }
+        counts.entry(active_signature.clone()).or_insert(0);
+
+        Ok(counts
+            .into_iter()
+            .map(|(model_signature, vectors)| SignatureInfo {
+                coverage: if total_chunks == 0 { 0.0 } else { vectors as f32 / total_chunks as f32 },
+                is_default: default_signature.as_ref() == Some(&model_signature),
+                is_active: model_signature == active_signature,
+                model_signature,
+                vectors,
+            })
+            .collect())
+    }
+
+    /// The default model signature, recorded in the `manifest` when the disk was
+    /// created.
+    ///
+    /// Disks written before the default was recorded fall back to the only
+    /// signature with stored embeddings, and have no default if there are
+    /// several or none.
+    pub fn default_model_signature(&self) -> Result<Option<String>, DiskError> {
+        Self::read_default_signature(&self.conn)
+    }
+
+    fn read_default_signature(conn: &Connection) -> Result<Option<String>, DiskError> {
+        let recorded = conn
+            .query_row("SELECT value FROM manifest WHERE key = 'model_signature'", [], |row| row.get(0))
+            .optional()?;
+        if recorded.is_some() {
+            return Ok(recorded);
+        }
+        let stored: Vec<String> = conn
+            .prepare("SELECT DISTINCT model_signature FROM indices WHERE index_type = 'vector_embedding' LIMIT 2")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(match <[String; 1]>::try_from(stored) {
+            Ok([signature]) => Some(signature),
+            Err(_) => None,
+        })
+    }
+
+    /// Stores the embedding of an existing chunk under `model_signature`, which
+    /// need not be the active one, replacing any embedding already stored there.
+    ///
+    /// Without a metric suffix the signature uses the disk's metric. If it is
+    /// the active signature, the in-memory index is updated as well.
+    pub fn add_embedding(
+        &mut self,
+        chunk_id: &str,
+        model_signature: &str,
+        embedding: QueryVector,
+    ) -> Result<(), DiskError> {
+        let model_signature = Self::disk_signature(&self.conn, model_signature)?;
+        let is_active = model_signature == self.model_signature;
+
+        let encoded = if is_active {
+            self.encode_embedding(&embedding)?
+        } else {
+            let calibration = match model_signature.dtype {
+                Dtype::Int8 => Some(Self::load_int8_calibration(&self.conn, &model_signature)?),
+                _ => None,
+            };
+            Self::encode_for_signature(&model_signature, calibration.as_ref(), &embedding)?
+        };
+
+        let tx = self.conn.transaction()?;
+        let exists = tx
+            .query_row("SELECT 1 FROM chunks WHERE chunk_id = ?1", params![chunk_id], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if !exists {
+            return Err(DiskError::NotFound(chunk_id.to_string()));
+        }
+        Self::write_embedding(&tx, &model_signature, chunk_id, &encoded)?;
+        tx.commit()?;
+
+        if is_active {
+            // Retire any previous HNSW node for the chunk and index the new
+            // vector under one lock, as in `update_chunk`.
+            let mut index = self.index.write()?;
+            let mut id_map = self.id_to_chunk_id.write()?;
+            let chunk_ids = [chunk_id.to_string()];
+            self.mark_dead(&mut id_map, &chunk_ids);
+            self.insert_vectors(&mut index, &mut id_map, &chunk_ids, &[encoded.vector])?;
+        }
+        Ok(())
+    }
+
+    /// Loads the index of another model signature in place of the active one,
+    /// without reopening the disk. Chunks without an embedding under the new
+    /// signature are not searchable until one is added.
+    pub fn switch_model_signature(&mut self, model_signature: &str) -> Result<(), DiskError> {
+        let model_signature = Self::disk_signature(&self.conn, model_signature)?;
+        let (index, id_map, data_version) =
+            Self::load_index_from_db(&self.conn, &model_signature, &self.index_config)?;
+
+        let mut index_guard = self.index.write()?;
+        let mut id_map_guard = self.id_to_chunk_id.write()?;
+        *index_guard = index;
+        self.live_vectors.store(id_map.live(), Ordering::SeqCst);
+        *id_map_guard = id_map;
+        drop((index_guard, id_map_guard));
+        self.index_data_version.store(data_version, Ordering::SeqCst);
+        self.model_signature = model_signature;
+        Ok(())
+    }
+
+    /// Records `model_signature` as the disk's default signature, the one
+    /// `open_default` loads.
+    ///
+    /// Without a metric suffix the signature uses the disk's metric.
+    pub fn set_default_model_signature(&mut self, model_signature: &str) -> Result<(), DiskError> {
+        let model_signature = Self::disk_signature(&self.conn, model_signature)?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO manifest (key, value) VALUES ('model_signature', ?1)",
+            params![&model_signature],
+        )?;
+        Ok(())
+    }
+
+    /// Deletes every embedding stored under `model_signature`, together with its
+    /// stored graph and `manifest` entries, and returns how many were deleted.
+    ///
+    /// The active and default signatures cannot be dropped; switch away from
+    /// them or record another default first. Dropping a binary signature also
+    /// deletes the fp32 copies it keeps for rescoring; dropping the fp32
+    /// signature of the same model leaves them alone.
+    pub fn drop_model_signature(&mut self, model_signature: &str) -> Result<usize, DiskError> {
+        let model_signature = Self::disk_signature(&self.conn, model_signature)?;
+        if model_signature == self.model_signature {
+            return Err(DiskError::InvalidData(format!(
+                "Cannot drop the active model signature '{}'",
+                model_signature
+            )));
+        }
+        if self.default_model_signature()? == Some(model_signature.to_string()) {
+            return Err(DiskError::InvalidData(format!(
+                "Cannot drop the default model signature '{}'",
+                model_signature
+            )));
+        }
+
+        let tx = self.conn.transaction()?;
+        let deleted = tx.execute(
+            "DELETE FROM indices WHERE model_signature = ?1",
+            params![&model_signature],
+        )?;
+        tx.execute(
+            "DELETE FROM indices WHERE model_signature = ?1",
+            params![Self::rescoring_key(&model_signature)],
+        )?;
+        tx.execute(
+            "DELETE FROM hnsw_graphs WHERE model_signature = ?1",
+            params![&model_signature],
+        )?;
+        let mut delete_key = tx.prepare("DELETE FROM manifest WHERE key = ?1")?;
+        for key in [
+            Self::int8_calibration_key(&model_signature),
+            Self::hashing_idf_key(&model_signature),
+            Self::reembed_progress_key(&model_signature),
+        ] {
+            delete_key.execute(params![key])?;
+        }
+        drop(delete_key);
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    /// The `manifest` key holding the progress of re-embedding into
+    /// `model_signature`.
+    fn reembed_progress_key(model_signature: &ModelSignature) -> String {
+        format!("reembed:{}", model_signature)
+    }
+
+    /// Embeds the content of every chunk without an embedding under
+    /// `target_signature`, sending `batch_size` chunks at a time to `embedder`.
+    ///
+    /// Each batch is committed together with the run's progress in the
+    /// `manifest`, so a run that fails or is interrupted resumes after its last
+    /// committed batch when called again. Searches keep using the active index
+    /// throughout; if `target_signature` is the active signature, new vectors
+    /// become searchable as their batch is committed.
+    ///
+    /// Chunks are visited in `chunk_id` order, so chunks added during a run may
+    /// be passed over. The report lists every chunk still missing an embedding
+    /// when the run ends, and another run embeds them.
+    pub fn reembed(
+        &mut self,
+        target_signature: &str,
+        embedder: &dyn Embedder,
+        batch_size: usize,
+    ) -> Result<ReembedReport, DiskError> {
+        self.reembed_with_options(target_signature, embedder, &ReembedOptions::new(batch_size))
+    }
+
+    /// Like [`reembed`](Self::reembed), tuned by `options`.
+    ///
+    /// With `drop_old`, a run that leaves every chunk embedded makes
+    /// `target_signature` the default and drops the previous default. The
+    /// previous default cannot be dropped while it is active, so such runs are
+    /// rejected before embedding anything; switch to the target first.
+    pub fn reembed_with_options(
+        &mut self,
+        target_signature: &str,
+        embedder: &dyn Embedder,
+        options: &ReembedOptions,
+    ) -> Result<ReembedReport, DiskError> {
+        let mut run = self.begin_reembed(target_signature, embedder, options)?;
+        loop {
+            let batch = self.next_reembed_batch(&run)?;
+            if batch.is_empty() {
+                break;
+            }
+            let embeddings = Self::embed_reembed_batch(embedder, &batch)?;
+            self.write_reembed_batch(&mut run, &batch, &embeddings)?;
+        }
+        self.finish_reembed(run)
+    }
+
+    /// Validates a re-embedding run and picks up the progress of an
+    /// interrupted one.
+    pub(crate) fn begin_reembed(
+        &self,
+        target_signature: &str,
+        embedder: &dyn Embedder,
+        options: &ReembedOptions,
+    ) -> Result<ReembedRun, DiskError> {
+        if options.batch_size == 0 {
+            return Err(DiskError::InvalidData("Batch size must be non-zero".into()));
+        }
+        let target = Self::disk_signature(&self.conn, target_signature)?;
+        embedder.check_signature(&target)?;
+        let old_default = match self.default_model_signature()? {
+            Some(default) if options.drop_old => Some(Self::disk_signature(&self.conn, &default)?),
+            _ => None,
+        }
+        .filter(|default| *default != target);
+        if old_default.as_ref() == Some(&self.model_signature) {
+            return Err(DiskError::InvalidData(format!(
+                "Cannot drop the active model signature '{}'; switch to '{}' first",
+                self.model_signature, target
+            )));
+        }
+
+        let progress_key = Self::reembed_progress_key(&target);
+        let stored: Option<String> = self
+            .conn
+            .query_row("SELECT value FROM manifest WHERE key = ?1", params![&progress_key], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        let resumed = stored.is_some();
+        let progress = match stored {
+            Some(json) => serde_json::from_str::<ReembedProgress>(&json)?,
+            None => ReembedProgress::default(),
+        };
+        Ok(ReembedRun {
+            target,
+            old_default,
+            batch_size: options.batch_size,
+            progress_key,
+            progress,
+            resumed,
+            embedded: 0,
+        })
+    }
+
+    /// The `(chunk_id, content)` of the next chunks `run` has to embed; empty
+    /// once every chunk after its cursor is embedded.
+    pub(crate) fn next_reembed_batch(&self, run: &ReembedRun) -> Result<Vec<(String, String)>, DiskError> {
+        let batch = self
+            .conn
+            .prepare_cached(
+                "SELECT c.chunk_id, c.content FROM chunks c
+                 WHERE (?1 IS NULL OR c.chunk_id > ?1)
+                   AND NOT EXISTS (SELECT 1 FROM indices i WHERE i.chunk_id = c.chunk_id AND i.model_signature = ?2)
+                 ORDER BY c.chunk_id LIMIT ?3",
+            )?
+            .query_map(params![&run.progress.cursor, &run.target, run.batch_size as i64], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(batch)
+    }
+
+    /// Embeds the contents of a batch from `next_reembed_batch`.
+    pub(crate) fn embed_reembed_batch(
+        embedder: &dyn Embedder,
+        batch: &[(String, String)],
+    ) -> Result<Vec<Vec<f32>>, DiskError> {
+        let texts: Vec<&str> = batch.iter().map(|(_, content)| content.as_str()).collect();
+        let embeddings = embedder.embed(&texts)?;
+        if embeddings.len() != batch.len() {
+            return Err(DiskError::Embedding(format!(
+                "Expected {} embeddings, got {}",
+                batch.len(),
+                embeddings.len()
+            )));
+        }
+        Ok(embeddings)
+    }
+
+    /// Stores the embeddings of a batch and advances the run's progress in one
+    /// transaction.
+    ///
+    /// Chunks deleted, changed or embedded by another write since the batch was
+    /// read are skipped; a changed chunk is left for the next run.
+    pub(crate) fn write_reembed_batch(
+        &mut self,
+        run: &mut ReembedRun,
+        batch: &[(String, String)],
+        embeddings: &[Vec<f32>],
+    ) -> Result<(), DiskError> {
+        let Some((last_chunk_id, _)) = batch.last() else {
+            return Ok(());
+        };
+        // Checked per batch, as the active signature may be switched between them.
+        let is_active = run.target == self.model_signature;
+        let calibration = match run.target.dtype {
+            Dtype::Int8 if !is_active => Some(Self::load_int8_calibration(&self.conn, &run.target)?),
+            _ => None,
+        };
+        let encoded = embeddings
+            .iter()
+            .map(|embedding| {
+                let embedding = QueryVector::F32(embedding);
+                if is_active {
+                    self.encode_embedding(&embedding)
+                } else {
+                    Self::encode_for_signature(&run.target, calibration.as_ref(), &embedding)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let tx = self.conn.transaction()?;
+        let mut written = Vec::with_capacity(batch.len());
+        {
+            let mut pending = tx.prepare_cached(
+                "SELECT 1 FROM chunks c WHERE c.chunk_id = ?1 AND c.content = ?2
+                   AND NOT EXISTS (SELECT 1 FROM indices i WHERE i.chunk_id = c.chunk_id AND i.model_signature = ?3)",
+            )?;
+            for ((chunk_id, content), encoded) in batch.iter().zip(encoded) {
+                if pending.exists(params![chunk_id, content, &run.target])? {
+                    Self::write_embedding(&tx, &run.target, chunk_id, &encoded)?;
+                    written.push((chunk_id.clone(), encoded.vector));
+                }
+            }
+        }
+        let progress = ReembedProgress {
+            cursor: Some(last_chunk_id.clone()),
+            embedded: run.progress.embedded + written.len(),
+        };
+        tx.execute(
+            "INSERT OR REPLACE INTO manifest (key, value) VALUES (?1, ?2)",
+            params![&run.progress_key, serde_json::to_string(&progress)?],
+        )?;
+        tx.commit()?;
+        run.progress = progress;
+        run.embedded += written.len();
+
+        if is_active {
+            let (chunk_ids, vectors): (Vec<String>, Vec<IndexVector>) = written.into_iter().unzip();
+            self.index_vectors(&chunk_ids, &vectors)?;
+        }
+        Ok(())
+    }
+
+    /// Ends a run: clears its progress, reports what is still missing and, with
+    /// `drop_old`, replaces the default signature if nothing is.
+    pub(crate) fn finish_reembed(&mut self, run: ReembedRun) -> Result<ReembedReport, DiskError> {
+        let target = run.target;
+        // The run is over; the next one starts from the first chunk again.
+        self.conn.execute("DELETE FROM manifest WHERE key = ?1", params![&run.progress_key])?;
+        let total_chunks: usize = self.conn.query_row("SELECT COUNT(*) FROM chunks", [], |row| row.get(0))?;
+        let missing = self
+            .conn
+            .prepare(
+                "SELECT c.chunk_id FROM chunks c
+                 WHERE NOT EXISTS (SELECT 1 FROM indices i WHERE i.chunk_id = c.chunk_id AND i.model_signature = ?1)
+                 ORDER BY c.chunk_id",
+            )?
+            .query_map(params![&target], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut dropped = 0;
+        let dropped_signature = match run.old_default {
+            Some(old_default) if missing.is_empty() => {
+                self.set_default_model_signature(&target.to_string())?;
+                dropped = self.drop_model_signature(&old_default.to_string())?;
+                Some(old_default.to_string())
+            }
+            _ => None,
+        };
+        Ok(ReembedReport {
+            model_signature: target.to_string(),
+            embedded: run.embedded,
+            resumed: run.resumed,
+            total_chunks,
+            missing,
+            dropped_signature,
+            dropped,
+        })
+    }
+
+    /// Retrieves the specification version of the disk.
+    pub fn get_spec_version(&self) -> Result<String, DiskError> {
+        let version = self.conn.query_row(
+            "SELECT value FROM manifest WHERE key = 'spec_version'",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(version)
+    }
+
+    /// Returns every `manifest` entry, including the library-managed ones.
+    ///
+    /// Values are JSON; library-managed plain-text values such as
+    /// `spec_version` are returned as JSON strings.
+    pub fn get_manifest(&self) -> Result<BTreeMap<String, Json>, DiskError> {
+        let mut stmt = self.conn.prepare("SELECT key, value FROM manifest")?;
+        let mut rows = stmt.query([])?;
+        let mut manifest = BTreeMap::new();
+        while let Some(row) = rows.next()? {
+            let key: String = row.get(0)?;
+            let value: String = row.get(1)?;
+            let value = if PLAIN_TEXT_MANIFEST_KEYS.contains(&key.as_str()) {
+                Json::String(value)
+            } else {
+                serde_json::from_str(&value).unwrap_or(Json::String(value))
+            };
+            manifest.insert(key, value);
+        }
+        Ok(manifest)
+    }
+
+    /// Sets a disk-level property such as an owner, description or license.
+    ///
+    /// Fails with `DiskError::ReservedManifestKey` for keys the library manages.
+    pub fn set_manifest_value(&mut self, key: &str, value: Json) -> Result<(), DiskError> {
+        Self::check_manifest_key(key)?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO manifest (key, value) VALUES (?1, ?2)",
+            params![key, value.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Removes a disk-level property.
+    ///
+    /// Fails with `DiskError::ReservedManifestKey` for keys the library manages
+    /// and `DiskError::NotFound` if the key is not set.
+    pub fn remove_manifest_value(&mut self, key: &str) -> Result<(), DiskError> {
+        Self::check_manifest_key(key)?;
+        let rows_affected = self
+            .conn
+            .execute("DELETE FROM manifest WHERE key = ?1", params![key])?;
+        if rows_affected == 0 {
+            return Err(DiskError::NotFound(key.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Whether `key` is managed by the library rather than set by users.
+    pub fn is_reserved_manifest_key(key: &str) -> bool {
+        RESERVED_MANIFEST_KEYS.contains(&key)
+            || RESERVED_MANIFEST_PREFIXES.iter().any(|prefix| key.starts_with(prefix))
+    }
+
+    fn check_manifest_key(key: &str) -> Result<(), DiskError> {
+        if Self::is_reserved_manifest_key(key) {
+            return Err(DiskError::ReservedManifestKey(key.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns the type of the currently loaded search index.
+    pub fn get_index_type_description(&self) -> Result<String, DiskError> {
+        let index_guard = self.index.read()?;
+        let metric = match self.model_signature.metric {
+            Metric::Cosine => "Cosine",
+            Metric::L2 => "L2",
+            Metric::Dot => "Dot Product",
+        };
+        Ok(match *index_guard {
+            SearchIndex::F32(_) => format!("F32 ({metric} Distance)"),
+            SearchIndex::F16(_) => format!("F16 (stored as fp16, searched as f32, {metric} Distance)"),
+            SearchIndex::I8(_) => format!("I8 (scalar-quantized int8, {metric} Distance on dequantized values)"),
+            SearchIndex::Bin(_) => format!("Bin (sign-quantized bits, Hamming Distance with f32 {metric} rescoring)"),
+            // Add other types as they are implemented
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tempfile::TempDir;
+
+    /// Creates `disk.idz` under a new temporary directory for `signature`, whose
+    /// model must be the hashing embedder, and adds `texts` in one batch with the
+    /// metadata `metadata` returns for each position.
+    ///
+    /// # Returns
+    /// The directory, which deletes the disk when dropped, the disk and the
+    /// chunk ids in the order of `texts`.
+    fn disk_with_metadata<S: AsRef<str>>(
+        texts: &[S],
+        signature: &str,
+        metadata: impl Fn(usize) -> Option<Json>,
+    ) -> (TempDir, IdentityDisk, Vec<String>) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut disk = IdentityDisk::create(dir.path().join("disk.idz"), signature).unwrap();
+        let texts: Vec<&str> = texts.iter().map(AsRef::as_ref).collect();
+        let embeddings = disk.hashing_embedder().unwrap().embed(&texts).unwrap();
+        let chunks: Vec<NewChunk> = texts
+            .iter()
+            .zip(&embeddings)
+            .enumerate()
+            .map(|(i, (content, embedding))| NewChunk {
+                content,
+                embedding: QueryVector::F32(embedding),
+                metadata: metadata(i),
+            })
+            .collect();
+        let chunk_ids = disk.add_chunks(&chunks).unwrap();
+        (dir, disk, chunk_ids)
+    }
+
+    /// [`disk_with_metadata`] without metadata.
+    fn disk_with<S: AsRef<str>>(texts: &[S], signature: &str) -> (TempDir, IdentityDisk, Vec<String>) {
+        disk_with_metadata(texts, signature, |_| None)
+    }
+
+    /// The live-vector count `SearchMode::Auto` uses, checked against the id map.
+    fn live_vectors(disk: &IdentityDisk) -> usize {
+        let live = disk.live_vectors.load(Ordering::SeqCst);
+        assert_eq!(live, disk.id_to_chunk_id.read().unwrap().iter().flatten().count());
+        live
+    }
+
+    fn stored_under(disk: &IdentityDisk, model_signature: &str) -> usize {
+        disk.conn
+            .query_row("SELECT COUNT(*) FROM indices WHERE model_signature = ?1", params![model_signature], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    /// The contents of `results`, best first.
+    fn contents(results: &[SearchResult]) -> Vec<&str> {
+        results.iter().map(|r| r.chunk.content.as_str()).collect()
+    }
+
+    #[test]
+    fn updated_and_deleted_chunks_never_appear_in_results() {
+        let texts: Vec<String> = (0..30).map(|i| format!("chunk {} in group {}", i, i % 3)).collect();
+        let (dir, mut disk, chunk_ids) =
+            disk_with_metadata(&texts, "local/hashing-16_fp32", |i| Some(serde_json::json!({ "group": i % 3 })));
+        let path = dir.path().join("disk.idz");
+        let embedder = disk.hashing_embedder().unwrap();
+        let embed = |text: &str| embedder.embed(&[text]).unwrap().remove(0);
+
+        disk.update_chunk(&chunk_ids[0], "a replacement", QueryVector::F32(&embed("a replacement")), None).unwrap();
+        disk.delete_chunk(&chunk_ids[1]).unwrap();
+        assert_eq!(disk.delete_chunks(|chunk| chunk.metadata["group"] == 2).unwrap(), 10);
+        let deleted: HashSet<&String> =
+            chunk_ids.iter().enumerate().filter(|(i, _)| *i == 1 || i % 3 == 2).map(|(_, id)| id).collect();
+
+        let check = |disk: &IdentityDisk| {
+            for mode in [SearchMode::Approximate, SearchMode::Exact] {
+                let options = SearchOptions::default().with_mode(mode);
+                for query in ["chunk 0 in group 0", "chunk 1 in group 1", "chunk 5 in group 2", "a replacement"] {
+                    let results = disk.search_with_options(QueryVector::F32(&embed(query)), 30, &options).unwrap();
+                    assert!(results.iter().all(|r| !deleted.contains(&r.chunk.chunk_id)), "{mode:?} {query}");
+                    let updated: Vec<_> = results.iter().filter(|r| r.chunk.chunk_id == chunk_ids[0]).collect();
+                    assert!(updated.iter().all(|r| r.chunk.content == "a replacement"));
+                    // HNSW may miss a live node, but never returns one twice.
+                    if mode == SearchMode::Exact {
+                        assert_eq!(results.len(), 19, "{query}");
+                        assert_eq!(updated.len(), 1, "{query}");
+                    } else {
+                        assert!(results.len() <= 19 && updated.len() <= 1, "{query}");
+                    }
+                }
+                let results = disk.search_with_options(QueryVector::F32(&embed("a replacement")), 1, &options).unwrap();
+                assert_eq!(results[0].chunk.chunk_id, chunk_ids[0], "{mode:?}");
+            }
+        };
+        check(&disk);
+        drop(disk);
+
+        // Reopening rebuilds the graph from the remaining rows.
+        let disk = IdentityDisk::open(&path, "local/hashing-16_fp32").unwrap();
+        check(&disk);
+        drop(disk);
+
+        // A graph persisted with its tombstones reloads with them.
+        let mut disk = IdentityDisk::open(&path, "local/hashing-16_fp32").unwrap();
+        disk.delete_chunk(&chunk_ids[3]).unwrap();
+        disk.persist_index().unwrap();
+        drop(disk);
+        let disk = IdentityDisk::open(&path, "local/hashing-16_fp32").unwrap();
+        let options = SearchOptions::default().with_mode(SearchMode::Approximate);
+        let results = disk.search_with_options(QueryVector::F32(&embed("chunk 3 in group 0")), 30, &options).unwrap();
+        assert!(!results.is_empty() && results.len() <= 18);
+        assert!(results.iter().all(|r| r.chunk.chunk_id != chunk_ids[3] && !deleted.contains(&r.chunk.chunk_id)));
+    }
+
+    #[test]
+    fn a_failing_batch_item_rolls_back_the_whole_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut disk = IdentityDisk::create(dir.path().join("disk.idz"), "local/hashing-8_fp32").unwrap();
+        let embedder = HashingEmbedder::new(8).unwrap();
+        let texts = ["first", "second", "poison", "fourth"];
+        let embeddings = embedder.embed(&texts).unwrap();
+        let batch = |wrong_dim: Option<usize>| -> Vec<NewChunk> {
+            texts
+                .iter()
+                .zip(&embeddings)
+                .enumerate()
+                .map(|(i, (content, embedding))| NewChunk {
+                    content,
+                    embedding: QueryVector::F32(if Some(i) == wrong_dim { &embedding[..4] } else { embedding }),
+                    metadata: None,
+                })
+                .collect()
+        };
+
+        // An embedding of the wrong dimension is rejected before anything is written.
+        let err = disk.add_chunks(&batch(Some(3))).unwrap_err();
+        assert!(matches!(err, DiskError::BatchItem { index: 3, .. }), "{err:?}");
+
+        // A row the database rejects undoes the rows written before it.
+        disk.conn
+            .execute_batch(
+                "CREATE TEMP TRIGGER reject_poison BEFORE INSERT ON chunks WHEN NEW.content = 'poison'
+                 BEGIN SELECT RAISE(ABORT, 'poisoned'); END",
+            )
+            .unwrap();
+        let err = disk.add_chunks(&batch(None)).unwrap_err();
+        assert!(matches!(err, DiskError::BatchItem { index: 2, .. }), "{err:?}");
+
+        assert!(disk.get_chunks().unwrap().is_empty());
+        assert_eq!(stored_under(&disk, "local/hashing-8_fp32"), 0);
+        assert_eq!(live_vectors(&disk), 0);
+        assert!(disk.search(QueryVector::F32(&embeddings[0]), 4).unwrap().is_empty());
+
+        disk.conn.execute_batch("DROP TRIGGER reject_poison").unwrap();
+        assert_eq!(disk.add_chunks(&batch(None)).unwrap().len(), 4);
+        assert_eq!(live_vectors(&disk), 4);
+    }
+
+    #[test]
+    fn batches_are_stored_in_order_and_each_chunk_is_its_own_nearest_neighbour() {
+        let texts: Vec<String> = (0..300).map(|i| format!("batch entry {} of {}", i, i * 7 % 13)).collect();
+        let (_dir, disk, chunk_ids) =
+            disk_with_metadata(&texts, "local/hashing-64_fp32", |i| Some(serde_json::json!({ "i": i })));
+        let embedder = disk.hashing_embedder().unwrap();
+        let embeddings = embedder.embed(&texts.iter().map(String::as_str).collect::<Vec<_>>()).unwrap();
+
+        assert_eq!(chunk_ids.len(), texts.len());
+        assert_eq!(live_vectors(&disk), texts.len());
+        let stored: HashMap<String, Chunk> =
+            disk.get_chunks().unwrap().into_iter().map(|chunk| (chunk.chunk_id.clone(), chunk)).collect();
+        for (i, chunk_id) in chunk_ids.iter().enumerate() {
+            assert_eq!(stored[chunk_id].content, texts[i]);
+            assert_eq!(stored[chunk_id].metadata["i"], i);
+        }
+
+        // Every vector was inserted into HNSW under its own chunk.
+        let exact = SearchOptions::default().with_mode(SearchMode::Exact);
+        let approximate = SearchOptions::default().with_mode(SearchMode::Approximate);
+        let mut found = 0;
+        for (chunk_id, embedding) in chunk_ids.iter().zip(&embeddings) {
+            let results = disk.search_with_options(QueryVector::F32(embedding), 1, &exact).unwrap();
+            assert_eq!(&results[0].chunk.chunk_id, chunk_id);
+            let results = disk.search_with_options(QueryVector::F32(embedding), 1, &approximate).unwrap();
+            found += usize::from(&results[0].chunk.chunk_id == chunk_id);
+        }
+        assert!(found >= texts.len() * 95 / 100, "HNSW found {found} of {}", texts.len());
+    }
+
+    /// Every chunk's stored blob length under `model_signature`.
+    fn stored_lengths(disk: &IdentityDisk, model_signature: &str) -> Vec<usize> {
+        disk.conn
+            .prepare("SELECT length(data) FROM indices WHERE model_signature = ?1")
+            .unwrap()
+            .query_map(params![model_signature], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn fp16_disks_store_half_the_bytes_and_rank_like_fp32() {
+        let dir = tempfile::tempdir().unwrap();
+        let vectors: Vec<Vec<f32>> =
+            (0..40).map(|i| (0..8).map(|j| ((i * 8 + j) as f32 * 0.37).sin()).collect()).collect();
+        let mut fp32 = IdentityDisk::create(dir.path().join("fp32.idz"), "x/m-8_fp32").unwrap();
+        let mut fp16 = IdentityDisk::create(dir.path().join("fp16.idz"), "x/m-8_fp16").unwrap();
+        for (i, vector) in vectors.iter().enumerate() {
+            let content = format!("vector {}", i);
+            fp32.add_chunk(&content, QueryVector::F32(vector), None).unwrap();
+            if i % 2 == 0 {
+                fp16.add_chunk(&content, QueryVector::F32(vector), None).unwrap();
+            } else {
+                let narrowed: Vec<f16> = vector.iter().map(|&f| f16::from_f32(f)).collect();
+                fp16.add_chunk(&content, QueryVector::F16(&narrowed), None).unwrap();
+            }
+        }
+        assert!(stored_lengths(&fp32, "x/m-8_fp32").iter().all(|&len| len == 32));
+        assert!(stored_lengths(&fp16, "x/m-8_fp16").iter().all(|&len| len == 16));
+
+        let check = |fp16: &IdentityDisk| {
+            let options = SearchOptions::default().with_mode(SearchMode::Exact);
+            for query in vectors.iter().step_by(7) {
+                let expected = fp32.search_with_options(QueryVector::F32(query), 5, &options).unwrap();
+                let results = fp16.search_with_options(QueryVector::F32(query), 5, &options).unwrap();
+                let narrowed: Vec<f16> = query.iter().map(|&f| f16::from_f32(f)).collect();
+                let half_query = fp16.search_with_options(QueryVector::F16(&narrowed), 5, &options).unwrap();
+                assert_eq!(results[0].chunk.content, expected[0].chunk.content);
+                assert_eq!(half_query[0].chunk.content, expected[0].chunk.content);
+                for (result, expected) in results.iter().zip(&expected) {
+                    assert!((result.distance - expected.distance).abs() < 1e-3, "{result:?} {expected:?}");
+                }
+            }
+        };
+        check(&fp16);
+        drop(fp16);
+        // Reopening widens the stored halves again.
+        check(&IdentityDisk::open(dir.path().join("fp16.idz"), "x/m-8_fp16").unwrap());
+    }
+
+    #[test]
+    fn int8_disks_quantize_with_their_stored_calibration() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("int8.idz");
+        // Components range over [-4, 4], well outside the default unit calibration.
+        let vectors: Vec<Vec<f32>> =
+            (0..40).map(|i| (0..16).map(|j| 4.0 * ((i * 16 + j) as f32 * 0.37).sin()).collect()).collect();
+        let samples: Vec<&[f32]> = vectors.iter().map(Vec::as_slice).collect();
+        let mut disk = IdentityDisk::create(&path, "x/m-16_int8").unwrap();
+        disk.calibrate_int8(&samples).unwrap();
+        for (i, vector) in vectors.iter().enumerate() {
+            disk.add_chunk(&format!("vector {}", i), QueryVector::F32(vector), None).unwrap();
+        }
+        // One byte per dimension, a quarter of the fp32 size.
+        assert!(stored_lengths(&disk, "x/m-16_int8").iter().all(|&len| len == 16));
+        assert!(matches!(disk.calibrate_int8(&samples), Err(DiskError::InvalidData(_))));
+
+        let calibration = Int8Calibration::from_samples(16, &samples).unwrap();
+        // Returns the exact rankings, which must not change across a reopen.
+        let check = |disk: &IdentityDisk| -> Vec<Vec<(String, f32)>> {
+            let mut rankings = Vec::new();
+            for mode in [SearchMode::Exact, SearchMode::Approximate] {
+                let options = SearchOptions::default().with_mode(mode);
+                for (i, query) in vectors.iter().enumerate().step_by(7) {
+                    let results = disk.search_with_options(QueryVector::F32(query), 3, &options).unwrap();
+                    assert_eq!(results[0].chunk.content, format!("vector {}", i), "{mode:?}");
+                    let codes = calibration.quantize(query);
+                    let quantized = disk.search_with_options(QueryVector::I8(&codes), 3, &options).unwrap();
+                    assert_eq!(quantized[0].chunk.content, results[0].chunk.content, "{mode:?}");
+                    if mode == SearchMode::Exact {
+                        rankings.push(results.into_iter().map(|r| (r.chunk.content, r.distance)).collect());
+                    }
+                }
+            }
+            rankings
+        };
+        let before = check(&disk);
+        drop(disk);
+
+        // The calibration is read back from the manifest rather than reset.
+        let mut disk = IdentityDisk::open(&path, "x/m-16_int8").unwrap();
+        assert_eq!(check(&disk), before);
+        assert!(matches!(disk.calibrate_int8(&samples), Err(DiskError::InvalidData(_))));
+    }
+
+    #[test]
+    fn binary_rescoring_copies_are_independent_of_the_fp32_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut disk = IdentityDisk::create(dir.path().join("disk.idz"), "x/m-4_bin").unwrap();
+        let near = disk.add_chunk("near", QueryVector::F32(&[1.0, 2.0, 3.0, 4.0]), None).unwrap();
+        let far = disk.add_chunk("far", QueryVector::F32(&[4.0, 3.0, 2.0, 1.0]), None).unwrap();
+        let query = [1.0, 2.0, 3.0, 4.0];
+        let assert_rescored = |disk: &IdentityDisk| {
+            let results = disk.search(QueryVector::F32(&query), 2).unwrap();
+            assert_eq!(results[0].chunk.content, "near");
+            assert_eq!(results[0].metric, ScoreMetric::Cosine);
+            assert!(results[0].distance.abs() < 1e-6, "{}", results[0].distance);
+        };
+        assert_rescored(&disk);
+
+        // Writing the fp32 signature of the same model leaves the copies alone.
+        for chunk_id in [&near, &far] {
+            disk.add_embedding(chunk_id, "x/m-4_fp32", QueryVector::F32(&[-1.0, -2.0, -3.0, -4.0])).unwrap();
+        }
+        assert_rescored(&disk);
+        let listed: Vec<String> = disk.list_model_signatures().unwrap().into_iter().map(|s| s.model_signature).collect();
+        assert_eq!(listed, ["x/m-4_bin", "x/m-4_fp32"]);
+
+        // So does dropping it.
+        assert_eq!(disk.drop_model_signature("x/m-4_fp32").unwrap(), 2);
+        assert_rescored(&disk);
+        assert_eq!(stored_under(&disk, "x/m-4_bin#fp32"), 2);
+
+        // Dropping the binary signature takes its copies with it.
+        disk.add_embedding(&near, "x/m-4_fp32", QueryVector::F32(&query)).unwrap();
+        disk.set_default_model_signature("x/m-4_fp32").unwrap();
+        disk.switch_model_signature("x/m-4_fp32").unwrap();
+        assert_eq!(disk.drop_model_signature("x/m-4_bin").unwrap(), 2);
+        assert_eq!(stored_under(&disk, "x/m-4_bin#fp32"), 0);
+    }
+
+    #[test]
+    fn each_metric_ranks_and_reports_its_own_distance() {
+        let dir = tempfile::tempdir().unwrap();
+        let vectors = [("unit", [1.0, 0.0]), ("long", [3.0, 0.0]), ("tilted", [0.6, 0.8])];
+        let query = [1.0, 0.1];
+        let cases = [
+            ("x/m-2_fp32", ScoreMetric::Cosine, ["unit", "long", "tilted"]),
+            ("x/m-2_fp32_l2", ScoreMetric::L2, ["unit", "tilted", "long"]),
+            ("x/m-2_fp32_dot", ScoreMetric::Dot, ["long", "unit", "tilted"]),
+        ];
+        for (signature, metric, ranking) in cases {
+            let path = dir.path().join(format!("{:?}.idz", metric));
+            let mut disk = IdentityDisk::create(&path, signature).unwrap();
+            for (content, vector) in &vectors {
+                disk.add_chunk(content, QueryVector::F32(vector), None).unwrap();
+            }
+            drop(disk);
+
+            // Without a suffix the signature takes the metric recorded on the disk.
+            let disk = IdentityDisk::open(&path, "x/m-2_fp32").unwrap();
+            assert_eq!(disk.model_signature().to_string(), signature);
+            for mode in [SearchMode::Exact, SearchMode::Approximate] {
+                let options = SearchOptions::default().with_mode(mode);
+                let results = disk.search_with_options(QueryVector::F32(&query), 3, &options).unwrap();
+                // HNSW may miss a node of such a tiny graph, but keeps the order.
+                // Cosine ignores length, so the unit and long vectors tie.
+                if mode == SearchMode::Exact && metric == ScoreMetric::Cosine {
+                    assert_eq!(results[2].chunk.content, "tilted");
+                    assert!((results[0].distance - results[1].distance).abs() < 1e-6);
+                } else if mode == SearchMode::Exact {
+                    assert_eq!(contents(&results), ranking, "{signature}");
+                }
+                assert!(results.windows(2).all(|pair| pair[0].distance <= pair[1].distance), "{signature} {mode:?}");
+                for result in &results {
+                    let vector = vectors.iter().find(|(content, _)| *content == result.chunk.content).unwrap().1;
+                    let dot = vector[0] + 0.1 * vector[1];
+                    let expected = match metric {
+                        ScoreMetric::Cosine => 1.0 - dot / (vector[0].hypot(vector[1]) * 1.01f32.sqrt()),
+                        ScoreMetric::L2 => (vector[0] - 1.0f32).hypot(vector[1] - 0.1),
+                        _ => 1.0 - dot,
+                    };
+                    assert_eq!(result.metric, metric);
+                    assert!((result.distance - expected).abs() < 1e-5, "{signature} {mode:?} {result:?}");
+                }
+            }
+        }
+
+        // A signature naming another metric than the disk's is rejected.
+        let path = dir.path().join("L2.idz");
+        assert!(matches!(IdentityDisk::open(&path, "x/m-2_fp32_dot"), Err(DiskError::InvalidSignature(_))));
+    }
+
+    #[test]
+    fn selective_filters_still_return_top_k_matching_chunks() {
+        let texts: Vec<String> = (0..200).map(|i| format!("passage {} on subject {}", i, i % 9)).collect();
+        // 8 of the 200 chunks, 4%, are rare.
+        let kind = |i: usize| serde_json::json!({ "kind": if i.is_multiple_of(25) { "rare" } else { "common" } });
+        let (_dir, mut disk, _) = disk_with_metadata(&texts, "local/hashing-16_fp32", |i| Some(kind(i)));
+        let embedder = disk.hashing_embedder().unwrap();
+        let embeddings = embedder.embed(&texts.iter().map(String::as_str).collect::<Vec<_>>()).unwrap();
+        disk.set_exact_search_threshold(10);
+        assert!(!disk.searches_exactly(SearchMode::Auto));
+
+        let filter = serde_json::json!({ "kind": "rare" });
+        let search = |query: &[f32], mode: SearchMode| {
+            let options = SearchOptions::default().with_filter(filter.clone()).with_mode(mode);
+            disk.search_with_options(QueryVector::F32(query), 5, &options).unwrap()
+        };
+        for query in embeddings.iter().step_by(13) {
+            let approximate = search(query, SearchMode::Approximate);
+            assert_eq!(approximate.len(), 5);
+            assert!(approximate.iter().all(|r| r.chunk.metadata["kind"] == "rare"), "{approximate:?}");
+
+            // Auto scans the few matching chunks exactly.
+            let exact = search(query, SearchMode::Exact);
+            let auto = search(query, SearchMode::Auto);
+            assert_eq!(exact.len(), 5);
+            assert!(exact.iter().all(|r| r.chunk.metadata["kind"] == "rare"), "{exact:?}");
+            let ids = |results: &[SearchResult]| -> Vec<String> {
+                results.iter().map(|r| r.chunk.chunk_id.clone()).collect()
+            };
+            assert_eq!(ids(&auto), ids(&exact));
+        }
+    }
+
+    #[test]
+    fn keyword_search_follows_every_write_to_chunk_contents() {
+        let firmware = "Printer error E-1042 after the firmware update";
+        let texts = [firmware, "The update fixed error E-2001", "Shipping address changed", "error error error report"];
+        let (dir, mut disk, chunk_ids) = disk_with(&texts, "local/hashing-16_fp32");
+        let path = dir.path().join("disk.idz");
+        let (printer, shipping) = (&chunk_ids[0], &chunk_ids[2]);
+        let embedder = disk.hashing_embedder().unwrap();
+        let embed = |text: &str| embedder.embed(&[text]).unwrap().remove(0);
+
+        assert_eq!(contents(&disk.keyword_search("e-1042", 5).unwrap()), [firmware]);
+        // Every term must match, and quoted terms match as a phrase.
+        assert_eq!(disk.keyword_search("error update", 5).unwrap().len(), 2);
+        assert!(disk.keyword_search("error shipping", 5).unwrap().is_empty());
+        assert_eq!(disk.keyword_search("\"firmware update\"", 5).unwrap().len(), 1);
+        assert!(disk.keyword_search("\"update firmware\"", 5).unwrap().is_empty());
+        assert!(disk.keyword_search("  ", 5).unwrap().is_empty());
+
+        // BM25 ranks the chunk that repeats the term first, lowest rank first.
+        let results = disk.keyword_search("error", 5).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].chunk.content, "error error error report");
+        assert!(results.iter().all(|r| r.metric == ScoreMetric::Bm25 && r.distance < 0.0));
+        assert!(results.windows(2).all(|pair| pair[0].distance <= pair[1].distance));
+        assert_eq!(disk.keyword_search("error", 1).unwrap().len(), 1);
+
+        // Updates and deletes reach the keyword index through its triggers.
+        let content = "Printer error E-3003";
+        disk.update_chunk(shipping, content, QueryVector::F32(&embed(content)), None).unwrap();
+        assert!(disk.keyword_search("shipping", 5).unwrap().is_empty());
+        assert_eq!(contents(&disk.keyword_search("E-3003", 5).unwrap()), [content]);
+        disk.update_chunk_metadata(shipping, serde_json::json!({ "team": "support" })).unwrap();
+        assert_eq!(disk.keyword_search("E-3003", 5).unwrap()[0].chunk.metadata["team"], "support");
+        disk.delete_chunk(printer).unwrap();
+        assert!(disk.keyword_search("E-1042", 5).unwrap().is_empty());
+        assert_eq!(disk.delete_chunks(|chunk| chunk.content.contains("report")).unwrap(), 1);
+        drop(disk);
+
+        let disk = IdentityDisk::open(&path, "local/hashing-16_fp32").unwrap();
+        let results = disk.keyword_search("error", 5).unwrap();
+        let mut remaining = contents(&results);
+        remaining.sort();
+        assert_eq!(remaining, [content, "The update fixed error E-2001"]);
+    }
+
+    #[test]
+    fn hybrid_search_fuses_the_ranks_or_scores_of_both_retrievers() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut disk = IdentityDisk::create(dir.path().join("disk.idz"), "x/m-2_fp32").unwrap();
+        disk.add_chunk("alpha report", QueryVector::F32(&[1.0, 0.0]), None).unwrap();
+        disk.add_chunk("alpha alpha summary", QueryVector::F32(&[0.2, 0.98]), None).unwrap();
+        disk.add_chunk("beta E-77 notes", QueryVector::F32(&[0.0, 1.0]), None).unwrap();
+        for i in 0..10 {
+            let vector = [1.0, 0.02 * (i + 1) as f32];
+            disk.add_chunk(&format!("filler {}", i), QueryVector::F32(&vector), None).unwrap();
+        }
+        let query = QueryVector::F32(&[1.0, 0.0]);
+
+        // With k = 60, the keyword weight lifts every keyword hit above the
+        // fillers, and the vector rank decides between the two "alpha" chunks.
+        let weights = HybridWeights { vector: 1.0, keyword: 2.0, fusion: Fusion::Rrf { k: 60.0 } };
+        let results = disk.hybrid_search("alpha E-77", query.clone(), 3, &weights).unwrap();
+        assert_eq!(contents(&results), ["alpha report", "alpha alpha summary", "beta E-77 notes"]);
+        // The vector neighbours are searched 4 * 3 deep, which misses the last chunk.
+        assert_eq!(results[2].vector, None);
+        let rrf = |hit: Option<RankedScore>, weight: f32| hit.map_or(0.0, |hit| weight / (60.0 + hit.rank as f32));
+        for result in &results {
+            let fused = rrf(result.vector, 1.0) + rrf(result.keyword, 2.0);
+            assert_eq!(result.metric, ScoreMetric::Rrf);
+            assert!((result.distance + fused).abs() < 1e-6, "{result:?}");
+        }
+        // Each retriever's rank is its own position for the query.
+        let vector_hits = disk.search(query.clone(), 12).unwrap();
+        let keyword_hits = disk.fts_search(&fts::to_match_query("alpha E-77", true), 12).unwrap();
+        for result in &results {
+            let id = &result.chunk.chunk_id;
+            let rank_in = |hits: &[SearchResult]| hits.iter().position(|hit| &hit.chunk.chunk_id == id);
+            assert_eq!(result.vector.map(|hit| hit.rank - 1), rank_in(&vector_hits));
+            assert_eq!(result.keyword.map(|hit| hit.rank - 1), rank_in(&keyword_hits));
+        }
+
+        // Weighted scores are min-max normalized, so the best hit of a retriever
+        // that has all the weight scores exactly 1.
+        for (vector, keyword, expected) in [(1.0, 0.0, &vector_hits), (0.0, 1.0, &keyword_hits)] {
+            let weights = HybridWeights { vector, keyword, fusion: Fusion::WeightedScore };
+            let results = disk.hybrid_search("alpha E-77", query.clone(), 3, &weights).unwrap();
+            assert_eq!(contents(&results), contents(&expected[..3]));
+            assert_eq!(results[0].distance, -1.0);
+            assert!(results.iter().all(|r| r.metric == ScoreMetric::WeightedScore));
+            assert!(results.iter().all(|r| (-1.0..=0.0).contains(&r.distance)));
+            assert!(results.windows(2).all(|pair| pair[0].distance <= pair[1].distance));
+        }
+    }
+
+    #[test]
+    fn auto_search_scans_exactly_up_to_the_threshold_of_live_vectors() {
+        let texts: Vec<String> = (0..6).map(|i| format!("chunk number {}", i)).collect();
+        let (dir, mut disk, chunk_ids) = disk_with(&texts[..5], "local/hashing-8_fp32");
+        let path = dir.path().join("disk.idz");
+        let embedder = disk.hashing_embedder().unwrap();
+        let embeddings = embedder.embed(&texts.iter().map(String::as_str).collect::<Vec<_>>()).unwrap();
+        let new_chunk = |i: usize| NewChunk { content: &texts[i], embedding: QueryVector::F32(&embeddings[i]), metadata: None };
+
+        disk.set_exact_search_threshold(5);
+        assert_eq!(live_vectors(&disk), 5);
+        assert!(disk.searches_exactly(SearchMode::Auto));
+        assert!(!disk.searches_exactly(SearchMode::Approximate));
+        let auto = disk.search(QueryVector::F32(&embeddings[2]), 5).unwrap();
+        let exact = disk
+            .search_with_options(QueryVector::F32(&embeddings[2]), 5, &SearchOptions::default().with_mode(SearchMode::Exact))
+            .unwrap();
+        let summary = |results: &[SearchResult]| -> Vec<(String, f32)> {
+            results.iter().map(|r| (r.chunk.chunk_id.clone(), r.distance)).collect()
+        };
+        assert_eq!(summary(&auto), summary(&exact));
+
+        let extra = disk.add_chunks(&[new_chunk(5)]).unwrap();
+        assert_eq!(live_vectors(&disk), 6);
+        assert!(!disk.searches_exactly(SearchMode::Auto));
+
+        // Updates retire one node and add another, so the count is unchanged.
+        disk.update_chunk(&chunk_ids[0], "updated", QueryVector::F32(&embeddings[5]), None).unwrap();
+        assert_eq!(live_vectors(&disk), 6);
+        disk.delete_chunk(&extra[0]).unwrap();
+        assert_eq!(live_vectors(&disk), 5);
+        assert!(disk.searches_exactly(SearchMode::Auto));
+        disk.delete_chunks(|chunk| chunk.content == "chunk number 1").unwrap();
+        assert_eq!(live_vectors(&disk), 4);
+
+        disk.set_exact_search_threshold(0);
+        assert!(!disk.searches_exactly(SearchMode::Auto));
+        disk.persist_index().unwrap();
+        drop(disk);
+        let disk = IdentityDisk::open(&path, "local/hashing-8_fp32").unwrap();
+        assert_eq!(live_vectors(&disk), 4);
+        assert!(disk.searches_exactly(SearchMode::Auto));
+    }
+
+    #[test]
+    fn evaluate_recall_compares_hnsw_with_exact_search() {
+        let texts: Vec<String> = (0..100).map(|i| format!("sample text {} about topic {}", i, i % 7)).collect();
+        let (_dir, disk, _) = disk_with(&texts, "local/hashing-16_fp32");
+        let embedder = disk.hashing_embedder().unwrap();
+        let embeddings = embedder.embed(&texts.iter().map(String::as_str).collect::<Vec<_>>()).unwrap();
+
+        let queries: Vec<QueryVector> = embeddings.iter().take(10).map(|e| QueryVector::F32(e)).collect();
+        let report = disk.evaluate_recall(&queries, 5).unwrap();
+        assert_eq!((report.queries, report.k), (10, 5));
+        assert!(report.recall_at_k >= 0.9 && report.recall_at_k <= 1.0, "{report:?}");
+        assert!(report.min_recall <= report.recall_at_k, "{report:?}");
+
+        assert!(matches!(disk.evaluate_recall(&[], 5), Err(DiskError::InvalidData(_))));
+    }
+
+    #[test]
+    fn exact_search_skips_chunks_deleted_after_the_scan() {
+        let texts: Vec<String> = (0..4).map(|i| format!("chunk number {}", i)).collect();
+        let (dir, disk, _) = disk_with(&texts, "local/hashing-8_fp32");
+        let path = dir.path().join("disk.idz");
+
+        let query = &disk.hashing_embedder().unwrap().embed(&[&texts[0]]).unwrap()[0];
+        let scored = disk
+            .scan_indices(&disk.model_signature, None, |blob| {
+                codec::decode_f32(blob, 8).map(|v| reported_distance(Metric::Cosine, query, &v))
+            })
+            .unwrap();
+        let nearest = scored[0].1.clone();
+
+        // Another connection deletes the nearest chunk before its row is looked up.
+        let mut writer = IdentityDisk::open(&path, "local/hashing-8_fp32").unwrap();
+        writer.delete_chunk(&nearest).unwrap();
+
+        let results = disk.load_scored(scored, 3, ScoreMetric::Cosine).unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.chunk.chunk_id != nearest));
+    }
+
+    /// The checksum of the graph stored for `model_signature`, if any.
+    fn stored_graph_checksum(disk: &IdentityDisk, model_signature: &ModelSignature) -> Option<String> {
+        disk.conn
+            .query_row("SELECT checksum FROM hnsw_graphs WHERE model_signature = ?1", params![model_signature], |row| {
+                row.get(0)
+            })
+            .optional()
+            .unwrap()
+    }
+
+    #[test]
+    fn index_configs_are_validated_recorded_and_rebuild_the_graph_when_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        let signature = "local/hashing-16_fp32";
+        let embedder = HashingEmbedder::new(16).unwrap();
+        let custom = IndexConfig { max_nb_connection: 8, ef_construction: 50, max_layers: 16, ef_search: 20 };
+        let invalid = [
+            IndexConfig { max_nb_connection: 0, ..custom },
+            IndexConfig { ef_search: 0, ..custom },
+            IndexConfig { max_layers: 17, ..custom },
+        ];
+        for config in invalid {
+            let result = IdentityDisk::create_with_config(&path, signature, config);
+            assert!(matches!(result, Err(DiskError::InvalidData(_))), "{config:?}");
+        }
+
+        let mut disk = IdentityDisk::create_with_config(&path, signature, custom).unwrap();
+        for i in 0..40 {
+            let content = format!("configured chunk {}", i);
+            disk.add_chunk(&content, QueryVector::F32(&embedder.embed(&[&content]).unwrap()[0]), None).unwrap();
+        }
+        disk.persist_index().unwrap();
+        drop(disk);
+
+        // The recorded config is used unless another one is passed.
+        let disk = IdentityDisk::open(&path, signature).unwrap();
+        assert_eq!(*disk.index_config(), custom);
+        let recorded = disk.get_manifest().unwrap().remove("index_config").unwrap();
+        assert_eq!(serde_json::from_value::<IndexConfig>(recorded).unwrap(), custom);
+        // ef_search is raised to top_k, so a tiny override still fills the results.
+        let query = embedder.embed(&["configured chunk 7"]).unwrap().remove(0);
+        let options = SearchOptions::default().with_mode(SearchMode::Approximate);
+        let narrow = options.clone().with_ef_search(1);
+        assert_eq!(disk.search_with_options(QueryVector::F32(&query), 10, &narrow).unwrap().len(), 10);
+        drop(disk);
+
+        let result = IdentityDisk::open_with_config(&path, signature, Some(invalid[2]));
+        assert!(matches!(result, Err(DiskError::InvalidData(_))));
+        assert_eq!(*IdentityDisk::open(&path, signature).unwrap().index_config(), custom);
+
+        // New construction parameters rebuild and store the graph.
+        let rebuilt = IndexConfig { ef_construction: 100, ..custom };
+        let disk = IdentityDisk::open_with_config(&path, signature, Some(rebuilt)).unwrap();
+        let model_signature = disk.model_signature().clone();
+        let (checksum, _) = graph::indices_checksum(&disk.conn, &model_signature, &rebuilt).unwrap();
+        assert_eq!(stored_graph_checksum(&disk, &model_signature), Some(checksum));
+        let results = disk.search_with_options(QueryVector::F32(&query), 1, &options).unwrap();
+        assert_eq!(results[0].chunk.content, "configured chunk 7");
+        drop(disk);
+        assert_eq!(*IdentityDisk::open(&path, signature).unwrap().index_config(), rebuilt);
+
+        // hnsw_rs cannot store graphs with fewer layers, so they are rebuilt instead.
+        let shallow = IndexConfig { max_layers: 4, ..custom };
+        let disk = IdentityDisk::open_with_config(&path, signature, Some(shallow)).unwrap();
+        let (checksum, _) = graph::indices_checksum(&disk.conn, &model_signature, &shallow).unwrap();
+        disk.persist_index().unwrap();
+        assert_ne!(stored_graph_checksum(&disk, &model_signature), Some(checksum));
+        let results = disk.search_with_options(QueryVector::F32(&query), 1, &options).unwrap();
+        assert_eq!(results[0].chunk.content, "configured chunk 7");
+    }
+
+    #[test]
+    fn manifest_values_round_trip_and_library_keys_are_protected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        let mut disk = IdentityDisk::create(&path, "x/m-4_fp32_l2").unwrap();
+        let owner = serde_json::json!({ "name": "Ada", "teams": ["search", 2] });
+        disk.set_manifest_value("owner", owner.clone()).unwrap();
+        disk.set_manifest_value("license", serde_json::json!("MIT")).unwrap();
+        disk.set_manifest_value("license", serde_json::json!("Apache-2.0")).unwrap();
+        drop(disk);
+
+        let mut disk = IdentityDisk::open(&path, "x/m-4_fp32").unwrap();
+        let manifest = disk.get_manifest().unwrap();
+        assert_eq!(manifest["owner"], owner);
+        assert_eq!(manifest["license"], "Apache-2.0");
+        // Library keys read back as their plain values.
+        assert_eq!(manifest["model_signature"], "x/m-4_fp32_l2");
+        assert_eq!(manifest["metric"], "l2");
+        assert_eq!(manifest["spec_version"], disk.get_spec_version().unwrap());
+
+        let reserved = ["spec_version", "model_signature", "metric", "index_config", "migration_history"];
+        for key in reserved.into_iter().chain(["int8_calibration:x/m-4_int8", "hashing_idf:x", "reembed:x"]) {
+            assert!(IdentityDisk::is_reserved_manifest_key(key), "{key}");
+            let set = disk.set_manifest_value(key, serde_json::json!("changed"));
+            assert!(matches!(set, Err(DiskError::ReservedManifestKey(_))), "{key}");
+            assert!(matches!(disk.remove_manifest_value(key), Err(DiskError::ReservedManifestKey(_))), "{key}");
+        }
+        assert_eq!(disk.get_manifest().unwrap()["metric"], "l2");
+        // Only whole keys and prefixes are reserved.
+        assert!(!IdentityDisk::is_reserved_manifest_key("metric_notes"));
+        assert!(!IdentityDisk::is_reserved_manifest_key("my_reembed:x"));
+
+        disk.remove_manifest_value("owner").unwrap();
+        assert!(matches!(disk.remove_manifest_value("owner"), Err(DiskError::NotFound(_))));
+        assert!(!disk.get_manifest().unwrap().contains_key("owner"));
+    }
+
+    /// Cuts `content` into paragraphs and stores them as the document `uri`,
+    /// with the metadata the CLI records.
+    fn ingest(disk: &mut IdentityDisk, embedder: &HashingEmbedder, uri: &str, content: &str) -> String {
+        use crate::chunking::{Chunker, ParagraphChunker};
+        let chunks = ParagraphChunker.chunk(content);
+        let texts: Vec<&str> = chunks.iter().map(|chunk| chunk.text).collect();
+        let embeddings = embedder.embed(&texts).unwrap();
+        let new_chunks: Vec<NewChunk> = chunks
+            .iter()
+            .zip(&embeddings)
+            .map(|(chunk, embedding)| NewChunk {
+                content: chunk.text,
+                embedding: QueryVector::F32(embedding),
+                metadata: Some(serde_json::json!({ "byte_start": chunk.start, "byte_end": chunk.end })),
+            })
+            .collect();
+        disk.add_document(uri, Some("Notes"), content, &new_chunks).unwrap()
+    }
+
+    #[test]
+    fn reingesting_a_document_replaces_its_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        let embedder = HashingEmbedder::new(32).unwrap();
+        let mut disk = IdentityDisk::create(&path, "local/hashing-32_fp32").unwrap();
+        let loose = disk.add_chunk("a chunk outside any document", QueryVector::F32(&[0.5; 32]), None).unwrap();
+
+        let first = "Tuesday standup notes.\n\nThe cache warmer is flaky.\n\nRelease moved to Friday.";
+        let notes = ingest(&mut disk, &embedder, "notes.md", first);
+        let document = disk.get_document(&notes).unwrap();
+        assert_eq!(document.uri, "notes.md");
+        assert_eq!(document.title.as_deref(), Some("Notes"));
+        assert_eq!(document.chunk_count, 3);
+        assert_eq!(document.content_hash, blake3::hash(first.as_bytes()).to_hex().as_str());
+        let chunks = disk.get_document_chunks(&notes).unwrap();
+        let texts: Vec<&str> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert_eq!(texts, ["Tuesday standup notes.", "The cache warmer is flaky.", "Release moved to Friday."]);
+        for chunk in &chunks {
+            let range = |key: &str| chunk.metadata[key].as_u64().unwrap() as usize;
+            assert_eq!(&first[range("byte_start")..range("byte_end")], chunk.content);
+        }
+        let other = ingest(&mut disk, &embedder, "a/other.md", "Unrelated text about gardening.");
+
+        // The same URI keeps its document id, and only the new chunks remain.
+        let second = "Wednesday standup notes.\n\nThe cache warmer is fixed.";
+        assert_eq!(ingest(&mut disk, &embedder, "notes.md", second), notes);
+        let document = disk.get_document(&notes).unwrap();
+        assert_eq!(document.chunk_count, 2);
+        assert_eq!(document.content_hash, blake3::hash(second.as_bytes()).to_hex().as_str());
+        let chunks = disk.get_document_chunks(&notes).unwrap();
+        let texts: Vec<&str> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert_eq!(texts, ["Wednesday standup notes.", "The cache warmer is fixed."]);
+        assert_eq!(disk.get_chunks().unwrap().len(), 4);
+        assert!(disk.keyword_search("Friday", 5).unwrap().is_empty());
+        let uris: Vec<String> = disk.list_documents().unwrap().into_iter().map(|d| d.uri).collect();
+        assert_eq!(uris, ["a/other.md", "notes.md"]);
+
+        // Replaced chunks are gone from the graph, also once it is rebuilt.
+        let check = |disk: &IdentityDisk| {
+            let query = embedder.embed(&["Release moved to Friday."]).unwrap().remove(0);
+            for mode in [SearchMode::Exact, SearchMode::Approximate] {
+                let options = SearchOptions::default().with_mode(mode);
+                let results = disk.search_with_options(QueryVector::F32(&query), 10, &options).unwrap();
+                assert!(results.iter().all(|r| r.chunk.content != "Release moved to Friday."), "{mode:?}");
+                assert!(results.iter().all(|r| !r.chunk.content.contains("flaky")), "{mode:?}");
+            }
+        };
+        check(&disk);
+        drop(disk);
+        let mut disk = IdentityDisk::open(&path, "local/hashing-32_fp32").unwrap();
+        check(&disk);
+
+        assert_eq!(disk.delete_document(&notes).unwrap(), 2);
+        assert!(matches!(disk.get_document(&notes), Err(DiskError::NotFound(_))));
+        assert!(matches!(disk.delete_document(&notes), Err(DiskError::NotFound(_))));
+        let remaining: HashSet<String> = disk.get_chunks().unwrap().into_iter().map(|c| c.chunk_id).collect();
+        assert_eq!(remaining.len(), 2);
+        assert!(remaining.contains(&loose));
+        assert_eq!(disk.get_document(&other).unwrap().chunk_count, 1);
+    }
+
+    #[test]
+    fn hashing_idf_weights_are_checked_stored_and_kept_per_signature() {
+        let corpus = ["common words here", "common words there", "rare zebra"];
+        let (dir, mut disk, chunk_ids) = disk_with(&corpus, "local/hashing-16_fp32");
+        let path = dir.path().join("disk.idz");
+
+        // Weights are checked against the signature the way `with_idf` checks them.
+        let idf = HashingEmbedder::new(32).unwrap().fit_idf(&corpus);
+        let error = disk.set_hashing_idf(&idf).unwrap_err();
+        assert!(matches!(error, DiskError::DimensionMismatch { expected: 16, got: 32 }), "{error}");
+        assert_eq!(disk.hashing_embedder().unwrap().idf(), None);
+
+        let idf = HashingEmbedder::new(16).unwrap().fit_idf(&corpus);
+        disk.set_hashing_idf(&idf).unwrap();
+        let embedder = disk.hashing_embedder().unwrap();
+        assert_eq!(embedder.idf(), Some(&idf));
+        // A chunk re-embedded with the weights is found by queries embedded with them.
+        let query = embedder.embed(&["rare zebra"]).unwrap().remove(0);
+        disk.update_chunk(&chunk_ids[2], "rare zebra", QueryVector::F32(&query), None).unwrap();
+        let results = disk.search(QueryVector::F32(&query), 1).unwrap();
+        assert_eq!(results[0].chunk.chunk_id, chunk_ids[2]);
+
+        // The weights belong to the signature they were stored for.
+        disk.add_embedding(&chunk_ids[0], "local/hashing-16_fp16", QueryVector::F32(&query)).unwrap();
+        disk.switch_model_signature("local/hashing-16_fp16").unwrap();
+        assert_eq!(disk.hashing_embedder().unwrap().idf(), None);
+        drop(disk);
+        let disk = IdentityDisk::open(&path, "local/hashing-16_fp32").unwrap();
+        assert_eq!(disk.hashing_embedder().unwrap().idf(), Some(&idf));
+
+        // Disks of other models have no hashing embedder.
+        let other = IdentityDisk::create(dir.path().join("other.idz"), "x/m-16_fp32").unwrap();
+        assert!(matches!(other.hashing_embedder(), Err(DiskError::Embedding(_))));
+    }
+
+    #[test]
+    fn signatures_are_listed_switched_made_default_and_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        let (first, second) = ("x/m-4_fp32", "y/n-2_fp32");
+        let mut disk = IdentityDisk::create(&path, first).unwrap();
+        let vectors = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0]];
+        let chunk_ids: Vec<String> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, vector)| disk.add_chunk(&format!("chunk {}", i), QueryVector::F32(vector), None).unwrap())
+            .collect();
+        // Only the first two chunks get an embedding from the second model.
+        disk.add_embedding(&chunk_ids[0], second, QueryVector::F32(&[0.0, 1.0])).unwrap();
+        disk.add_embedding(&chunk_ids[1], second, QueryVector::F32(&[1.0, 0.0])).unwrap();
+
+        let listed = |disk: &IdentityDisk| -> Vec<(String, usize, bool, bool)> {
+            let signatures = disk.list_model_signatures().unwrap();
+            signatures.into_iter().map(|s| (s.model_signature, s.vectors, s.is_default, s.is_active)).collect()
+        };
+        assert_eq!(listed(&disk), [(first.into(), 3, true, true), (second.into(), 2, false, false)]);
+        let coverage: Vec<f32> = disk.list_model_signatures().unwrap().iter().map(|s| s.coverage).collect();
+        assert_eq!(coverage, [1.0, 2.0 / 3.0]);
+
+        // Switching searches the other model's vectors, which rank the chunks differently.
+        disk.switch_model_signature(second).unwrap();
+        assert_eq!(disk.model_signature().to_string(), second);
+        let results = disk.search(QueryVector::F32(&[1.0, 0.0]), 5).unwrap();
+        assert_eq!(contents(&results), ["chunk 1", "chunk 0"]);
+        assert!(disk.search(QueryVector::F32(&vectors[0]), 5).is_err());
+        assert_eq!(listed(&disk), [(first.into(), 3, true, false), (second.into(), 2, false, true)]);
+
+        // Neither the active nor the default signature can be dropped.
+        assert!(matches!(disk.drop_model_signature(second), Err(DiskError::InvalidData(_))));
+        assert!(matches!(disk.drop_model_signature(first), Err(DiskError::InvalidData(_))));
+        disk.set_default_model_signature(second).unwrap();
+        assert_eq!(disk.default_model_signature().unwrap().as_deref(), Some(second));
+        disk.persist_index().unwrap();
+        drop(disk);
+
+        let mut disk = IdentityDisk::open_default(&path).unwrap();
+        assert_eq!(disk.model_signature().to_string(), second);
+        let model_signature: ModelSignature = second.parse().unwrap();
+        assert!(stored_graph_checksum(&disk, &model_signature).is_some());
+        disk.switch_model_signature(first).unwrap();
+        disk.set_default_model_signature(first).unwrap();
+        assert_eq!(disk.drop_model_signature(second).unwrap(), 2);
+        assert_eq!(stored_under(&disk, second), 0);
+        assert_eq!(stored_graph_checksum(&disk, &model_signature), None);
+        assert_eq!(listed(&disk), [(first.into(), 3, true, true)]);
+        let results = disk.search(QueryVector::F32(&vectors[2]), 1).unwrap();
+        assert_eq!(results[0].chunk.content, "chunk 2");
+    }
+
+    /// Hashing embeddings that fail once `fail_at` texts have been embedded.
+    struct FlakyEmbedder {
+        inner: HashingEmbedder,
+        fail_at: usize,
+        embedded: AtomicUsize,
+    }
+
+    impl FlakyEmbedder {
+        fn new(dim: usize, fail_at: usize) -> Self {
+            Self { inner: HashingEmbedder::new(dim).unwrap(), fail_at, embedded: AtomicUsize::new(0) }
+        }
+    }
+
+    impl Embedder for FlakyEmbedder {
+        fn model(&self) -> &str {
+            self.inner.model()
+        }
+
+        fn dim(&self) -> usize {
+            self.inner.dim()
+        }
+
+        fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, DiskError> {
+            if self.embedded.fetch_add(texts.len(), Ordering::SeqCst) + texts.len() > self.fail_at {
+                return Err(DiskError::Embedding("connection reset".into()));
+            }
+            self.inner.embed(texts)
+        }
+    }
+
+    #[test]
+    fn reembed_resumes_after_an_interrupted_run() {
+        let source = "local/hashing-8_fp32";
+        let target = "local/hashing-8_fp16";
+        let texts: Vec<String> = (0..10).map(|i| format!("chunk number {}", i)).collect();
+        let (dir, mut disk, _) = disk_with(&texts, source);
+        let path = dir.path().join("disk.idz");
+        let embedder = disk.hashing_embedder().unwrap();
+
+        // The third batch fails, after two batches have been committed.
+        let flaky = FlakyEmbedder::new(8, 7);
+        assert!(matches!(disk.reembed(target, &flaky, 3), Err(DiskError::Embedding(_))));
+        assert_eq!(stored_under(&disk, target), 6);
+        drop(disk);
+
+        let mut disk = IdentityDisk::open(&path, source).unwrap();
+        let counting = FlakyEmbedder::new(8, usize::MAX);
+        let report = disk.reembed(target, &counting, 3).unwrap();
+        assert!(report.resumed);
+        assert_eq!(report.embedded, 4);
+        assert_eq!(counting.embedded.load(Ordering::SeqCst), 4);
+        assert_eq!(report.total_chunks, 10);
+        assert!(report.is_complete(), "missing {:?}", report.missing);
+        assert_eq!(stored_under(&disk, target), 10);
+
+        // A finished run leaves no progress behind, so the next one starts over.
+        let report = disk.reembed(target, &counting, 3).unwrap();
+        assert!(!report.resumed);
+        assert_eq!(report.embedded, 0);
+
+        // The vectors match what an uninterrupted run would have stored.
+        disk.switch_model_signature(target).unwrap();
+        let query = embedder.embed(&["chunk number 7"]).unwrap().remove(0);
+        let results = disk.search(QueryVector::F32(&query), 1).unwrap();
+        assert_eq!(results[0].chunk.content, "chunk number 7");
+    }
+
+    #[test]
+    fn reembed_can_replace_the_default_signature() {
+        let source = "local/hashing-8_fp32";
+        let target = "local/hashing-8_fp16";
+        let texts: Vec<String> = (0..5).map(|i| format!("chunk number {}", i)).collect();
+        let (dir, mut disk, _) = disk_with(&texts, source);
+        let path = dir.path().join("disk.idz");
+        let embedder = disk.hashing_embedder().unwrap();
+        let options = ReembedOptions::new(2).with_drop_old();
+
+        // The active default would be dropped, so nothing is embedded.
+        let result = disk.reembed_with_options(target, &embedder, &options);
+        assert!(matches!(result, Err(DiskError::InvalidData(_))));
+        assert_eq!(stored_under(&disk, target), 0);
+
+        disk.switch_model_signature(target).unwrap();
+        let report = disk.reembed_with_options(target, &embedder, &options).unwrap();
+        assert!(report.is_complete());
+        assert_eq!(report.dropped_signature.as_deref(), Some(source));
+        assert_eq!(report.dropped, 5);
+        assert_eq!(stored_under(&disk, source), 0);
+        assert_eq!(disk.default_model_signature().unwrap().as_deref(), Some(target));
+
+        // Once the target is the default, there is nothing left to drop.
+        let report = disk.reembed_with_options(target, &embedder, &options).unwrap();
+        assert_eq!(report.dropped_signature, None);
+        drop(disk);
+        let disk = IdentityDisk::open_default(&path).unwrap();
+        assert_eq!(disk.model_signature().to_string(), target);
+        assert_eq!(disk.list_model_signatures().unwrap().len(), 1);
     }
 }
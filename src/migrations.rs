@@ -0,0 +1,443 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection, DatabaseName, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+
+use crate::documents;
+use crate::errors::DiskError;
+use crate::fts;
+use crate::graph;
+use crate::models::{IndexConfig, ModelSignature};
+
+/// The spec version written by `CREATE_DB_SQL`, before any migration.
+pub(crate) const BASE_SPEC_VERSION: &str = "1.0";
+
+/// The spec version this library writes and fully understands.
+pub(crate) const SPEC_VERSION: &str = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// One step in upgrading a disk's schema.
+struct Migration {
+    /// The spec version a disk has once this migration is applied.
+    version: &'static str,
+    description: &'static str,
+    /// Returns a note for the history when the migration had to discard data.
+    apply: fn(&Transaction) -> Result<Option<String>, DiskError>,
+}
+
+/// Every migration, oldest first.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: "1.1",
+        description: "Add hnsw_graphs table for persisted HNSW graphs",
+        apply: |tx| {
+            tx.execute_batch(graph::CREATE_GRAPH_TABLE_SQL)?;
+            Ok(None)
+        },
+    },
+    Migration {
+        version: "1.2",
+        description: "Add chunks_fts keyword index and back-fill it",
+        apply: |tx| fts::ensure_fts(tx).map(|_| None),
+    },
+    Migration {
+        version: "1.3",
+        description: "Record metric and index_config in the manifest",
+        apply: |tx| {
+            tx.execute(
+                "INSERT OR IGNORE INTO manifest (key, value) VALUES ('metric', 'cosine')",
+                [],
+            )?;
+            tx.execute(
+                "INSERT OR IGNORE INTO manifest (key, value) VALUES ('index_config', ?1)",
+                params![serde_json::to_string(&IndexConfig::default())?],
+            )?;
+            Ok(None)
+        },
+    },
+    Migration {
+        version: "1.4",
+        description: "Add documents table and link chunks to their document",
+        apply: |tx| documents::ensure_documents(tx).map(|_| None),
+    },
+    Migration {
+        version: "1.5",
+        description: "Rewrite legacy model signatures in canonical form",
+        apply: canonicalize_signatures,
+    },
+    Migration {
+        version: "1.6",
+        description: "Record the only stored model signature as the default",
+        apply: |tx| {
+            tx.execute(
+                "INSERT OR IGNORE INTO manifest (key, value)
+                 SELECT 'model_signature', MIN(model_signature) FROM indices
+                 HAVING COUNT(DISTINCT model_signature) = 1",
+                [],
+            )?;
+            Ok(None)
+        },
+    },
+];
+
+/// Rewrites stored model signatures in canonical form so that lookups find them.
+///
+/// This covers the form without a `_{dtype}` suffix, e.g. `openai/ada-002-1536`,
+/// and the free-form fp32 names written before signatures were parsed, e.g.
+/// `openai/text-embedding-ada-002_fp32`, whose dimension is read from the length
+/// of their stored vectors. Rows that already have a canonical duplicate are
+/// dropped, and how many were dropped per signature is returned as the note;
+/// signatures that fit neither form are left as they are. Stored graphs are
+/// renamed along with their rows.
+fn canonicalize_signatures(tx: &Transaction) -> Result<Option<String>, DiskError> {
+    let stored: Vec<(String, usize)> = tx
+        .prepare("SELECT model_signature, MAX(length(data)) FROM indices GROUP BY model_signature")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+    let mut renamed = Vec::new();
+    for (legacy, bytes) in stored {
+        let signature = match legacy.parse::<ModelSignature>() {
+            Ok(signature) => signature,
+            Err(_) => match ModelSignature::from_legacy(&legacy, bytes / 4) {
+                Some(signature) => signature,
+                None => continue,
+            },
+        };
+        if signature.to_string() != legacy {
+            renamed.push((legacy, signature));
+        }
+    }
+    let mut dropped = Vec::new();
+    for (legacy, signature) in &renamed {
+        // Stored graphs stay valid: their checksum covers the rows, not the key.
+        for table in ["indices", "hnsw_graphs"] {
+            tx.execute(
+                &format!("UPDATE OR IGNORE {table} SET model_signature = ?1 WHERE model_signature = ?2"),
+                params![signature, legacy],
+            )?;
+            let deleted = tx.execute(&format!("DELETE FROM {table} WHERE model_signature = ?1"), params![legacy])?;
+            // Graphs are rebuilt from the rows, so only dropped vectors are lost.
+            if table == "indices" && deleted > 0 {
+                dropped.push(format!("{} under '{}' already stored as '{}'", deleted, legacy, signature));
+            }
+        }
+    }
+
+    let manifest_signature: Option<String> = tx
+        .query_row("SELECT value FROM manifest WHERE key = 'model_signature'", [], |row| row.get(0))
+        .optional()?;
+    if let Some(manifest_signature) = manifest_signature {
+        let signature = match renamed.iter().find(|(legacy, _)| *legacy == manifest_signature) {
+            Some((_, signature)) => Some(signature.clone()),
+            None => manifest_signature.parse::<ModelSignature>().ok(),
+        };
+        if let Some(signature) = signature {
+            tx.execute(
+                "UPDATE manifest SET value = ?1 WHERE key = 'model_signature'",
+                params![signature],
+            )?;
+        }
+    }
+    Ok((!dropped.is_empty()).then(|| format!("Dropped duplicate vectors: {}", dropped.join("; "))))
+}
+
+/// An applied migration, as recorded under the `migration_history` manifest key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationRecord {
+    pub from: String,
+    pub to: String,
+    pub description: String,
+    /// UTC timestamp in ISO 8601 format.
+    pub applied_at: String,
+    /// What the migration discarded, if anything.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Parses a `major.minor` spec version.
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let (major, minor) = version.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Creates the schema of [`SPEC_VERSION`] on a disk just created at
+/// [`BASE_SPEC_VERSION`]. Nothing is recorded under `migration_history`, as the
+/// disk never existed at an older version.
+pub(crate) fn initialize(conn: &mut Connection) -> Result<(), DiskError> {
+    let tx = conn.transaction()?;
+    for migration in MIGRATIONS {
+        (migration.apply)(&tx)?;
+    }
+    tx.execute(
+        "UPDATE manifest SET value = ?1 WHERE key = 'spec_version'",
+        params![SPEC_VERSION],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Brings the disk on `conn` up to [`SPEC_VERSION`].
+///
+/// Disks from a newer major version are rejected with `DiskError::SpecVersion`;
+/// newer minor versions only add to the schema and are opened as they are.
+/// Older disks are migrated in a single transaction after copying the database
+/// to `{backup_path}.v{old_version}.bak`, if `backup_path` is given. Every applied
+/// migration is appended to the `migration_history` manifest key.
+pub(crate) fn upgrade(conn: &mut Connection, backup_path: Option<&Path>) -> Result<(), DiskError> {
+    let found: String = conn
+        .query_row("SELECT value FROM manifest WHERE key = 'spec_version'", [], |row| row.get(0))
+        .optional()?
+        .unwrap_or_else(|| BASE_SPEC_VERSION.to_string());
+    let spec_error = || DiskError::SpecVersion {
+        found: found.clone(),
+        supported: SPEC_VERSION.to_string(),
+    };
+    let found_version = parse_version(&found).ok_or_else(spec_error)?;
+    let supported = parse_version(SPEC_VERSION).expect("SPEC_VERSION is well-formed");
+    if found_version.0 > supported.0 {
+        return Err(spec_error());
+    }
+
+    let pending: Vec<&Migration> = MIGRATIONS
+        .iter()
+        .filter(|m| parse_version(m.version).expect("migration versions are well-formed") > found_version)
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(path) = backup_path {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(format!(".v{}.bak", found));
+        conn.backup(DatabaseName::Main, &backup, None)?;
+    }
+
+    let tx = conn.transaction()?;
+    let mut history: Vec<MigrationRecord> = tx
+        .query_row("SELECT value FROM manifest WHERE key = 'migration_history'", [], |row| {
+            row.get::<_, String>(0)
+        })
+        .optional()?
+        .map(|json| serde_json::from_str(&json))
+        .transpose()?
+        .unwrap_or_default();
+
+    let mut from = found.clone();
+    for migration in pending {
+        let note = (migration.apply)(&tx)?;
+        history.push(MigrationRecord {
+            from: from.clone(),
+            to: migration.version.to_string(),
+            description: migration.description.to_string(),
+            applied_at: tx.query_row("SELECT strftime('%Y-%m-%dT%H:%M:%SZ', 'now')", [], |row| row.get(0))?,
+            note,
+        });
+        from = migration.version.to_string();
+    }
+
+    tx.execute(
+        "INSERT OR REPLACE INTO manifest (key, value) VALUES ('spec_version', ?1)",
+        params![SPEC_VERSION],
+    )?;
+    tx.execute(
+        "INSERT OR REPLACE INTO manifest (key, value) VALUES ('migration_history', ?1)",
+        params![serde_json::to_string(&history)?],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::QueryVector;
+    use crate::{IdentityDisk, CREATE_DB_SQL, UPSERT_INDEX_SQL};
+
+    /// Writes a disk the way spec version 1.0 did: the bare schema, and fp32
+    /// vectors stored under a free-form `model_signature`.
+    fn write_v1_0_disk(path: &Path, model_signature: &str, chunks: &[(&str, &[f32])]) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(&CREATE_DB_SQL.replace("?1", &format!("'{}'", BASE_SPEC_VERSION))).unwrap();
+        for (i, (content, embedding)) in chunks.iter().enumerate() {
+            let chunk_id = format!("chunk-{}", i);
+            conn.execute(
+                "INSERT INTO chunks (chunk_id, content, metadata) VALUES (?1, ?2, '{}')",
+                params![&chunk_id, content],
+            )
+            .unwrap();
+            let blob: Vec<u8> = embedding.iter().flat_map(|x| x.to_le_bytes()).collect();
+            conn.execute(UPSERT_INDEX_SQL, params![&chunk_id, "vector_embedding", model_signature, blob]).unwrap();
+        }
+        conn
+    }
+
+    fn manifest_value(conn: &Connection, key: &str) -> Option<String> {
+        conn.query_row("SELECT value FROM manifest WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()
+            .unwrap()
+    }
+
+    fn rows_under(conn: &Connection, table: &str, model_signature: &str) -> usize {
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM {table} WHERE model_signature = ?1"),
+            params![model_signature],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn old_disks_are_backed_up_then_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        write_v1_0_disk(&path, "openai/ada-002-3", &[("north", &[1.0, 0.0, 0.0]), ("east", &[0.0, 1.0, 0.0])]);
+
+        let disk = IdentityDisk::open(&path, "openai/ada-002-3").unwrap();
+        let results = disk.search(QueryVector::F32(&[0.0, 1.0, 0.1]), 1).unwrap();
+        assert_eq!(results[0].chunk.content, "east");
+        drop(disk);
+
+        // The backup is the disk as it was before the migration.
+        let backup = Connection::open(dir.path().join("disk.idz.v1.0.bak")).unwrap();
+        assert_eq!(manifest_value(&backup, "spec_version").as_deref(), Some(BASE_SPEC_VERSION));
+        assert_eq!(rows_under(&backup, "indices", "openai/ada-002-3"), 2);
+
+        let conn = Connection::open(&path).unwrap();
+        assert_eq!(SPEC_VERSION, "1.6");
+        assert_eq!(manifest_value(&conn, "spec_version").as_deref(), Some(SPEC_VERSION));
+        let history: Vec<MigrationRecord> =
+            serde_json::from_str(&manifest_value(&conn, "migration_history").unwrap()).unwrap();
+        let steps: Vec<(&str, &str)> = history.iter().map(|r| (r.from.as_str(), r.to.as_str())).collect();
+        assert_eq!(steps, [("1.0", "1.1"), ("1.1", "1.2"), ("1.2", "1.3"), ("1.3", "1.4"), ("1.4", "1.5"), ("1.5", "1.6")]);
+
+        // Migration 1.5 renamed the rows and 1.6 recorded them as the default.
+        assert_eq!(rows_under(&conn, "indices", "openai/ada-002-3"), 0);
+        assert_eq!(rows_under(&conn, "indices", "openai/ada-002-3_fp32"), 2);
+        assert_eq!(manifest_value(&conn, "model_signature").as_deref(), Some("openai/ada-002-3_fp32"));
+        assert_eq!(manifest_value(&conn, "metric").as_deref(), Some("cosine"));
+    }
+
+    #[test]
+    fn disks_with_pre_parsing_signatures_open_under_their_old_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        let legacy = "openai/text-embedding-ada-002_fp32";
+        let canonical = "openai/text-embedding-ada-002-3_fp32";
+        write_v1_0_disk(&path, legacy, &[("north", &[1.0, 0.0, 0.0]), ("east", &[0.0, 1.0, 0.0])]);
+
+        let disk = IdentityDisk::open(&path, legacy).unwrap();
+        assert_eq!(disk.model_signature().to_string(), canonical);
+        assert_eq!(rows_under(&disk.conn, "indices", canonical), 2);
+        assert_eq!(rows_under(&disk.conn, "indices", legacy), 0);
+        let results = disk.search(QueryVector::F32(&[0.1, 0.9, 0.0]), 1).unwrap();
+        assert_eq!(results[0].chunk.content, "east");
+        drop(disk);
+
+        // The canonical name works too, as does the recorded default.
+        IdentityDisk::open(&path, canonical).unwrap();
+        let disk = IdentityDisk::open_default(&path).unwrap();
+        assert_eq!(disk.model_signature().to_string(), canonical);
+    }
+
+    #[test]
+    fn stored_graphs_are_renamed_with_their_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut conn = write_v1_0_disk(&dir.path().join("disk.idz"), "x/m-2", &[("a", &[1.0, 0.0])]);
+        conn.execute(
+            "INSERT INTO indices (chunk_id, index_type, model_signature, data)
+             SELECT chunk_id, index_type, 'y/m-2', data FROM indices",
+            [],
+        )
+        .unwrap();
+        // A 1.1 disk with graphs stored under the legacy names and, for `y`, a
+        // graph already stored under the canonical name too.
+        conn.execute_batch(graph::CREATE_GRAPH_TABLE_SQL).unwrap();
+        conn.execute("UPDATE manifest SET value = '1.1' WHERE key = 'spec_version'", []).unwrap();
+        let insert_graph = |model_signature: &str, checksum: &str| {
+            conn.execute(
+                "INSERT INTO hnsw_graphs (model_signature, checksum, id_map, graph, data) VALUES (?1, ?2, '[]', x'', x'')",
+                params![model_signature, checksum],
+            )
+            .unwrap();
+        };
+        insert_graph("x/m-2", "legacy");
+        insert_graph("y/m-2", "legacy");
+        insert_graph("y/m-2_fp32", "canonical");
+
+        upgrade(&mut conn, None).unwrap();
+        let graphs: Vec<(String, String)> = conn
+            .prepare("SELECT model_signature, checksum FROM hnsw_graphs ORDER BY model_signature")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let graphs: Vec<(&str, &str)> = graphs.iter().map(|(s, c)| (s.as_str(), c.as_str())).collect();
+        assert_eq!(graphs, [("x/m-2_fp32", "legacy"), ("y/m-2_fp32", "canonical")]);
+    }
+
+    #[test]
+    fn legacy_rows_shadowed_by_canonical_ones_are_dropped_and_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut conn = write_v1_0_disk(&dir.path().join("disk.idz"), "x/m-2", &[("a", &[1.0, 0.0]), ("b", &[0.0, 1.0])]);
+        // The first chunk is also stored under the canonical name, with another vector.
+        let canonical: Vec<u8> = [0.5f32, 0.5].iter().flat_map(|x| x.to_le_bytes()).collect();
+        conn.execute(UPSERT_INDEX_SQL, params!["chunk-0", "vector_embedding", "x/m-2_fp32", canonical]).unwrap();
+
+        upgrade(&mut conn, None).unwrap();
+        assert_eq!(rows_under(&conn, "indices", "x/m-2"), 0);
+        assert_eq!(rows_under(&conn, "indices", "x/m-2_fp32"), 2);
+        let kept: Vec<u8> = conn
+            .query_row(
+                "SELECT data FROM indices WHERE chunk_id = 'chunk-0' AND model_signature = 'x/m-2_fp32'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(kept, canonical);
+
+        let history: Vec<MigrationRecord> =
+            serde_json::from_str(&manifest_value(&conn, "migration_history").unwrap()).unwrap();
+        let notes: Vec<(&str, Option<&str>)> = history.iter().map(|r| (r.to.as_str(), r.note.as_deref())).collect();
+        assert_eq!(
+            notes,
+            [
+                ("1.1", None),
+                ("1.2", None),
+                ("1.3", None),
+                ("1.4", None),
+                ("1.5", Some("Dropped duplicate vectors: 1 under 'x/m-2' already stored as 'x/m-2_fp32'")),
+                ("1.6", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn newer_major_versions_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        drop(IdentityDisk::create(&path, "x/m-2_fp32").unwrap());
+        let conn = Connection::open(&path).unwrap();
+
+        // Newer minor versions only add to the schema and open as they are.
+        conn.execute("UPDATE manifest SET value = '1.99' WHERE key = 'spec_version'", []).unwrap();
+        IdentityDisk::open(&path, "x/m-2_fp32").unwrap();
+
+        conn.execute("UPDATE manifest SET value = '2.0' WHERE key = 'spec_version'", []).unwrap();
+        let error = IdentityDisk::open(&path, "x/m-2_fp32").err().unwrap();
+        assert!(matches!(&error, DiskError::SpecVersion { found, .. } if found == "2.0"), "{error}");
+        assert_eq!(manifest_value(&conn, "spec_version").as_deref(), Some("2.0"));
+    }
+
+    #[test]
+    fn new_disks_start_at_the_current_version_without_a_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.idz");
+        drop(IdentityDisk::create(&path, "x/m-2_fp32").unwrap());
+        IdentityDisk::open(&path, "x/m-2_fp32").unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        assert_eq!(manifest_value(&conn, "spec_version").as_deref(), Some(SPEC_VERSION));
+        assert_eq!(manifest_value(&conn, "migration_history"), None);
+        assert!(!dir.path().join(format!("disk.idz.v{}.bak", BASE_SPEC_VERSION)).exists());
+    }
+}
@@ -1,7 +1,13 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rusqlite::types::{ToSql, ToSqlOutput};
 use rusqlite::{Row, Result as RusqliteResult};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as Json;
 
+use crate::errors::DiskError;
+
 /// Represents a chunk of text and its associated metadata.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chunk {
@@ -29,12 +35,220 @@ impl<'stmt> TryFrom<&Row<'stmt>> for Chunk {
     }
 }
 
+/// A source document whose chunks are stored on the disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Document {
+    pub document_id: String,
+    /// Identifies the source; ingesting the same URI again replaces its chunks.
+    pub uri: String,
+    pub title: Option<String>,
+    /// blake3 hex digest of the content the chunks were cut from.
+    pub content_hash: String,
+    /// UTC timestamp in ISO 8601 format.
+    pub ingested_at: String,
+    pub chunk_count: usize,
+}
+
+impl<'stmt> TryFrom<&Row<'stmt>> for Document {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row<'stmt>) -> RusqliteResult<Self> {
+        Ok(Document {
+            document_id: row.get(0)?,
+            uri: row.get(1)?,
+            title: row.get(2)?,
+            content_hash: row.get(3)?,
+            ingested_at: row.get(4)?,
+            chunk_count: row.get::<_, i64>(5)? as usize,
+        })
+    }
+}
+
 /// Represents a query vector, which can be of different underlying types.
 #[derive(Debug, Clone)]
 pub enum QueryVector<'a> {
     F32(&'a [f32]),
-    // TODO: Add variants for I8, F16 etc.
-    // I8(&'a [i8]),
+    F16(&'a [half::f16]),
+    /// Codes already quantized with the disk's int8 calibration.
+    I8(&'a [i8]),
+    /// Sign bits packed LSB-first, `ceil(dim / 8)` bytes.
+    Bin(&'a [u8]),
+}
+
+/// A chunk to be added to a disk as part of a batch.
+#[derive(Debug, Clone)]
+pub struct NewChunk<'a> {
+    pub content: &'a str,
+    pub embedding: QueryVector<'a>,
+    pub metadata: Option<Json>,
+}
+
+/// An owned [`QueryVector`], for APIs that need their arguments to outlive
+/// the call, such as [`AsyncIdentityDisk`](crate::async_disk::AsyncIdentityDisk).
+#[derive(Debug, Clone)]
+pub enum OwnedQueryVector {
+    F32(Vec<f32>),
+    F16(Vec<half::f16>),
+    I8(Vec<i8>),
+    Bin(Vec<u8>),
+}
+
+impl OwnedQueryVector {
+    pub fn as_query(&self) -> QueryVector<'_> {
+        match self {
+            OwnedQueryVector::F32(v) => QueryVector::F32(v),
+            OwnedQueryVector::F16(v) => QueryVector::F16(v),
+            OwnedQueryVector::I8(v) => QueryVector::I8(v),
+            OwnedQueryVector::Bin(v) => QueryVector::Bin(v),
+        }
+    }
+}
+
+impl From<QueryVector<'_>> for OwnedQueryVector {
+    fn from(query: QueryVector<'_>) -> Self {
+        match query {
+            QueryVector::F32(v) => OwnedQueryVector::F32(v.to_vec()),
+            QueryVector::F16(v) => OwnedQueryVector::F16(v.to_vec()),
+            QueryVector::I8(v) => OwnedQueryVector::I8(v.to_vec()),
+            QueryVector::Bin(v) => OwnedQueryVector::Bin(v.to_vec()),
+        }
+    }
+}
+
+/// An owned [`NewChunk`].
+#[derive(Debug, Clone)]
+pub struct OwnedNewChunk {
+    pub content: String,
+    pub embedding: OwnedQueryVector,
+    pub metadata: Option<Json>,
+}
+
+impl OwnedNewChunk {
+    pub fn as_new_chunk(&self) -> NewChunk<'_> {
+        NewChunk {
+            content: &self.content,
+            embedding: self.embedding.as_query(),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+impl From<NewChunk<'_>> for OwnedNewChunk {
+    fn from(chunk: NewChunk<'_>) -> Self {
+        Self {
+            content: chunk.content.to_string(),
+            embedding: chunk.embedding.into(),
+            metadata: chunk.metadata,
+        }
+    }
+}
+
+/// Options that narrow or tune a search.
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Only chunks whose metadata matches this filter are returned. See
+    /// [`MetadataFilter`](crate::filter::MetadataFilter) for the syntax.
+    pub filter: Option<Json>,
+    /// Whether to traverse HNSW or scan every stored vector.
+    pub mode: SearchMode,
+    /// Overrides the disk's `IndexConfig::ef_search` for this query.
+    pub ef_search: Option<usize>,
+}
+
+impl SearchOptions {
+    /// Restricts the search to chunks whose metadata matches `filter`.
+    pub fn with_filter(mut self, filter: Json) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Selects how neighbours are found.
+    pub fn with_mode(mut self, mode: SearchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Searches HNSW with a candidate list of `ef_search` for this query only.
+    pub fn with_ef_search(mut self, ef_search: usize) -> Self {
+        self.ef_search = Some(ef_search);
+        self
+    }
+}
+
+/// HNSW construction and search parameters of a disk.
+///
+/// Larger values raise recall at the cost of memory and build or query time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexConfig {
+    /// Maximum number of links per node (HNSW `M`).
+    pub max_nb_connection: usize,
+    /// Candidate list size while inserting.
+    pub ef_construction: usize,
+    /// Number of graph layers, at most 16. hnsw_rs can only store graphs with
+    /// all 16 layers, so with fewer the graph is rebuilt on every open.
+    pub max_layers: usize,
+    /// Candidate list size while searching; raised to `top_k` when smaller.
+    pub ef_search: usize,
+}
+
+impl IndexConfig {
+    /// The most layers hnsw_rs supports.
+    pub const MAX_LAYERS: usize = 16;
+
+    /// Checks that every parameter is usable by HNSW.
+    pub fn validate(&self) -> Result<(), DiskError> {
+        let invalid = |reason: &str| Err(DiskError::InvalidData(format!("Invalid index config: {}", reason)));
+        if self.max_nb_connection == 0 {
+            return invalid("max_nb_connection must be at least 1");
+        }
+        if self.ef_construction == 0 || self.ef_search == 0 {
+            return invalid("ef_construction and ef_search must be at least 1");
+        }
+        if !(1..=Self::MAX_LAYERS).contains(&self.max_layers) {
+            return invalid("max_layers must be between 1 and 16");
+        }
+        Ok(())
+    }
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            max_nb_connection: 16,
+            ef_construction: 200,
+            max_layers: 16,
+            ef_search: 100,
+        }
+    }
+}
+
+/// How a semantic search finds neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /// Exact search on disks at or below the exact-search threshold, HNSW above it.
+    /// Also exact when a metadata filter matches no more chunks than the threshold.
+    #[default]
+    Auto,
+    /// Approximate search through the HNSW graph.
+    Approximate,
+    /// Exact flat scan over every stored vector.
+    Exact,
+}
+
+/// Recall of HNSW search measured against exact search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecallReport {
+    /// Number of queries evaluated.
+    pub queries: usize,
+    pub k: usize,
+    /// Mean fraction of the exact top `k` that HNSW also returned.
+    pub recall_at_k: f32,
+    /// Lowest recall of any single query.
+    pub min_recall: f32,
+    /// Mean HNSW search latency.
+    pub hnsw_latency: std::time::Duration,
+    /// Mean exact search latency.
+    pub exact_latency: std::time::Duration,
 }
 
 /// Represents a search result, including the chunk and its distance to the query.
@@ -42,4 +256,454 @@ pub enum QueryVector<'a> {
 pub struct SearchResult {
     pub chunk: Chunk,
     pub distance: f32,
+    /// The distance function that produced `distance`.
+    pub metric: ScoreMetric,
+    /// Where a hybrid search found the chunk among the vector neighbours.
+    pub vector: Option<RankedScore>,
+    /// Where a hybrid search found the chunk among the keyword hits.
+    pub keyword: Option<RankedScore>,
+}
+
+/// The position and raw score of a result within one retriever's results.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RankedScore {
+    /// 1-based rank, best first.
+    pub rank: usize,
+    /// The distance or BM25 rank reported by that retriever.
+    pub score: f32,
+}
+
+/// How `hybrid_search` combines vector and keyword results.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fusion {
+    /// Reciprocal rank fusion: each retriever contributes `weight / (k + rank)`.
+    Rrf { k: f32 },
+    /// Each retriever's scores are min-max normalized to `[0, 1]`, best = 1,
+    /// and summed by weight.
+    WeightedScore,
+}
+
+/// Relative weights and fusion method for `hybrid_search`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HybridWeights {
+    pub vector: f32,
+    pub keyword: f32,
+    pub fusion: Fusion,
+}
+
+impl Default for HybridWeights {
+    /// Equal weights with reciprocal rank fusion at the customary `k = 60`.
+    fn default() -> Self {
+        Self {
+            vector: 1.0,
+            keyword: 1.0,
+            fusion: Fusion::Rrf { k: 60.0 },
+        }
+    }
+}
+
+/// The distance function behind a [`SearchResult`] score.
+///
+/// This is the disk's [`Metric`], except for binary indices searched without
+/// full-precision rescoring, which report raw Hamming distances, and keyword
+/// and hybrid searches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScoreMetric {
+    /// `1 - cosine similarity`.
+    Cosine,
+    /// Euclidean distance.
+    L2,
+    /// `1 - inner product`; negative when the inner product exceeds 1.
+    Dot,
+    /// Number of differing sign bits.
+    Hamming,
+    /// FTS5 BM25 rank; more negative is a better match.
+    Bm25,
+    /// Negated reciprocal rank fusion score of a hybrid search.
+    Rrf,
+    /// Negated weighted normalized score of a hybrid search.
+    WeightedScore,
+}
+
+impl ScoreMetric {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScoreMetric::Cosine => "cosine",
+            ScoreMetric::L2 => "l2",
+            ScoreMetric::Dot => "dot",
+            ScoreMetric::Hamming => "hamming",
+            ScoreMetric::Bm25 => "bm25",
+            ScoreMetric::Rrf => "rrf",
+            ScoreMetric::WeightedScore => "weighted",
+        }
+    }
+}
+
+impl From<Metric> for ScoreMetric {
+    fn from(metric: Metric) -> Self {
+        match metric {
+            Metric::Cosine => ScoreMetric::Cosine,
+            Metric::L2 => ScoreMetric::L2,
+            Metric::Dot => ScoreMetric::Dot,
+        }
+    }
+}
+
+/// The element type embeddings are stored as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dtype {
+    Fp32,
+    Fp16,
+    Int8,
+    Bin,
+}
+
+impl Dtype {
+    /// The name used for this dtype in model signatures.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Dtype::Fp32 => "fp32",
+            Dtype::Fp16 => "fp16",
+            Dtype::Int8 => "int8",
+            Dtype::Bin => "bin",
+        }
+    }
+}
+
+impl FromStr for Dtype {
+    type Err = DiskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fp32" => Ok(Dtype::Fp32),
+            "fp16" => Ok(Dtype::Fp16),
+            "int8" => Ok(Dtype::Int8),
+            "bin" => Ok(Dtype::Bin),
+            _ => Err(DiskError::UnsupportedSignature(format!("unknown dtype '{}'", s))),
+        }
+    }
+}
+
+/// The distance metric used to compare embeddings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    #[default]
+    Cosine,
+    L2,
+    Dot,
+}
+
+impl Metric {
+    /// The name used for this metric in model signatures.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Metric::Cosine => "cosine",
+            Metric::L2 => "l2",
+            Metric::Dot => "dot",
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = DiskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cosine" => Ok(Metric::Cosine),
+            "l2" => Ok(Metric::L2),
+            "dot" => Ok(Metric::Dot),
+            _ => Err(DiskError::UnsupportedSignature(format!("unknown metric '{}'", s))),
+        }
+    }
+}
+
+/// Identifies the embedding model and vector layout of a set of embeddings.
+///
+/// The string form is `{provider}/{model}-{dim}_{dtype}[_{metric}]`, e.g.
+/// `openai/text-embedding-3-small-1536_fp16`. The metric defaults to cosine and
+/// is omitted from the string form in that case. The legacy form without a
+/// `_{dtype}` suffix is read as fp32.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModelSignature {
+    pub provider: String,
+    pub model: String,
+    pub dim: usize,
+    pub dtype: Dtype,
+    pub metric: Metric,
+}
+
+impl FromStr for ModelSignature {
+    type Err = DiskError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with_explicit_metric(s).map(|(signature, _)| signature)
+    }
+}
+
+impl ModelSignature {
+    /// Parses `s` like `from_str`, also returning the metric `s` names, or
+    /// `None` if it has no metric part and the signature defaulted to cosine.
+    pub(crate) fn parse_with_explicit_metric(s: &str) -> Result<(ModelSignature, Option<Metric>), DiskError> {
+        let invalid = |reason: &str| {
+            DiskError::InvalidSignature(format!("'{}': {}", s, reason))
+        };
+        // A well-formed signature can still name a dtype or metric this library lacks.
+        let unsupported = |kind: &str, name: &str| {
+            DiskError::UnsupportedSignature(format!("'{}': unknown {} '{}'", s, kind, name))
+        };
+
+        let (provider, rest) = s
+            .split_once('/')
+            .ok_or_else(|| invalid("expected '{provider}/{model}-{dim}_{dtype}'"))?;
+        if provider.is_empty() || rest.contains('/') {
+            return Err(invalid("provider must be a single non-empty segment"));
+        }
+
+        // The dimension follows the last '-', so model names may contain '-' and '_'.
+        let (model, suffix) = rest
+            .rsplit_once('-')
+            .ok_or_else(|| invalid("missing '-{dim}' after the model name"))?;
+        if model.is_empty() {
+            return Err(invalid("model name is empty"));
+        }
+        let mut parts = suffix.split('_');
+        let dim = parts.next().unwrap_or_default();
+        let dtype = parts.next();
+        let metric = parts.next();
+        if parts.next().is_some() {
+            return Err(invalid("too many '_' separated parts"));
+        }
+        if dim.is_empty() || dim.starts_with('0') || !dim.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid("dimension must be a positive integer"));
+        }
+        let dim = dim.parse().map_err(|_| invalid("dimension is too large"))?;
+
+        // Signatures written before dtypes were introduced have no suffix and are fp32.
+        let dtype = match dtype {
+            Some(dtype) => dtype.parse().map_err(|_| unsupported("dtype", dtype))?,
+            None => Dtype::Fp32,
+        };
+        let explicit_metric = match metric {
+            Some(metric) => Some(metric.parse().map_err(|_| unsupported("metric", metric))?),
+            None => None,
+        };
+
+        let signature = ModelSignature {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            dim,
+            dtype,
+            metric: explicit_metric.unwrap_or_default(),
+        };
+        Ok((signature, explicit_metric))
+    }
+
+    /// Reads a signature written before the `{provider}/{model}-{dim}_{dtype}`
+    /// form was enforced, given the `dim` of the vectors stored under it.
+    ///
+    /// Such signatures were free-form names of fp32 embeddings, e.g.
+    /// `openai/text-embedding-ada-002_fp32`: anything without a `_`, or ending in
+    /// `_fp32`. A trailing `-{dim}` is taken as the dimension, and a name without
+    /// a provider gets the provider `legacy`.
+    pub(crate) fn from_legacy(s: &str, dim: usize) -> Option<ModelSignature> {
+        let (provider, model) = Self::legacy_name(s)?;
+        let model = model.strip_suffix(&format!("-{}", dim)).unwrap_or(model);
+        if dim == 0 || model.is_empty() {
+            return None;
+        }
+        Some(ModelSignature {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            dim,
+            dtype: Dtype::Fp32,
+            metric: Metric::Cosine,
+        })
+    }
+
+    /// Whether `s` has the shape of a signature [`ModelSignature::from_legacy`] reads.
+    pub(crate) fn is_legacy(s: &str) -> bool {
+        Self::legacy_name(s).is_some()
+    }
+
+    /// Splits a legacy signature into its provider and model name.
+    fn legacy_name(s: &str) -> Option<(&str, &str)> {
+        let name = match s.strip_suffix("_fp32") {
+            Some(name) => name,
+            None if !s.contains('_') => s,
+            None => return None,
+        };
+        let (provider, model) = name.split_once('/').unwrap_or(("legacy", name));
+        if provider.is_empty() || model.is_empty() || model.contains('/') {
+            return None;
+        }
+        Some((provider, model))
+    }
+
+    /// The same model and metric stored with a different dtype.
+    pub fn with_dtype(&self, dtype: Dtype) -> ModelSignature {
+        ModelSignature {
+            dtype,
+            ..self.clone()
+        }
+    }
+}
+
+impl fmt::Display for ModelSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}-{}_{}",
+            self.provider,
+            self.model,
+            self.dim,
+            self.dtype.as_str()
+        )?;
+        if self.metric != Metric::Cosine {
+            write!(f, "_{}", self.metric.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+impl ToSql for ModelSignature {
+    fn to_sql(&self) -> RusqliteResult<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+/// A model signature with embeddings stored on a disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureInfo {
+    pub model_signature: String,
+    /// The number of chunks with an embedding under this signature.
+    pub vectors: usize,
+    /// `vectors` as a fraction of all chunks on the disk.
+    pub coverage: f32,
+    /// Whether this is the signature recorded when the disk was created.
+    pub is_default: bool,
+    /// Whether the instance listing it is searching this signature.
+    pub is_active: bool,
+}
+
+/// The outcome of re-embedding a disk's chunks under another model signature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReembedReport {
+    pub model_signature: String,
+    /// The number of chunks embedded by this run.
+    pub embedded: usize,
+    /// Whether this run continued an interrupted one.
+    pub resumed: bool,
+    /// The number of chunks on the disk when the run finished.
+    pub total_chunks: usize,
+    /// Chunks that still have no embedding under `model_signature`, such as
+    /// chunks added while the run was in progress.
+    pub missing: Vec<String>,
+    /// The previous default signature, if the run replaced it. See
+    /// [`ReembedOptions::with_drop_old`].
+    pub dropped_signature: Option<String>,
+    /// The number of embeddings deleted with `dropped_signature`.
+    pub dropped: usize,
+}
+
+/// Options that tune a re-embedding run.
+#[derive(Debug, Clone)]
+pub struct ReembedOptions {
+    /// The number of chunks sent to the embedder at a time.
+    pub batch_size: usize,
+    /// Whether a complete run replaces the default signature.
+    pub drop_old: bool,
+}
+
+impl ReembedOptions {
+    /// Re-embeds `batch_size` chunks at a time and keeps the old signature.
+    pub fn new(batch_size: usize) -> Self {
+        Self { batch_size, drop_old: false }
+    }
+
+    /// Once every chunk has an embedding under the target signature, records
+    /// the target as the default and drops the previous default signature.
+    /// A run that leaves chunks missing keeps the previous default.
+    pub fn with_drop_old(mut self) -> Self {
+        self.drop_old = true;
+        self
+    }
+}
+
+impl ReembedReport {
+    /// Whether every chunk has an embedding under `model_signature`.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_round_trip_through_their_string_form() {
+        for signature in ["openai/text-embedding-3-small-1536_fp16", "local/hashing-512_int8_l2", "x/m-8_bin_dot"] {
+            assert_eq!(signature.parse::<ModelSignature>().unwrap().to_string(), signature);
+        }
+        let explicit_cosine: ModelSignature = "x/m-8_fp32_cosine".parse().unwrap();
+        assert_eq!(explicit_cosine.to_string(), "x/m-8_fp32");
+    }
+
+    #[test]
+    fn signatures_without_a_dtype_are_fp32() {
+        let signature: ModelSignature = "openai/text-embedding-ada-002-1536".parse().unwrap();
+        assert_eq!(signature.model, "text-embedding-ada-002");
+        assert_eq!(signature.dim, 1536);
+        assert_eq!(signature.dtype, Dtype::Fp32);
+        assert_eq!(signature.metric, Metric::Cosine);
+        assert_eq!(signature.to_string(), "openai/text-embedding-ada-002-1536_fp32");
+    }
+
+    #[test]
+    fn unknown_dtypes_and_metrics_are_unsupported() {
+        for signature in ["x/m-8_fp64", "x/m-8_fp32_manhattan"] {
+            let error = signature.parse::<ModelSignature>().unwrap_err();
+            assert!(matches!(error, DiskError::UnsupportedSignature(_)), "{signature} gave {error}");
+        }
+    }
+
+    #[test]
+    fn malformed_signatures_are_invalid() {
+        for signature in ["m-8_fp32", "/m-8_fp32", "a/b/m-8_fp32", "x/m_fp32", "x/-8_fp32", "x/m-08_fp32", "x/m-8_fp32_l2_more"] {
+            let error = signature.parse::<ModelSignature>().unwrap_err();
+            assert!(matches!(error, DiskError::InvalidSignature(_)), "{signature} gave {error}");
+        }
+    }
+
+    #[test]
+    fn model_names_may_contain_underscores() {
+        let signature: ModelSignature = "hf/all_MiniLM-L6-v2-384_int8".parse().unwrap();
+        assert_eq!(signature.model, "all_MiniLM-L6-v2");
+        assert_eq!(signature.dim, 384);
+        assert_eq!(signature.dtype, Dtype::Int8);
+        assert_eq!(signature.to_string(), "hf/all_MiniLM-L6-v2-384_int8");
+
+        let (signature, metric) = ModelSignature::parse_with_explicit_metric("hf/all_MiniLM-L6-v2-384_int8").unwrap();
+        assert_eq!((signature.metric, metric), (Metric::Cosine, None));
+        let (signature, metric) = ModelSignature::parse_with_explicit_metric("hf/all_MiniLM-L6-v2-384_int8_l2").unwrap();
+        assert_eq!(signature.model, "all_MiniLM-L6-v2");
+        assert_eq!((signature.metric, metric), (Metric::L2, Some(Metric::L2)));
+    }
+
+    #[test]
+    fn legacy_signatures_are_read_as_fp32_with_the_stored_dimension() {
+        assert!("openai/text-embedding-ada-002_fp32".parse::<ModelSignature>().is_err());
+        let signature = ModelSignature::from_legacy("openai/text-embedding-ada-002_fp32", 1536).unwrap();
+        assert_eq!(signature.to_string(), "openai/text-embedding-ada-002-1536_fp32");
+
+        let signature = ModelSignature::from_legacy("my-model-8", 8).unwrap();
+        assert_eq!(signature.to_string(), "legacy/my-model-8_fp32");
+
+        for signature in ["x/m_int8", "/m_fp32", "a/b/m_fp32", "x/_fp32"] {
+            assert!(ModelSignature::from_legacy(signature, 8).is_none(), "{signature}");
+        }
+    }
 }
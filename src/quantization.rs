@@ -0,0 +1,261 @@
+use hnsw_rs::prelude::Distance;
+use serde::{Deserialize, Serialize};
+
+use crate::distance::graph_distance;
+use crate::errors::DiskError;
+use crate::models::Metric;
+
+/// Largest magnitude of an int8 code; -128 is unused so the range is symmetric.
+const INT8_MAX: f32 = 127.0;
+
+/// Per-dimension affine parameters mapping int8 codes back to f32 values.
+///
+/// A value `x` in dimension `i` is stored as
+/// `round((x - offset[i]) / scale[i])`, clamped to `[-127, 127]`, and read
+/// back as `code * scale[i] + offset[i]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Int8Calibration {
+    pub scale: Vec<f32>,
+    pub offset: Vec<f32>,
+}
+
+impl Int8Calibration {
+    /// A symmetric `[-1, 1]` range in every dimension, suited to unit-normalized
+    /// embeddings.
+    pub fn unit(dim: usize) -> Self {
+        Self {
+            scale: vec![1.0 / INT8_MAX; dim],
+            offset: vec![0.0; dim],
+        }
+    }
+
+    /// Fits the range of every dimension to the minimum and maximum of `samples`.
+    pub fn from_samples(dim: usize, samples: &[&[f32]]) -> Result<Self, DiskError> {
+        if samples.is_empty() {
+            return Err(DiskError::InvalidData(
+                "At least one sample is needed to calibrate int8 quantization".into(),
+            ));
+        }
+
+        let mut min = vec![f32::INFINITY; dim];
+        let mut max = vec![f32::NEG_INFINITY; dim];
+        for sample in samples {
+            if sample.len() != dim {
+                return Err(DiskError::DimensionMismatch {
+                    expected: dim,
+                    got: sample.len(),
+                });
+            }
+            for (i, &x) in sample.iter().enumerate() {
+                min[i] = min[i].min(x);
+                max[i] = max[i].max(x);
+            }
+        }
+
+        let (scale, offset) = min
+            .iter()
+            .zip(&max)
+            .map(|(&lo, &hi)| {
+                let half_range = (hi - lo) / 2.0;
+                // A constant dimension still needs a non-zero scale.
+                let scale = if half_range > 0.0 { half_range / INT8_MAX } else { 1.0 / INT8_MAX };
+                (scale, lo + half_range)
+            })
+            .unzip();
+        Ok(Self { scale, offset })
+    }
+
+    /// The number of dimensions this calibration covers.
+    pub fn dim(&self) -> usize {
+        self.scale.len()
+    }
+
+    /// Quantizes an f32 vector to int8 codes.
+    pub fn quantize(&self, vector: &[f32]) -> Vec<i8> {
+        vector
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| {
+                ((x - self.offset[i]) / self.scale[i])
+                    .round()
+                    .clamp(-INT8_MAX, INT8_MAX) as i8
+            })
+            .collect()
+    }
+
+    /// Maps int8 codes back to approximate f32 values.
+    pub fn dequantize(&self, codes: &[i8]) -> Vec<f32> {
+        codes
+            .iter()
+            .enumerate()
+            .map(|(i, &q)| self.dequantize_at(i, q))
+            .collect()
+    }
+
+    #[inline]
+    fn dequantize_at(&self, i: usize, q: i8) -> f32 {
+        q as f32 * self.scale[i] + self.offset[i]
+    }
+}
+
+/// Distance between int8 codes under the disk's metric, computed on their
+/// dequantized values.
+///
+/// Dot products use the same positive graph distance as
+/// [`DistInnerProduct`](crate::distance::DistInnerProduct).
+#[derive(Debug, Clone)]
+pub struct DistInt8 {
+    calibration: Int8Calibration,
+    metric: Metric,
+}
+
+impl DistInt8 {
+    pub fn new(calibration: Int8Calibration, metric: Metric) -> Self {
+        Self { calibration, metric }
+    }
+
+    /// The calibration used to quantize and dequantize vectors for this index.
+    pub fn calibration(&self) -> &Int8Calibration {
+        &self.calibration
+    }
+}
+
+impl Distance<i8> for DistInt8 {
+    fn eval(&self, va: &[i8], vb: &[i8]) -> f32 {
+        assert_eq!(va.len(), vb.len());
+        let (mut dot, mut norm_a, mut norm_b, mut sq_diff) = (0f64, 0f64, 0f64, 0f64);
+        for (i, (&qa, &qb)) in va.iter().zip(vb).enumerate() {
+            let a = self.calibration.dequantize_at(i, qa) as f64;
+            let b = self.calibration.dequantize_at(i, qb) as f64;
+            dot += a * b;
+            norm_a += a * a;
+            norm_b += b * b;
+            sq_diff += (a - b) * (a - b);
+        }
+        match self.metric {
+            // Same conventions as `DistCosine`: zero vectors are at distance 0.
+            Metric::Cosine if norm_a > 0.0 && norm_b > 0.0 => {
+                (1.0 - dot / (norm_a * norm_b).sqrt()).max(0.0) as f32
+            }
+            Metric::Cosine => 0.0,
+            Metric::L2 => sq_diff.sqrt() as f32,
+            Metric::Dot => graph_distance(dot as f32),
+        }
+    }
+}
+
+/// Sign-quantizes a vector into bits packed LSB-first, one bit per dimension.
+///
+/// A bit is set when the value is strictly positive.
+pub fn binarize(vector: &[f32]) -> Vec<u8> {
+    let mut bits = vec![0u8; vector.len().div_ceil(8)];
+    for (i, &x) in vector.iter().enumerate() {
+        if x > 0.0 {
+            bits[i / 8] |= 1 << (i % 8);
+        }
+    }
+    bits
+}
+
+/// Hamming distance between bit-packed vectors: the number of differing bits.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DistBinary;
+
+impl Distance<u8> for DistBinary {
+    fn eval(&self, va: &[u8], vb: &[u8]) -> f32 {
+        assert_eq!(va.len(), vb.len());
+        va.iter()
+            .zip(vb)
+            .map(|(a, b)| (a ^ b).count_ones())
+            .sum::<u32>() as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distance::reported_distance;
+
+    fn assert_round_trips(calibration: &Int8Calibration, vector: &[f32]) {
+        let restored = calibration.dequantize(&calibration.quantize(vector));
+        for (i, (x, y)) in vector.iter().zip(&restored).enumerate() {
+            assert!(
+                (x - y).abs() <= calibration.scale[i] / 2.0 + 1e-6,
+                "dimension {i}: {x} came back as {y}"
+            );
+        }
+    }
+
+    #[test]
+    fn unit_calibration_round_trips_within_half_a_step() {
+        let calibration = Int8Calibration::unit(5);
+        assert_round_trips(&calibration, &[-1.0, -0.33, 0.0, 0.5, 1.0]);
+        assert_eq!(calibration.quantize(&[1.0, -1.0, 0.0, 0.0, 0.0]), vec![127, -127, 0, 0, 0]);
+    }
+
+    #[test]
+    fn values_outside_the_calibrated_range_are_clamped() {
+        let calibration = Int8Calibration::unit(2);
+        assert_eq!(calibration.quantize(&[3.0, -3.0]), vec![127, -127]);
+    }
+
+    #[test]
+    fn fitted_calibration_covers_every_sample() {
+        let samples: [&[f32]; 3] = [&[0.0, 10.0, 5.0], &[2.0, -10.0, 5.0], &[1.0, 0.0, 5.0]];
+        let calibration = Int8Calibration::from_samples(3, &samples).unwrap();
+        assert_eq!(calibration.dim(), 3);
+        assert_eq!(calibration.offset, vec![1.0, 0.0, 5.0]);
+        for sample in samples {
+            assert_round_trips(&calibration, sample);
+        }
+        // The extremes map onto the ends of the code range.
+        assert_eq!(calibration.quantize(&[0.0, 10.0, 5.0]), vec![-127, 127, 0]);
+    }
+
+    #[test]
+    fn calibration_needs_samples_of_the_right_dimension() {
+        assert!(Int8Calibration::from_samples(2, &[]).is_err());
+        assert!(matches!(
+            Int8Calibration::from_samples(2, &[&[1.0, 2.0, 3.0]]),
+            Err(DiskError::DimensionMismatch { expected: 2, got: 3 })
+        ));
+    }
+
+    #[test]
+    fn int8_distance_matches_the_float_distance_of_dequantized_vectors() {
+        let calibration = Int8Calibration::unit(4);
+        let a = calibration.quantize(&[0.9, -0.2, 0.1, 0.4]);
+        let b = calibration.quantize(&[-0.3, 0.8, 0.5, 0.0]);
+        let (fa, fb) = (calibration.dequantize(&a), calibration.dequantize(&b));
+        for metric in [Metric::Cosine, Metric::L2] {
+            let distance = DistInt8::new(calibration.clone(), metric);
+            assert!((distance.eval(&a, &b) - reported_distance(metric, &fa, &fb)).abs() < 1e-5);
+            assert_eq!(distance.eval(&a, &a), 0.0);
+        }
+        let dot = DistInt8::new(calibration, Metric::Dot);
+        let expected = graph_distance(fa.iter().zip(&fb).map(|(x, y)| x * y).sum());
+        assert!((dot.eval(&a, &b) - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn binarize_packs_positive_signs_lsb_first() {
+        let vector = [0.5, -1.0, 0.0, 2.0, -0.1, 0.1, 0.0, 0.0, 1.0, -3.0];
+        assert_eq!(binarize(&vector), vec![0b0010_1001, 0b0000_0001]);
+        assert_eq!(binarize(&[]), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn binary_codes_round_trip_through_the_stored_form() {
+        let bits = binarize(&[1.0, -1.0, 1.0, 1.0, -1.0, 1.0, -1.0, -1.0, 1.0]);
+        assert_eq!(crate::codec::decode_bin(&bits, 9), Some(bits.clone()));
+        assert_eq!(crate::codec::decode_bin(&bits, 17), None);
+    }
+
+    #[test]
+    fn hamming_distance_counts_differing_bits() {
+        let a = binarize(&[1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0, 1.0, 1.0]);
+        let b = binarize(&[1.0, -1.0, -1.0, 1.0, 1.0, 1.0, 1.0, 1.0, -1.0]);
+        assert_eq!(DistBinary.eval(&a, &b), 3.0);
+        assert_eq!(DistBinary.eval(&a, &a), 0.0);
+    }
+}
@@ -0,0 +1,630 @@
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{Connection, OpenFlags};
+use serde_json::Value as Json;
+
+use crate::embedding::{Embedder, IdfWeights};
+use crate::errors::DiskError;
+use crate::models::{
+    Chunk, Document, HybridWeights, IndexConfig, ModelSignature, NewChunk, QueryVector,
+    ReembedOptions, ReembedReport, SearchOptions, SearchResult, SignatureInfo,
+};
+use crate::id_map::IdMap;
+use crate::{IdentityDisk, SearchIndex};
+
+/// The SQLite connection behind an [`IdentityDisk`]: its own, or one borrowed
+/// from a [`SharedDisk`]'s pool for the duration of a read.
+pub(crate) enum DiskConnection {
+    Owned(Connection),
+    Pooled(PooledConnection<SqliteConnectionManager>),
+}
+
+impl Deref for DiskConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        match self {
+            DiskConnection::Owned(conn) => conn,
+            DiskConnection::Pooled(conn) => conn,
+        }
+    }
+}
+
+impl DerefMut for DiskConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        match self {
+            DiskConnection::Owned(conn) => conn,
+            DiskConnection::Pooled(conn) => conn,
+        }
+    }
+}
+
+/// Connection settings of a [`SharedDisk`].
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    /// The most read connections open at once; further readers wait for one.
+    pub max_connections: u32,
+    /// How long a connection waits for another one's lock before failing
+    /// with `SQLITE_BUSY`.
+    pub busy_timeout: Duration,
+}
+
+impl PoolConfig {
+    pub const DEFAULT_MAX_CONNECTIONS: u32 = 4;
+    pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: Self::DEFAULT_MAX_CONNECTIONS,
+            busy_timeout: Self::DEFAULT_BUSY_TIMEOUT,
+        }
+    }
+}
+
+/// What readers need from the writer to search the same index.
+struct ReaderState {
+    index: Arc<RwLock<SearchIndex>>,
+    id_to_chunk_id: Arc<RwLock<IdMap>>,
+    live_vectors: Arc<AtomicUsize>,
+    model_signature: ModelSignature,
+    exact_search_threshold: usize,
+    index_config: IndexConfig,
+}
+
+impl ReaderState {
+    fn of(disk: &IdentityDisk) -> Self {
+        Self {
+            index: Arc::clone(&disk.index),
+            id_to_chunk_id: Arc::clone(&disk.id_to_chunk_id),
+            live_vectors: Arc::clone(&disk.live_vectors),
+            model_signature: disk.model_signature.clone(),
+            exact_search_threshold: disk.exact_search_threshold,
+            index_config: disk.index_config,
+        }
+    }
+}
+
+struct SharedInner {
+    /// Owns the only connection that writes; holding the lock serializes writes.
+    writer: Mutex<IdentityDisk>,
+    /// Read-only connections for searches and other reads.
+    pool: Pool<SqliteConnectionManager>,
+    /// Held for reading by every read and for writing while the signature or
+    /// search settings change, so no read sees them half-changed.
+    reader_state: RwLock<ReaderState>,
+}
+
+/// A cloneable, `Send + Sync` handle to an Identity Disk for sharing between
+/// threads.
+///
+/// Reads run concurrently on a small pool of read-only connections and share
+/// the writer's HNSW index under its read lock. Writes go through a single
+/// connection and are serialized; they do not block reads except for the
+/// moment a new vector is inserted into the index. The disk is switched to WAL
+/// mode so readers keep seeing the last committed state while a write is in
+/// progress.
+#[derive(Clone)]
+pub struct SharedDisk {
+    inner: Arc<SharedInner>,
+}
+
+impl SharedDisk {
+    /// Opens a disk for sharing with the default [`PoolConfig`]. See
+    /// [`IdentityDisk::open`].
+    pub fn open<P: AsRef<Path>>(path: P, model_signature: &str) -> Result<Self, DiskError> {
+        Self::new(
+            IdentityDisk::open(path, model_signature)?,
+            PoolConfig::default(),
+        )
+    }
+
+    /// Opens a disk for sharing with the model signature recorded when it was
+    /// created. See [`IdentityDisk::open_default`].
+    pub fn open_default<P: AsRef<Path>>(path: P) -> Result<Self, DiskError> {
+        Self::new(IdentityDisk::open_default(path)?, PoolConfig::default())
+    }
+
+    /// Shares an open disk, which becomes the writer.
+    ///
+    /// Fails with `DiskError::InvalidData` for disks opened with
+    /// `IdentityDisk::open_in_memory`, whose contents other connections cannot
+    /// see.
+    pub fn new(disk: IdentityDisk, pool_config: PoolConfig) -> Result<Self, DiskError> {
+        let path = match disk.conn.path() {
+            Some(path) if !path.is_empty() => path.to_string(),
+            _ => {
+                return Err(DiskError::InvalidData(
+                    "In-memory disks cannot be shared".into(),
+                ))
+            }
+        };
+
+        let journal_mode: String = disk
+            .conn
+            .query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+        if !journal_mode.eq_ignore_ascii_case("wal") {
+            return Err(DiskError::InvalidData(format!(
+                "Could not switch the disk to WAL mode (journal mode is '{}')",
+                journal_mode
+            )));
+        }
+        disk.conn.busy_timeout(pool_config.busy_timeout)?;
+
+        let busy_timeout = pool_config.busy_timeout;
+        let manager = SqliteConnectionManager::file(path)
+            .with_flags(OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+            .with_init(move |conn| conn.busy_timeout(busy_timeout));
+        let pool = Pool::builder()
+            .max_size(pool_config.max_connections)
+            .min_idle(Some(0))
+            .build(manager)?;
+
+        let reader_state = RwLock::new(ReaderState::of(&disk));
+        Ok(Self {
+            inner: Arc::new(SharedInner {
+                writer: Mutex::new(disk),
+                pool,
+                reader_state,
+            }),
+        })
+    }
+
+    /// Runs `f` against a read-only view of the disk on a pooled connection.
+    ///
+    /// Any number of reads run at once. Writes made through `f`'s disk fail,
+    /// as its connection is read-only.
+    pub fn read<R>(
+        &self,
+        f: impl FnOnce(&IdentityDisk) -> Result<R, DiskError>,
+    ) -> Result<R, DiskError> {
+        let state = self.inner.reader_state.read()?;
+        let reader = IdentityDisk {
+            conn: DiskConnection::Pooled(self.inner.pool.get()?),
+            index: Arc::clone(&state.index),
+            id_to_chunk_id: Arc::clone(&state.id_to_chunk_id),
+            live_vectors: Arc::clone(&state.live_vectors),
+            model_signature: state.model_signature.clone(),
+            exact_search_threshold: state.exact_search_threshold,
+            index_config: state.index_config,
+            // Readers never reload or persist the index.
+            index_data_version: AtomicI64::new(0),
+        };
+        f(&reader)
+    }
+
+    /// Runs `f` against the writer, after any other write has finished.
+    fn write<R>(
+        &self,
+        f: impl FnOnce(&mut IdentityDisk) -> Result<R, DiskError>,
+    ) -> Result<R, DiskError> {
+        let mut writer = self.inner.writer.lock()?;
+        f(&mut writer)
+    }
+
+    /// Like `write`, but also waits for running reads and publishes the
+    /// writer's signature and search settings before reads resume.
+    fn update_reader_state<R>(
+        &self,
+        f: impl FnOnce(&mut IdentityDisk) -> Result<R, DiskError>,
+    ) -> Result<R, DiskError> {
+        let mut writer = self.inner.writer.lock()?;
+        let mut state = self.inner.reader_state.write()?;
+        let result = f(&mut writer);
+        *state = ReaderState::of(&writer);
+        result
+    }
+
+    /// See [`IdentityDisk::model_signature`].
+    pub fn model_signature(&self) -> Result<ModelSignature, DiskError> {
+        Ok(self.inner.reader_state.read()?.model_signature.clone())
+    }
+
+    /// See [`IdentityDisk::index_config`].
+    pub fn index_config(&self) -> Result<IndexConfig, DiskError> {
+        Ok(self.inner.reader_state.read()?.index_config)
+    }
+
+    // --- Reads ---
+
+    /// See [`IdentityDisk::search`].
+    pub fn search(
+        &self,
+        query_vector: QueryVector,
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>, DiskError> {
+        self.read(|disk| disk.search(query_vector, top_k))
+    }
+
+    /// See [`IdentityDisk::search_with_options`].
+    pub fn search_with_options(
+        &self,
+        query_vector: QueryVector,
+        top_k: usize,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResult>, DiskError> {
+        self.read(|disk| disk.search_with_options(query_vector, top_k, options))
+    }
+
+    /// See [`IdentityDisk::keyword_search`].
+    pub fn keyword_search(
+        &self,
+        query: &str,
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>, DiskError> {
+        self.read(|disk| disk.keyword_search(query, top_k))
+    }
+
+    /// See [`IdentityDisk::hybrid_search`].
+    pub fn hybrid_search(
+        &self,
+        text: &str,
+        vector: QueryVector,
+        top_k: usize,
+        weights: &HybridWeights,
+    ) -> Result<Vec<SearchResult>, DiskError> {
+        self.read(|disk| disk.hybrid_search(text, vector, top_k, weights))
+    }
+
+    /// See [`IdentityDisk::get_chunks`].
+    pub fn get_chunks(&self) -> Result<Vec<Chunk>, DiskError> {
+        self.read(|disk| disk.get_chunks())
+    }
+
+    /// See [`IdentityDisk::list_documents`].
+    pub fn list_documents(&self) -> Result<Vec<Document>, DiskError> {
+        self.read(|disk| disk.list_documents())
+    }
+
+    /// See [`IdentityDisk::get_document`].
+    pub fn get_document(&self, document_id: &str) -> Result<Document, DiskError> {
+        self.read(|disk| disk.get_document(document_id))
+    }
+
+    /// See [`IdentityDisk::get_document_chunks`].
+    pub fn get_document_chunks(&self, document_id: &str) -> Result<Vec<Chunk>, DiskError> {
+        self.read(|disk| disk.get_document_chunks(document_id))
+    }
+
+    /// See [`IdentityDisk::list_model_signatures`].
+    pub fn list_model_signatures(&self) -> Result<Vec<SignatureInfo>, DiskError> {
+        self.read(|disk| disk.list_model_signatures())
+    }
+
+    /// See [`IdentityDisk::get_manifest`].
+    pub fn get_manifest(&self) -> Result<BTreeMap<String, Json>, DiskError> {
+        self.read(|disk| disk.get_manifest())
+    }
+
+    // --- Writes ---
+
+    /// See [`IdentityDisk::add_chunk`].
+    pub fn add_chunk(
+        &self,
+        content: &str,
+        embedding: QueryVector,
+        metadata: Option<Json>,
+    ) -> Result<String, DiskError> {
+        self.write(|disk| disk.add_chunk(content, embedding, metadata))
+    }
+
+    /// See [`IdentityDisk::add_chunks`].
+    pub fn add_chunks(&self, chunks: &[NewChunk]) -> Result<Vec<String>, DiskError> {
+        self.write(|disk| disk.add_chunks(chunks))
+    }
+
+    /// See [`IdentityDisk::add_document`].
+    pub fn add_document(
+        &self,
+        uri: &str,
+        title: Option<&str>,
+        content: &str,
+        chunks: &[NewChunk],
+    ) -> Result<String, DiskError> {
+        self.write(|disk| disk.add_document(uri, title, content, chunks))
+    }
+
+    /// See [`IdentityDisk::update_chunk`].
+    pub fn update_chunk(
+        &self,
+        chunk_id: &str,
+        content: &str,
+        embedding: QueryVector,
+        metadata: Option<Json>,
+    ) -> Result<(), DiskError> {
+        self.write(|disk| disk.update_chunk(chunk_id, content, embedding, metadata))
+    }
+
+    /// See [`IdentityDisk::update_chunk_metadata`].
+    pub fn update_chunk_metadata(
+        &self,
+        chunk_id: &str,
+        new_metadata: Json,
+    ) -> Result<(), DiskError> {
+        self.write(|disk| disk.update_chunk_metadata(chunk_id, new_metadata))
+    }
+
+    /// See [`IdentityDisk::delete_chunk`].
+    pub fn delete_chunk(&self, chunk_id: &str) -> Result<(), DiskError> {
+        self.write(|disk| disk.delete_chunk(chunk_id))
+    }
+
+    /// See [`IdentityDisk::delete_chunks`].
+    pub fn delete_chunks<F>(&self, filter: F) -> Result<usize, DiskError>
+    where
+        F: Fn(&Chunk) -> bool,
+    {
+        self.write(|disk| disk.delete_chunks(filter))
+    }
+
+    /// See [`IdentityDisk::delete_document`].
+    pub fn delete_document(&self, document_id: &str) -> Result<usize, DiskError> {
+        self.write(|disk| disk.delete_document(document_id))
+    }
+
+    /// See [`IdentityDisk::add_embedding`].
+    pub fn add_embedding(
+        &self,
+        chunk_id: &str,
+        model_signature: &str,
+        embedding: QueryVector,
+    ) -> Result<(), DiskError> {
+        self.write(|disk| disk.add_embedding(chunk_id, model_signature, embedding))
+    }
+
+    /// See [`IdentityDisk::reembed`]. Batches are embedded without holding
+    /// the writer, so other writes only wait while a batch is stored.
+    pub fn reembed(
+        &self,
+        target_signature: &str,
+        embedder: &dyn Embedder,
+        batch_size: usize,
+    ) -> Result<ReembedReport, DiskError> {
+        self.reembed_with_options(target_signature, embedder, &ReembedOptions::new(batch_size))
+    }
+
+    /// See [`IdentityDisk::reembed_with_options`] and [`reembed`](Self::reembed).
+    pub fn reembed_with_options(
+        &self,
+        target_signature: &str,
+        embedder: &dyn Embedder,
+        options: &ReembedOptions,
+    ) -> Result<ReembedReport, DiskError> {
+        self.reembed_until_cancelled(target_signature, embedder, options, &AtomicBool::new(false))
+    }
+
+    /// `reembed_with_options`, stopping with `DiskError::Cancelled` before the
+    /// next batch once `cancelled` is set. The stored progress lets a later run
+    /// resume where this one stopped.
+    pub(crate) fn reembed_until_cancelled(
+        &self,
+        target_signature: &str,
+        embedder: &dyn Embedder,
+        options: &ReembedOptions,
+        cancelled: &AtomicBool,
+    ) -> Result<ReembedReport, DiskError> {
+        let mut run = self.read(|disk| disk.begin_reembed(target_signature, embedder, options))?;
+        loop {
+            if cancelled.load(Ordering::Acquire) {
+                return Err(DiskError::Cancelled);
+            }
+            let batch = self.read(|disk| disk.next_reembed_batch(&run))?;
+            if batch.is_empty() {
+                break;
+            }
+            let embeddings = IdentityDisk::embed_reembed_batch(embedder, &batch)?;
+            self.write(|disk| disk.write_reembed_batch(&mut run, &batch, &embeddings))?;
+        }
+        self.write(|disk| disk.finish_reembed(run))
+    }
+
+    /// See [`IdentityDisk::persist_index`].
+    pub fn persist_index(&self) -> Result<(), DiskError> {
+        self.write(|disk| disk.persist_index())
+    }
+
+    /// See [`IdentityDisk::calibrate_int8`].
+    pub fn calibrate_int8(&self, samples: &[&[f32]]) -> Result<(), DiskError> {
+        self.write(|disk| disk.calibrate_int8(samples))
+    }
+
+    /// See [`IdentityDisk::set_hashing_idf`].
+    pub fn set_hashing_idf(&self, idf: &IdfWeights) -> Result<(), DiskError> {
+        self.write(|disk| disk.set_hashing_idf(idf))
+    }
+
+    /// See [`IdentityDisk::set_manifest_value`].
+    pub fn set_manifest_value(&self, key: &str, value: Json) -> Result<(), DiskError> {
+        self.write(|disk| disk.set_manifest_value(key, value))
+    }
+
+    /// See [`IdentityDisk::remove_manifest_value`].
+    pub fn remove_manifest_value(&self, key: &str) -> Result<(), DiskError> {
+        self.write(|disk| disk.remove_manifest_value(key))
+    }
+
+    /// See [`IdentityDisk::set_default_model_signature`].
+    pub fn set_default_model_signature(&self, model_signature: &str) -> Result<(), DiskError> {
+        self.write(|disk| disk.set_default_model_signature(model_signature))
+    }
+
+    /// See [`IdentityDisk::drop_model_signature`].
+    pub fn drop_model_signature(&self, model_signature: &str) -> Result<usize, DiskError> {
+        self.write(|disk| disk.drop_model_signature(model_signature))
+    }
+
+    /// See [`IdentityDisk::switch_model_signature`]. Waits for running reads,
+    /// and reads started meanwhile search the new signature.
+    pub fn switch_model_signature(&self, model_signature: &str) -> Result<(), DiskError> {
+        self.update_reader_state(|disk| disk.switch_model_signature(model_signature))
+    }
+
+    /// See [`IdentityDisk::set_exact_search_threshold`].
+    pub fn set_exact_search_threshold(&self, max_vectors: usize) -> Result<(), DiskError> {
+        self.update_reader_state(|disk| {
+            disk.set_exact_search_threshold(max_vectors);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+
+    use super::*;
+    use crate::embedding::HashingEmbedder;
+    use crate::models::{SearchMode, SearchOptions};
+
+    #[test]
+    fn readers_see_every_committed_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let disk = IdentityDisk::create(dir.path().join("disk.idz"), "local/hashing-32_fp32").unwrap();
+        let disk = SharedDisk::new(disk, PoolConfig::default()).unwrap();
+        let embedder = HashingEmbedder::new(32).unwrap();
+        let texts: Vec<String> = (0..40).map(|i| format!("entry{} written by the writer", i)).collect();
+        let embeddings = embedder.embed(&texts.iter().map(String::as_str).collect::<Vec<_>>()).unwrap();
+        // How many chunks the writer has committed so far.
+        let written = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for (text, embedding) in texts.iter().zip(&embeddings) {
+                    disk.add_chunk(text, QueryVector::F32(embedding), None).unwrap();
+                    written.fetch_add(1, Ordering::SeqCst);
+                }
+            });
+            for mode in [SearchMode::Exact, SearchMode::Approximate, SearchMode::Auto] {
+                let (disk, texts, embeddings, written) = (disk.clone(), &texts, &embeddings, &written);
+                scope.spawn(move || {
+                    let options = SearchOptions::default().with_mode(mode);
+                    loop {
+                        let n = written.load(Ordering::SeqCst);
+                        if n > 0 {
+                            // The latest write is visible to vector, keyword and plain reads.
+                            let latest = QueryVector::F32(&embeddings[n - 1]);
+                            let results = disk.search_with_options(latest, 1, &options).unwrap();
+                            // HNSW may miss a node, so only exact scans must find it.
+                            if mode == SearchMode::Approximate {
+                                assert!(results.iter().all(|r| texts.contains(&r.chunk.content)));
+                            } else {
+                                assert_eq!(results[0].chunk.content, texts[n - 1], "{mode:?}");
+                            }
+                            let keyword = format!("entry{}", n - 1);
+                            assert_eq!(disk.keyword_search(&keyword, 1).unwrap()[0].chunk.content, texts[n - 1]);
+                            assert!(disk.get_chunks().unwrap().len() >= n);
+                        }
+                        if n == texts.len() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(disk.get_chunks().unwrap().len(), texts.len());
+
+        // Readers keep searching while the writer updates and deletes chunks
+        // between their vector scans and row lookups.
+        let chunk_ids: Vec<String> = disk.get_chunks().unwrap().into_iter().map(|c| c.chunk_id).collect();
+        let done = AtomicBool::new(false);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for (i, chunk_id) in chunk_ids.iter().enumerate() {
+                    if i % 2 == 0 {
+                        let embedding = QueryVector::F32(&embeddings[(i + 1) % embeddings.len()]);
+                        disk.update_chunk(chunk_id, &format!("updated {}", i), embedding, None).unwrap();
+                    } else {
+                        disk.delete_chunk(chunk_id).unwrap();
+                    }
+                }
+                done.store(true, Ordering::SeqCst);
+            });
+            for mode in [SearchMode::Exact, SearchMode::Auto] {
+                let (disk, embeddings, done) = (disk.clone(), &embeddings, &done);
+                scope.spawn(move || {
+                    let options = SearchOptions::default().with_mode(mode);
+                    while !done.load(Ordering::SeqCst) {
+                        for embedding in embeddings {
+                            let results = disk.search_with_options(QueryVector::F32(embedding), 5, &options);
+                            assert!(results.is_ok(), "{mode:?}: {results:?}");
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(disk.get_chunks().unwrap().len(), texts.len() / 2);
+    }
+
+    /// Hashing embeddings whose first call waits until the test lets it resume.
+    struct PausingEmbedder {
+        inner: HashingEmbedder,
+        started: Mutex<mpsc::Sender<()>>,
+        resume: Mutex<mpsc::Receiver<()>>,
+        // Whether the first call was resumed rather than timing out.
+        resumed: AtomicBool,
+        calls: AtomicUsize,
+    }
+
+    impl Embedder for PausingEmbedder {
+        fn model(&self) -> &str {
+            self.inner.model()
+        }
+
+        fn dim(&self) -> usize {
+            self.inner.dim()
+        }
+
+        fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, DiskError> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                self.started.lock().unwrap().send(()).unwrap();
+                let resumed = self.resume.lock().unwrap().recv_timeout(Duration::from_secs(5)).is_ok();
+                self.resumed.store(resumed, Ordering::SeqCst);
+            }
+            self.inner.embed(texts)
+        }
+    }
+
+    #[test]
+    fn writes_go_through_while_reembed_waits_on_the_embedder() {
+        let dir = tempfile::tempdir().unwrap();
+        let disk = IdentityDisk::create(dir.path().join("disk.idz"), "local/hashing-8_fp32").unwrap();
+        let disk = SharedDisk::new(disk, PoolConfig::default()).unwrap();
+        let hashing = HashingEmbedder::new(8).unwrap();
+        for i in 0..6 {
+            let content = format!("chunk number {}", i);
+            disk.add_chunk(&content, QueryVector::F32(&hashing.embed(&[&content]).unwrap()[0]), None).unwrap();
+        }
+        let (started, on_started) = mpsc::channel();
+        let (resume, on_resume) = mpsc::channel();
+        let embedder = PausingEmbedder {
+            inner: hashing.clone(),
+            started: Mutex::new(started),
+            resume: Mutex::new(on_resume),
+            resumed: AtomicBool::new(false),
+            calls: AtomicUsize::new(0),
+        };
+
+        let report = thread::scope(|scope| {
+            let run = scope.spawn(|| disk.reembed("local/hashing-8_fp16", &embedder, 2));
+            on_started.recv().unwrap();
+            // The run is embedding its first batch; the writer must be free.
+            let content = "written during the run";
+            disk.add_chunk(content, QueryVector::F32(&hashing.embed(&[content]).unwrap()[0]), None).unwrap();
+            resume.send(()).unwrap();
+            run.join().unwrap().unwrap()
+        });
+        assert!(embedder.resumed.load(Ordering::SeqCst));
+        assert_eq!(report.total_chunks, 7);
+        assert_eq!(report.embedded + report.missing.len(), 7);
+    }
+}